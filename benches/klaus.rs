@@ -0,0 +1,132 @@
+use std::{ops::RangeInclusive, time::Instant};
+
+use emulate6502::{
+    bus::{Bus, Device, TickReturn},
+    cpu::cpu::CPU,
+    devices::mem::Mem,
+};
+
+// Where Klaus Dormann's 6502_functional_test traps once every test has passed
+const SUCCESS: u16 = 0x3469;
+
+// Runs the functional test on a couple of bus layouts and prints how fast each went, with
+// `cargo bench`. Every instruction reads and writes through the bus, so this is mostly a
+// measure of how quickly it finds the device for an address. The BBC layout is also run
+// through a search of the devices in the order they were registered, which is how the bus
+// found them before it had a page table. The best of RUNS is shown
+const RUNS: usize = 3;
+
+fn main() {
+    best_of("64K of RAM as one device", flat);
+    best_of("11 devices laid out like a BBC Micro", split);
+    best_of("The same 11 devices, searched in order", linear);
+}
+
+fn flat() -> Bus {
+    let mut bus = Bus::default();
+    bus.register(0..=0xFFFF, Box::new(Mem::default(1024 * 64)));
+    bus
+}
+
+// Like the BBC Micro, where the RAM and ROMs come after the small I/O devices in page $FE.
+// They're all memory here so the test still passes
+fn bbc_layout() -> Vec<(RangeInclusive<u16>, Mem)> {
+    [
+        0xFE00..=0xFE07,
+        0xFE08..=0xFE1F,
+        0xFE20..=0xFE2F,
+        0xFE30..=0xFE30,
+        0xFE31..=0xFE3F,
+        0xFE40..=0xFE4F,
+        0xFE50..=0xFEFF,
+        0x0000..=0x7FFF,
+        0x8000..=0xBFFF,
+        0xC000..=0xFDFF,
+        0xFF00..=0xFFFF,
+    ]
+    .into_iter()
+    .map(|range| {
+        let len = (range.end() - range.start()) as usize + 1;
+        (range, Mem::default(len))
+    })
+    .collect()
+}
+
+fn split() -> Bus {
+    let mut bus = Bus::default();
+    for (range, mem) in bbc_layout() {
+        bus.register(range, Box::new(mem));
+    }
+    bus
+}
+
+// The layout behind one device covering everything, which looks for the device for each
+// access the way the bus used to. Going through the page table to reach it costs one more
+// call per access, so if anything this flatters the page table less than the old bus would
+fn linear() -> Bus {
+    let mut bus = Bus::default();
+    let devices = bbc_layout().into_iter().map(|(range, mem)| (range, Box::new(mem) as Box<dyn Device>)).collect();
+    bus.register(0..=0xFFFF, Box::new(Linear { devices }));
+    bus
+}
+
+struct Linear {
+    devices: Vec<(RangeInclusive<u16>, Box<dyn Device>)>,
+}
+
+impl Device for Linear {
+    fn read(&mut self, addr: u16) -> u8 {
+        for (range, device) in &mut self.devices {
+            if range.contains(&addr) {
+                return device.read(addr - range.start());
+            }
+        }
+        0
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        for (range, device) in &mut self.devices {
+            if range.contains(&addr) {
+                device.write(addr - range.start(), value);
+                return;
+            }
+        }
+    }
+
+    fn tick(&mut self) -> TickReturn {
+        TickReturn::NONE
+    }
+}
+
+fn best_of(name: &str, build: fn() -> Bus) {
+    let runs: Vec<_> = (0..RUNS).map(|_| run(build())).collect();
+    let Some((result, seconds, instructions, cycles)) = runs.into_iter().min_by(|a, b| a.1.total_cmp(&b.1)) else {
+        return;
+    };
+    println!("{}: {} in {:.2}s, {} instructions, {:.1} MHz", name, result, seconds, instructions, cycles as f64 / seconds / 1_000_000.0);
+}
+
+// Whether it passed, the seconds it took, and the instructions and cycles it ran
+fn run(mut bus: Bus) -> (String, f64, u64, u64) {
+    let mut cpu = CPU::default();
+    if !cpu.load_rom(&mut bus, "roms/6502_functional_test.bin", 0x0000) {
+        return ("couldn't load roms/6502_functional_test.bin".to_string(), 0.0, 0, 0);
+    }
+    cpu.reset(&mut bus);
+    cpu.pc = 0x0400;
+
+    let start = Instant::now();
+    let mut instructions: u64 = 0;
+    // The test ends in a jump to itself, passing or failing
+    loop {
+        let pc = cpu.pc;
+        cpu.step(&mut bus, 1);
+        instructions += 1;
+        if cpu.pc == pc {
+            break;
+        }
+    }
+    let seconds = start.elapsed().as_secs_f64();
+    let result = if cpu.pc == SUCCESS { "passed".to_string() } else { format!("failed at ${:04X}", cpu.pc) };
+    (result, seconds, instructions, cpu.read_cycles())
+}
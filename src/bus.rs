@@ -1,64 +1,547 @@
-use std::ops::RangeInclusive;
+use std::{any::Any, marker::PhantomData, ops::RangeInclusive};
 
+use crate::{
+    debug::coverage::{CODE, Coverage, DATA, OPCODE, WRITTEN},
+    snapshot::{StateReader, StateWriter},
+};
+
+// Returned by a device every tick, IRQ and NMI mean the device is holding that line asserted
+// for this tick, returning NONE again releases it
 pub enum TickReturn{
     SHUTDOWN,
     IRQ,
+    NMI,
     NONE
 }
 
-pub trait Device {
+// Any so a device can be borrowed back from the bus as its own type through a `DeviceHandle`
+pub trait Device: Any {
     fn read(&mut self, addr: u16) -> u8;
     fn write(&mut self, addr: u16, value: u8);
     fn tick(&mut self) -> TickReturn;
+
+    // Read without any side effects, for debuggers and disassemblers looking at memory.
+    // Devices whose reads change their state should override this
+    fn peek(&mut self, addr: u16) -> u8 {
+        self.read(addr)
+    }
+
+    // Write for a debugger changing memory or a register, setting it to `value` rather than
+    // doing whatever a write from the CPU would set off, like acknowledging an interrupt
+    fn poke(&mut self, addr: u16, value: u8) {
+        self.write(addr, value)
+    }
+
+    // Writes whatever the device needs to carry on from where it is, for snapshots. ROMs and
+    // other devices without any state can leave these out
+    fn save_state(&self, _state: &mut StateWriter) {}
+
+    // Reads back what `save_state` wrote
+    fn load_state(&mut self, _state: &mut StateReader) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+// An access to a watched range, recorded for a debugger to pick up
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WatchHit {
+    pub id: usize,
+    pub addr: u16,
+    pub value: u8,
+    pub write: bool,
+}
+
+// What the CPU did with a byte it read. Coverage counts opcodes and operands as code, other
+// reads as data, and leaves out the reads the CPU makes on a cycle and throws away
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReadKind {
+    Opcode,
+    Operand,
+    Data,
+    Dummy,
+}
+
+// What a read from an address no device answers gives back. Nothing drives the data bus, so on
+// real machines it still holds the last value that was on it, which some software relies on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnmappedReads {
+    OpenBus,
+    Value(u8),
+}
+
+// How many times the CPU read and wrote a page where nothing answered
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UnmappedAccesses {
+    pub reads: u64,
+    pub writes: u64,
+}
+
+struct Watch {
+    id: usize,
+    range: RangeInclusive<u16>,
+    reads: bool,
+    writes: bool,
+}
+
+// Where a device sits on the bus. The device is handed the offset from the start of `range`
+// ANDed with `mask`, so one smaller than its range repeats through it like a partly decoded
+// chip. Where mappings overlap the one with the highest `priority` answers, so a ROM can be
+// laid over RAM, but two overlapping at the same priority is an error
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mapping {
+    pub range: RangeInclusive<u16>,
+    pub mask: u16,
+    pub priority: u8,
+}
+
+impl Mapping {
+    pub fn new(range: RangeInclusive<u16>) -> Self {
+        Self { range, mask: 0xFFFF, priority: 0 }
+    }
+
+    // `range` repeating the first `mask + 1` bytes of the device, like 2K of RAM mirrored
+    // through $0000-$1FFF with a mask of $07FF
+    pub fn mirrored(range: RangeInclusive<u16>, mask: u16) -> Self {
+        Self { range, mask, priority: 0 }
+    }
+
+    fn offset(&self, addr: u16) -> u16 {
+        (addr - *self.range.start()) & self.mask
+    }
+
+    fn overlaps(&self, other: &Mapping) -> bool {
+        self.range.start() <= other.range.end() && other.range.start() <= self.range.end()
+    }
+}
+
+// Given back by `register` and `map` to reach the device again, as the type it was
+// registered as. It stays valid until the device is unregistered
+pub struct DeviceHandle<T> {
+    id: usize,
+    device: PhantomData<T>,
+}
+
+// Derived Clone and Copy would need T to be too
+impl<T> Clone for DeviceHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for DeviceHandle<T> {}
+
+struct Slot {
+    id: usize,
+    mapping: Mapping,
+    device: Box<dyn Device>,
+}
+
+// How the addresses in one page are decoded
+enum Page {
+    Unmapped,
+    // One device covers the whole page
+    Device(usize),
+    // Several devices, or one that only covers part of it, so every address gets an entry
+    Split(Box<[Option<usize>; 256]>),
 }
 
 pub struct Bus {
-    devices: Vec<(RangeInclusive<u16>, Box<dyn Device>)>,
+    devices: Vec<Slot>,
+    next_id: usize,
+    // Which device answers each address, worked out again whenever one is mapped so an access
+    // doesn't search the devices
+    pages: Box<[Page; 256]>,
+
+    irq: bool,
+    nmi: bool,
+    shutdown: bool,
+
+    // The last value read or written, and what unmapped reads give back
+    data_bus: u8,
+    unmapped_reads: UnmappedReads,
+    // Accesses to addresses nothing answers, for each page, to help find a wrong memory map
+    unmapped: Box<[UnmappedAccesses; 256]>,
+
+    // Ranges a debugger is watching and the last access to one since it last looked
+    watches: Vec<Watch>,
+    watch_hit: Option<WatchHit>,
+
+    // Records how every byte has been used while it's set
+    coverage: Option<Coverage>,
 }
 
 impl Bus {
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> Self {
-        Self { devices: vec![] }
+        Self {
+            devices: vec![],
+            next_id: 0,
+            pages: Box::new(std::array::from_fn(|_| Page::Unmapped)),
+            irq: false,
+            nmi: false,
+            shutdown: false,
+            data_bus: 0,
+            unmapped_reads: UnmappedReads::OpenBus,
+            unmapped: Box::new([UnmappedAccesses::default(); 256]),
+            watches: vec![],
+            watch_hit: None,
+            coverage: None,
+        }
+    }
+
+    // State of the wired-OR IRQ line after the last tick
+    pub fn irq(&self) -> bool {
+        self.irq
+    }
+
+    // State of the wired-OR NMI line after the last tick
+    pub fn nmi(&self) -> bool {
+        self.nmi
+    }
+
+    // Set once a device has returned SHUTDOWN from `tick`, so a CPU ticking the bus itself
+    // can pass it on
+    pub fn shutdown_requested(&self) -> bool {
+        self.shutdown
+    }
+
+    // Maps `device` over the whole of `range`. Panics if that overlaps another device at the
+    // same priority, `map` gives back the error instead
+    pub fn register<T: Device>(&mut self, range: RangeInclusive<u16>, device: Box<T>) -> DeviceHandle<T> {
+        self.map(Mapping::new(range), device).unwrap_or_else(|message| panic!("{}", message))
+    }
+
+    pub fn map<T: Device>(&mut self, mapping: Mapping, device: Box<T>) -> Result<DeviceHandle<T>, String> {
+        self.check_mapping(&mapping, None)?;
+        let id = self.next_id;
+        self.next_id += 1;
+        self.devices.push(Slot { id, mapping, device });
+        self.build_pages();
+        Ok(DeviceHandle { id, device: PhantomData })
+    }
+
+    // Takes the device off the bus and gives it back, None if it had already gone
+    pub fn unregister<T: Device>(&mut self, handle: DeviceHandle<T>) -> Option<Box<T>> {
+        let index = self.index_of(handle)?;
+        let slot = self.devices.remove(index);
+        self.build_pages();
+        let device: Box<dyn Any> = slot.device;
+        device.downcast().ok()
+    }
+
+    // Moves the device somewhere else, keeping its state
+    pub fn remap<T: Device>(&mut self, handle: DeviceHandle<T>, mapping: Mapping) -> Result<(), String> {
+        let index = self.index_of(handle).ok_or("the device isn't on the bus")?;
+        self.check_mapping(&mapping, Some(index))?;
+        self.devices[index].mapping = mapping;
+        self.build_pages();
+        Ok(())
+    }
+
+    // Puts `device` where the handle's device was, like swapping a cartridge, and gives back
+    // the old one with a handle for the new one. The old handle doesn't reach the new device
+    pub fn replace<T: Device, U: Device>(&mut self, handle: DeviceHandle<T>, device: Box<U>) -> Option<(Box<T>, DeviceHandle<U>)> {
+        let index = self.index_of(handle)?;
+        let id = self.next_id;
+        self.next_id += 1;
+        self.devices[index].id = id;
+        let old = std::mem::replace(&mut self.devices[index].device, device);
+        let old: Box<dyn Any> = old;
+        Some((old.downcast().ok()?, DeviceHandle { id, device: PhantomData }))
+    }
+
+    pub fn device<T: Device>(&self, handle: DeviceHandle<T>) -> Option<&T> {
+        let device: &dyn Any = self.devices[self.index_of(handle)?].device.as_ref();
+        device.downcast_ref()
+    }
+
+    pub fn device_mut<T: Device>(&mut self, handle: DeviceHandle<T>) -> Option<&mut T> {
+        let index = self.index_of(handle)?;
+        let device: &mut dyn Any = self.devices[index].device.as_mut();
+        device.downcast_mut()
     }
 
-    pub fn register(&mut self, range: RangeInclusive<u16>, device: Box<dyn Device>) {
-        self.devices.push((range, device));
+    pub fn mapping<T: Device>(&self, handle: DeviceHandle<T>) -> Option<&Mapping> {
+        Some(&self.devices[self.index_of(handle)?].mapping)
+    }
+
+    // Where the handle's device is, None if it's gone or isn't a T, so nothing is changed
+    // through a handle that doesn't match
+    fn index_of<T: Device>(&self, handle: DeviceHandle<T>) -> Option<usize> {
+        let index = self.devices.iter().position(|slot| slot.id == handle.id)?;
+        let device: &dyn Any = self.devices[index].device.as_ref();
+        device.is::<T>().then_some(index)
+    }
+
+    // Errors if `mapping` is empty or would be ambiguous, ignoring the device at `except`
+    fn check_mapping(&self, mapping: &Mapping, except: Option<usize>) -> Result<(), String> {
+        if mapping.range.start() > mapping.range.end() {
+            return Err(format!("${:04X}-${:04X} is empty", mapping.range.start(), mapping.range.end()));
+        }
+        let clash = self
+            .devices
+            .iter()
+            .enumerate()
+            .find(|&(index, slot)| Some(index) != except && slot.mapping.priority == mapping.priority && slot.mapping.overlaps(mapping));
+        if let Some((_, slot)) = clash {
+            return Err(format!(
+                "${:04X}-${:04X} overlaps the device at ${:04X}-${:04X}, both at priority {}",
+                mapping.range.start(),
+                mapping.range.end(),
+                slot.mapping.range.start(),
+                slot.mapping.range.end(),
+                mapping.priority
+            ));
+        }
+        Ok(())
+    }
+
+    fn build_pages(&mut self) {
+        for page in 0..256 {
+            let base = (page << 8) as u16;
+            let mut decoded = [None; 256];
+            for (offset, device) in decoded.iter_mut().enumerate() {
+                let addr = base + offset as u16;
+                *device = self
+                    .devices
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, slot)| slot.mapping.range.contains(&addr))
+                    .max_by_key(|(_, slot)| slot.mapping.priority)
+                    .map(|(index, _)| index);
+            }
+            self.pages[page] = if decoded.iter().all(|device| *device == decoded[0]) {
+                decoded[0].map_or(Page::Unmapped, Page::Device)
+            } else {
+                Page::Split(Box::new(decoded))
+            };
+        }
+    }
+
+    // The index of the device at `addr`
+    fn decode(&self, addr: u16) -> Option<usize> {
+        match &self.pages[(addr >> 8) as usize] {
+            Page::Unmapped => None,
+            Page::Device(index) => Some(*index),
+            Page::Split(devices) => devices[(addr & 0xFF) as usize],
+        }
     }
 
     pub fn read(&mut self, addr: u16) -> u8 {
-        for (range, device) in &mut self.devices {
-            if range.contains(&addr) {
-                let offset = addr - *range.start();
-                return device.read(offset);
+        self.read_as(addr, ReadKind::Data)
+    }
+
+    // A read by the CPU that tells coverage what the byte was for
+    pub fn read_as(&mut self, addr: u16, kind: ReadKind) -> u8 {
+        if let Some(coverage) = &mut self.coverage {
+            let flags = match kind {
+                ReadKind::Opcode => CODE | OPCODE,
+                ReadKind::Operand => CODE,
+                ReadKind::Data => DATA,
+                ReadKind::Dummy => 0,
+            };
+            coverage.mark(addr, flags);
+        }
+
+        let value = match self.decode(addr) {
+            Some(index) => {
+                let slot = &mut self.devices[index];
+                slot.device.read(slot.mapping.offset(addr))
+            }
+            None => {
+                self.unmapped[(addr >> 8) as usize].reads += 1;
+                self.unmapped_value()
+            }
+        };
+        self.data_bus = value;
+
+        if !self.watches.is_empty() {
+            self.check_watches(addr, value, false);
+        }
+        value
+    }
+
+    // Like `read` but goes through the device's side effect free `peek`
+    pub fn peek(&mut self, addr: u16) -> u8 {
+        match self.decode(addr) {
+            Some(index) => {
+                let slot = &mut self.devices[index];
+                slot.device.peek(slot.mapping.offset(addr))
             }
+            None => self.unmapped_value(),
         }
+    }
 
-        0
+    // Like `write` but goes through the device's `poke`, and isn't seen by watchpoints or
+    // coverage
+    pub fn poke(&mut self, addr: u16, value: u8) {
+        if let Some(index) = self.decode(addr) {
+            let slot = &mut self.devices[index];
+            slot.device.poke(slot.mapping.offset(addr), value);
+        }
     }
 
     pub fn write(&mut self, addr: u16, value: u8) {
-        for (range, device) in &mut self.devices {
-            if range.contains(&addr) {
-                let offset = addr - *range.start();
-                device.write(offset, value);
-                return;
+        if let Some(coverage) = &mut self.coverage {
+            coverage.mark(addr, WRITTEN);
+        }
+        if !self.watches.is_empty() {
+            self.check_watches(addr, value, true);
+        }
+
+        self.data_bus = value;
+        match self.decode(addr) {
+            Some(index) => {
+                let slot = &mut self.devices[index];
+                slot.device.write(slot.mapping.offset(addr), value);
+            }
+            None => self.unmapped[(addr >> 8) as usize].writes += 1,
+        }
+    }
+
+    fn unmapped_value(&self) -> u8 {
+        match self.unmapped_reads {
+            UnmappedReads::OpenBus => self.data_bus,
+            UnmappedReads::Value(value) => value,
+        }
+    }
+
+    // Unmapped reads give the last value on the data bus by default, or always `value` with
+    // `UnmappedReads::Value`
+    pub fn set_unmapped_reads(&mut self, unmapped_reads: UnmappedReads) {
+        self.unmapped_reads = unmapped_reads;
+    }
+
+    // The unmapped accesses to `page` since the bus was made or they were last cleared
+    pub fn unmapped_accesses(&self, page: u8) -> UnmappedAccesses {
+        self.unmapped[page as usize]
+    }
+
+    pub fn clear_unmapped_accesses(&mut self) {
+        self.unmapped.fill(UnmappedAccesses::default());
+    }
+
+    // Records reads and/or writes in `range` under `id`, `peek` and `poke` are never recorded
+    pub fn watch(&mut self, id: usize, range: RangeInclusive<u16>, reads: bool, writes: bool) {
+        self.watches.push(Watch { id, range, reads, writes });
+    }
+
+    pub fn unwatch(&mut self, id: usize) {
+        self.watches.retain(|watch| watch.id != id);
+    }
+
+    // The last watched access since the last call, so a read-modify-write instruction gives
+    // the value it finally wrote rather than its dummy write
+    pub fn take_watch_hit(&mut self) -> Option<WatchHit> {
+        self.watch_hit.take()
+    }
+
+    // Starts recording coverage into `coverage`, or stops with None
+    pub fn set_coverage(&mut self, coverage: Option<Coverage>) {
+        self.coverage = coverage;
+    }
+
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+
+    // Stops recording and hands back what was recorded
+    pub fn take_coverage(&mut self) -> Option<Coverage> {
+        self.coverage.take()
+    }
+
+    // The interrupt lines, the data bus and every device's range and state, in the order they
+    // were registered
+    pub fn save_state(&self, state: &mut StateWriter) {
+        state.bool(self.irq);
+        state.bool(self.nmi);
+        state.u8(self.data_bus);
+        state.u16(self.devices.len() as u16);
+        for slot in &self.devices {
+            state.u16(*slot.mapping.range.start());
+            state.u16(*slot.mapping.range.end());
+            state.u16(slot.mapping.mask);
+            state.u8(slot.mapping.priority);
+            // Each device gets a block of its own, so one reading the wrong amount is caught
+            let mut device_state = StateWriter::default();
+            slot.device.save_state(&mut device_state);
+            state.bytes(&device_state.into_bytes());
+        }
+    }
+
+    pub fn load_state(&mut self, state: &mut StateReader) -> Result<(), String> {
+        self.irq = state.bool()?;
+        self.nmi = state.bool()?;
+        self.data_bus = state.u8()?;
+        let count = state.u16()? as usize;
+        if count != self.devices.len() {
+            return Err(format!("the state has {} devices but the bus has {}", count, self.devices.len()));
+        }
+        for (i, slot) in self.devices.iter_mut().enumerate() {
+            let range = &slot.mapping.range;
+            let (start, end) = (state.u16()?, state.u16()?);
+            if (start, end) != (*range.start(), *range.end()) {
+                return Err(format!(
+                    "device {} is at ${:04X}-${:04X} in the state but ${:04X}-${:04X} on the bus",
+                    i,
+                    start,
+                    end,
+                    range.start(),
+                    range.end()
+                ));
+            }
+            let (mask, priority) = (state.u16()?, state.u8()?);
+            if (mask, priority) != (slot.mapping.mask, slot.mapping.priority) {
+                return Err(format!(
+                    "device {} has mask ${:04X} at priority {} in the state but mask ${:04X} at priority {} on the bus",
+                    i, mask, priority, slot.mapping.mask, slot.mapping.priority
+                ));
             }
+            let mut device_state = StateReader::new(state.bytes()?);
+            slot.device
+                .load_state(&mut device_state)
+                .and_then(|_| device_state.finish())
+                .map_err(|e| format!("device {} at ${:04X}: {}", i, range.start(), e))?;
+        }
+        Ok(())
+    }
+
+    fn check_watches(&mut self, addr: u16, value: u8, write: bool) {
+        let hit = self.watches.iter().find(|watch| {
+            watch.range.contains(&addr) && if write { watch.writes } else { watch.reads }
+        });
+        if let Some(watch) = hit {
+            self.watch_hit = Some(WatchHit { id: watch.id, addr, value, write });
         }
     }
 
     pub fn tick(&mut self) -> TickReturn{
-        let mut ret = TickReturn::NONE;
-        for (_, device) in &mut self.devices {
-            match device.tick() {
+        let mut irq = false;
+        let mut nmi = false;
+        for slot in &mut self.devices {
+            match slot.device.tick() {
                 TickReturn::IRQ => {
-                    ret = TickReturn::IRQ;
+                    irq = true;
+                }
+                TickReturn::NMI => {
+                    nmi = true;
                 }
                 TickReturn::SHUTDOWN => {
+                    self.shutdown = true;
                     return TickReturn::SHUTDOWN;
                 }
                 TickReturn::NONE => {}
             }
         }
-        ret
+
+        self.irq = irq;
+        self.nmi = nmi;
+        if nmi {
+            TickReturn::NMI
+        } else if irq {
+            TickReturn::IRQ
+        } else {
+            TickReturn::NONE
+        }
     }
 }
@@ -1,157 +1,230 @@
-use super::cpu::CPU;
+use super::{config::CpuVariant, cpu::CPU};
 
 use crate::bus::Bus;
 
-impl CPU {
-    pub(super) fn immediate_adressing(&mut self, bus: &mut Bus, ticks: &mut u32) -> u8 {
-        *ticks += 1;
-        self.fetch_byte(bus)
-    }
-
-    pub(super) fn accumulator_adressing(&mut self, ticks: &mut u32) -> u8 {
-        *ticks += 1;
-        self.a
-    }
-
-    pub(super) fn get_zp_adress(&mut self, bus: &mut Bus, ticks: &mut u32) -> u16 {
-        *ticks += 1;
-        self.fetch_byte(bus) as u16
-    }
-
-    pub(super) fn zero_page_adressing(&mut self, bus: &mut Bus, ticks: &mut u32) -> u8 {
-        let addr = self.get_zp_adress(bus, ticks);
-        *ticks += 1;
-        Self::read_byte(bus, addr as u16)
-    }
-
-    pub(super) fn get_zp_adress_x(&mut self, bus: &mut Bus, ticks: &mut u32) -> u16 {
-        *ticks += 2;
-        self.fetch_byte(bus).wrapping_add(self.x) as u16
-    }
+// Every addressing mode of the 6502 family, as listed in the opcode table
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AddrMode {
+    Implied,
+    Accumulator,
+    Immediate,
+    ZeroPage,
+    ZeroPageX,
+    ZeroPageY,
+    Absolute,
+    AbsoluteX,
+    AbsoluteY,
+    // JMP ($xxxx)
+    Indirect,
+    IndirectX,
+    IndirectY,
+    Relative,
+    // The rest are only on the 65C02: (zp), JMP ($xxxx,X) and the zero page test of BBR and BBS
+    ZeroPageIndirect,
+    AbsoluteXIndirect,
+    ZeroPageRelative,
+}
 
-    pub(super) fn get_zp_adress_y(&mut self, bus: &mut Bus, ticks: &mut u32) -> u16 {
-        *ticks += 2;
-        self.fetch_byte(bus).wrapping_add(self.y) as u16
+impl AddrMode {
+    // Length of an instruction in this mode, including the opcode
+    pub const fn bytes(self) -> u8 {
+        match self {
+            AddrMode::Implied | AddrMode::Accumulator => 1,
+            AddrMode::Immediate
+            | AddrMode::ZeroPage
+            | AddrMode::ZeroPageX
+            | AddrMode::ZeroPageY
+            | AddrMode::IndirectX
+            | AddrMode::IndirectY
+            | AddrMode::Relative
+            | AddrMode::ZeroPageIndirect => 2,
+            AddrMode::Absolute
+            | AddrMode::AbsoluteX
+            | AddrMode::AbsoluteY
+            | AddrMode::Indirect
+            | AddrMode::AbsoluteXIndirect
+            | AddrMode::ZeroPageRelative => 3,
+        }
     }
+}
 
-    pub(super) fn zero_page_adressing_x(&mut self, bus: &mut Bus, ticks: &mut u32) -> u8 {
-        let addr = self.get_zp_adress_x(bus, ticks);
-        *ticks += 1;
-        Self::read_byte(bus, addr as u16)
-    }
+// What the instruction does with its operand, indexed writes and read-modify-writes always
+// spend a cycle on the address before the page is fixed while reads only do on a page cross
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum Access {
+    Read,
+    Write,
+    Modify,
+}
 
-    pub(super) fn zero_page_adressing_y(&mut self, bus: &mut Bus, ticks: &mut u32) -> u8 {
-        let addr = self.get_zp_adress_y(bus, ticks);
-        *ticks += 3;
-        Self::read_byte(bus, addr as u16)
+impl CPU {
+    pub(super) fn immediate_adressing(&mut self, bus: &mut Bus) -> u8 {
+        self.fetch_byte(bus)
     }
 
-    pub(super) fn relative_adressing(&mut self, bus: &mut Bus, ticks: &mut u32) {
-        let old_pc = self.pc;
-
-        let offset = i8::from_ne_bytes([self.immediate_adressing(bus, ticks)]);
-        self.pc = self.pc.wrapping_add_signed(offset.into());
-
-        if (old_pc & 0xFF00) != (self.pc & 0xFF00) {
-            *ticks += 1;
-        }
+    // One byte instructions still read the next opcode in their second cycle and throw it away
+    pub(super) fn implied_adressing(&mut self, bus: &mut Bus) {
+        self.discard_read(bus, self.pc);
     }
 
-    pub(super) fn get_absolute_adress(&mut self, bus: &mut Bus, ticks: &mut u32) -> u16 {
+    pub(super) fn get_absolute_adress(&mut self, bus: &mut Bus) -> u16 {
         let lo = self.fetch_byte(bus) as u16;
         let hi = self.fetch_byte(bus) as u16;
-        *ticks += 3;
         (hi << 8) | lo
     }
 
-    pub(super) fn absolute_adressing(&mut self, bus: &mut Bus, ticks: &mut u32) -> u8 {
-        let addr = self.get_absolute_adress(bus, ticks) as u16;
-        Self::read_byte(bus, addr)
-    }
-
-    pub(super) fn get_absolute_adress_x(&mut self, bus: &mut Bus, ticks: &mut u32) -> u16 {
-        self.get_absolute_adress(bus, ticks)
-            .wrapping_add(self.x as u16)
-    }
-
-    pub(super) fn get_absolute_adress_y(&mut self, bus: &mut Bus, ticks: &mut u32) -> u16 {
-        self.get_absolute_adress(bus, ticks)
-            .wrapping_add(self.y as u16)
+    // Cycles spent on address arithmetic still drive the bus, the NMOS part reads whatever
+    // address is half formed while the 65C02 reads the last operand byte again
+    pub(super) fn dummy_read(&mut self, bus: &mut Bus, addr: u16) {
+        let addr = match self.config.variant {
+            CpuVariant::Nmos6502 => addr,
+            CpuVariant::Wdc65C02 => self.pc.wrapping_sub(1),
+        };
+        self.discard_read(bus, addr);
     }
 
-    pub(super) fn absolute_adressing_x(&mut self, bus: &mut Bus, ticks: &mut u32) -> u8 {
-        let base = self.get_absolute_adress(bus, ticks);
-        let addr = base.wrapping_add(self.x as u16);
-
-        if (base & 0xFF00) != (addr & 0xFF00) {
-            *ticks += 1;
+    // Fetches the offset and branches if `condition` holds, a taken branch spends a cycle
+    // adding the offset and another fixing the high byte if it crossed a page
+    pub(super) fn relative_adressing(&mut self, bus: &mut Bus, condition: bool) {
+        let offset = i8::from_ne_bytes([self.fetch_byte(bus)]);
+        if !condition {
+            return;
         }
 
-        Self::read_byte(bus, addr as u16)
-    }
-
-    pub(super) fn absolute_adressing_y(&mut self, bus: &mut Bus, ticks: &mut u32) -> u8 {
-        let base = self.get_absolute_adress(bus, ticks);
-        let addr = base.wrapping_add(self.y as u16);
-
-        if (base & 0xFF00) != (addr & 0xFF00) {
-            *ticks += 1;
+        self.discard_read(bus, self.pc);
+        let target = self.pc.wrapping_add_signed(offset.into());
+        if (self.pc & 0xFF00) != (target & 0xFF00) {
+            self.discard_read(bus, (self.pc & 0xFF00) | (target & 0x00FF));
         }
-
-        Self::read_byte(bus, addr as u16)
+        self.pc = target;
     }
 
-    pub(super) fn get_indirect_adress(&mut self, bus: &mut Bus, ticks: &mut u32) -> u16 {
-        let lo = self.fetch_byte(bus);
-        let hi = self.fetch_byte(bus);
-        let in_addr = (hi as u16) << 8 | lo as u16;
+    pub(super) fn get_indirect_adress(&mut self, bus: &mut Bus) -> u16 {
+        let in_addr = self.get_absolute_adress(bus);
 
-        *ticks += 5;
-        let addr_lo = Self::read_byte(bus, in_addr);
-        let hi_addr = if self.config.emulate_indirect_jmp_bug {
+        let hi_addr = if self.config.variant == CpuVariant::Wdc65C02 {
+            // The 65C02 fixed the page wrap and spends an extra cycle doing so
+            self.dummy_read(bus, in_addr);
+            in_addr.wrapping_add(1)
+        } else if self.config.emulate_indirect_jmp_bug {
             (in_addr & 0xFF00) | ((in_addr.wrapping_add(1)) & 0x00FF)
         } else {
             in_addr.wrapping_add(1)
         };
-        let addr_hi = Self::read_byte(bus, hi_addr);
+        let addr_lo = self.read_byte(bus, in_addr);
+        let addr_hi = self.read_byte(bus, hi_addr);
         ((addr_hi as u16) << 8) | (addr_lo as u16)
     }
 
-    pub(super) fn get_indirect_indexing_adress(&mut self, bus: &mut Bus, ticks: &mut u32) -> u16 {
-        let zp_addr = self.fetch_byte(bus).wrapping_add(self.x);
-        let lsb_addr = Self::read_byte(bus, zp_addr as u16);
-        let msb_addr = Self::read_byte(bus, zp_addr as u16 + 1);
-        *ticks += 5;
-
-        ((msb_addr as u16) << 8) | (lsb_addr as u16)
-    }
-
-    pub(super) fn indirect_indexing_adressing_x(&mut self, bus: &mut Bus, ticks: &mut u32) -> u8 {
-        let addr = self.get_indirect_indexing_adress(bus, ticks);
-        *ticks += 1;
-        Self::read_byte(bus, addr)
+    // Resolve the effective address with all the bus cycles the real chip spends on it, also
+    // returning the unindexed base for the SH* family and whether indexing crossed a page
+    pub(super) fn resolve_adress(&mut self, bus: &mut Bus, mode: AddrMode, access: Access) -> (u16, u16, bool) {
+        let resolved = match mode {
+            AddrMode::ZeroPage => {
+                let addr = self.fetch_byte(bus) as u16;
+                (addr, addr, false)
+            }
+            AddrMode::ZeroPageX | AddrMode::ZeroPageY => {
+                let base = self.fetch_byte(bus);
+                self.dummy_read(bus, base as u16);
+                let index = if let AddrMode::ZeroPageX = mode { self.x } else { self.y };
+                let addr = base.wrapping_add(index) as u16;
+                (addr, addr, false)
+            }
+            AddrMode::Absolute => {
+                let addr = self.get_absolute_adress(bus);
+                (addr, addr, false)
+            }
+            AddrMode::AbsoluteX | AddrMode::AbsoluteY => {
+                let base = self.get_absolute_adress(bus);
+                let index = if let AddrMode::AbsoluteX = mode { self.x } else { self.y };
+                let addr = base.wrapping_add(index as u16);
+                let crossed = (base & 0xFF00) != (addr & 0xFF00);
+                self.index_fixup(bus, base, addr, crossed, access);
+                (addr, base, crossed)
+            }
+            AddrMode::IndirectX => {
+                let zp = self.fetch_byte(bus);
+                self.dummy_read(bus, zp as u16);
+                let zp = zp.wrapping_add(self.x);
+                let lo = self.read_byte(bus, zp as u16);
+                let hi = self.read_byte(bus, zp.wrapping_add(1) as u16);
+                let addr = u16::from_le_bytes([lo, hi]);
+                (addr, addr, false)
+            }
+            AddrMode::IndirectY => {
+                let zp = self.fetch_byte(bus);
+                let lo = self.read_byte(bus, zp as u16);
+                let hi = self.read_byte(bus, zp.wrapping_add(1) as u16);
+                let base = u16::from_le_bytes([lo, hi]);
+                let addr = base.wrapping_add(self.y as u16);
+                let crossed = (base & 0xFF00) != (addr & 0xFF00);
+                self.index_fixup(bus, base, addr, crossed, access);
+                (addr, base, crossed)
+            }
+            AddrMode::ZeroPageIndirect => {
+                let zp = self.fetch_byte(bus);
+                let lo = self.read_byte(bus, zp as u16);
+                let hi = self.read_byte(bus, zp.wrapping_add(1) as u16);
+                let addr = u16::from_le_bytes([lo, hi]);
+                (addr, addr, false)
+            }
+            _ => unreachable!("{:?} has no operand address", mode),
+        };
+        self.effective_address = Some(resolved.0);
+        resolved
+    }
+
+    // The low byte is indexed first and the access goes out before the high byte is fixed.
+    // Reads that didn't cross a page are done by then, everything else wastes that access,
+    // except that the 65C02 only spends the cycle on read-modify-writes that cross a page
+    fn index_fixup(&mut self, bus: &mut Bus, base: u16, addr: u16, crossed: bool, access: Access) {
+        let needed = match access {
+            Access::Read => crossed,
+            Access::Write => true,
+            Access::Modify => crossed || self.config.variant == CpuVariant::Nmos6502,
+        };
+        if needed {
+            self.dummy_read(bus, (base & 0xFF00) | (addr & 0x00FF));
+        }
     }
 
-    pub(super) fn get_indexing_indirect_adress(&mut self, bus: &mut Bus, ticks: &mut u32) -> u16 {
-        let zp = self.fetch_byte(bus);
-
-        let lo = Self::read_byte(bus, zp as u16) as u16;
-        let hi = Self::read_byte(bus, zp.wrapping_add(1) as u16) as u16;
-
-        let base = (hi << 8) | lo;
-        let addr = base.wrapping_add(self.y as u16);
-
-        if (base & 0xFF00) != (addr & 0xFF00) {
-            *ticks += 1;
+    pub(super) fn read_operand(&mut self, bus: &mut Bus, mode: AddrMode) -> u8 {
+        if mode == AddrMode::Immediate {
+            return self.immediate_adressing(bus);
         }
+        let (addr, _, _) = self.resolve_adress(bus, mode, Access::Read);
+        self.read_byte(bus, addr)
+    }
 
-        *ticks += 4;
-        addr
+    pub(super) fn write_operand(&mut self, bus: &mut Bus, mode: AddrMode, value: u8) {
+        let (addr, _, _) = self.resolve_adress(bus, mode, Access::Write);
+        self.write_byte(bus, addr, value);
     }
 
-    pub(super) fn indexing_indirect_adressing_y(&mut self, bus: &mut Bus, ticks: &mut u32) -> u8 {
-        let addr = self.get_indexing_indirect_adress(bus, ticks);
-        *ticks += 1;
-        Self::read_byte(bus, addr as u16)
+    // The accumulator forms of the shifts and INC/DEC A spend their second cycle reading the
+    // next opcode like any other one byte instruction
+    pub(super) fn modify_operand(&mut self, bus: &mut Bus, mode: AddrMode, op: fn(&mut CPU, u8) -> u8) {
+        if mode == AddrMode::Accumulator {
+            self.implied_adressing(bus);
+            self.a = op(self, self.a);
+            return;
+        }
+        let (addr, _, _) = self.resolve_adress(bus, mode, Access::Modify);
+        self.modify(bus, addr, op);
+    }
+
+    // Read-modify-write, the NMOS part writes the unmodified value back while the ALU works
+    // on it, the 65C02 reads it a second time instead
+    pub(super) fn modify(&mut self, bus: &mut Bus, addr: u16, op: fn(&mut CPU, u8) -> u8) {
+        let value = self.read_byte(bus, addr);
+        match self.config.variant {
+            CpuVariant::Nmos6502 => self.write_byte(bus, addr, value),
+            CpuVariant::Wdc65C02 => {
+                self.read_byte(bus, addr);
+            }
+        }
+        let result = op(self, value);
+        self.write_byte(bus, addr, result);
     }
 }
@@ -0,0 +1,642 @@
+use std::{collections::HashMap, fmt};
+
+use super::{addresing::AddrMode, config::CpuVariant, cpu::CPU, opcodes::opcode_table};
+use crate::{bus::Bus, debug::symbols::SymbolTable};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AsmError {
+    // 1 based line number in the source
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+// A run of bytes assembled one after the other, a new one starts at every `*=`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Segment {
+    pub origin: u16,
+    pub bytes: Vec<u8>,
+}
+
+pub struct Assembly {
+    pub segments: Vec<Segment>,
+    // Every label in the source, constants defined with `=` aren't included
+    pub symbols: SymbolTable,
+    // The 1 based source line and address of every instruction, in source order
+    pub lines: Vec<(usize, u16)>,
+}
+
+impl Assembly {
+    // Writes every segment to its address with `poke`, so loading a program doesn't set off
+    // devices, watchpoints or coverage
+    pub fn load(&self, bus: &mut Bus) {
+        for segment in &self.segments {
+            for (i, byte) in segment.bytes.iter().enumerate() {
+                bus.poke(segment.origin.wrapping_add(i as u16), *byte);
+            }
+        }
+    }
+
+    // The lowest address anything was assembled to
+    pub fn origin(&self) -> u16 {
+        self.segments.iter().map(|segment| segment.origin).min().unwrap_or(0)
+    }
+
+    // Everything from `origin()` to the last byte assembled as one image, with gaps between
+    // segments filled with zeros
+    pub fn to_binary(&self) -> Vec<u8> {
+        let origin = self.origin() as usize;
+        let end = self.segments.iter().map(|segment| segment.origin as usize + segment.bytes.len()).max().unwrap_or(origin);
+        let mut binary = vec![0; end - origin];
+        for segment in &self.segments {
+            let start = segment.origin as usize - origin;
+            binary[start..start + segment.bytes.len()].copy_from_slice(&segment.bytes);
+        }
+        binary
+    }
+
+    // The address of the instruction on `line`
+    pub fn address_of_line(&self, line: usize) -> Option<u16> {
+        self.lines.iter().find(|(number, _)| *number == line).map(|(_, addr)| *addr)
+    }
+
+    // The source line of the instruction at `addr`
+    pub fn line_at(&self, addr: u16) -> Option<usize> {
+        self.lines.iter().find(|(_, address)| *address == addr).map(|(line, _)| *line)
+    }
+}
+
+enum Item {
+    Instruction { opcode: u8, mode: AddrMode, operands: Vec<String> },
+    Bytes(Vec<String>),
+    Words(Vec<String>),
+}
+
+struct Statement {
+    line: usize,
+    pc: u16,
+    item: Item,
+}
+
+// A two pass assembler for the syntax most 6502 assemblers share: `label:`, `NAME = expr`,
+// `*=`/`.org`, `.byte`/`.word` and the usual operand forms. Operands that aren't known in
+// the first pass (forward references) are assembled as absolute rather than zero page
+pub struct Assembler {
+    pub variant: CpuVariant,
+    // When off the undocumented NMOS mnemonics aren't recognised
+    pub undocumented_opcodes: bool,
+}
+
+impl Assembler {
+    pub fn new(variant: CpuVariant) -> Self {
+        Self {
+            variant,
+            undocumented_opcodes: true,
+        }
+    }
+
+    // Assembles for the way `cpu` is configured to run
+    pub fn for_cpu(cpu: &CPU) -> Self {
+        Self {
+            variant: cpu.config.variant,
+            undocumented_opcodes: cpu.config.undocumented_opcodes,
+        }
+    }
+
+    // Assembles `source`, starting at $0000 until the first `*=`
+    pub fn assemble(&self, source: &str) -> Result<Assembly, AsmError> {
+        let mut symbols: HashMap<String, i64> = HashMap::new();
+        let mut labels = vec![];
+        let mut pending = vec![];
+        let mut statements = vec![];
+        let mut pc: u32 = 0;
+
+        for (number, raw) in source.lines().enumerate() {
+            let line = number + 1;
+            let error = |message: String| AsmError { line, message };
+            let text = strip_comment(raw);
+            let mut rest = text.trim();
+
+            if let Some((name, after)) = split_ident(rest) {
+                let after = after.trim_start();
+                if let Some(expr) = after.strip_prefix('=') {
+                    if symbols.contains_key(name) || pending.iter().any(|(_, pending, _)| pending == name) {
+                        return Err(error(format!("{} is defined twice", name)));
+                    }
+                    match eval(expr, pc as u16, &symbols, false).map_err(error)? {
+                        Some(value) => {
+                            symbols.insert(name.to_string(), value);
+                        }
+                        None => pending.push((line, name.to_string(), expr.to_string())),
+                    }
+                    continue;
+                }
+
+                let label = match after.strip_prefix(':') {
+                    Some(after) => Some(after),
+                    // A name in the first column without a colon is a label too, unless it's
+                    // an instruction
+                    None if !text.starts_with(char::is_whitespace) && !self.is_mnemonic(name) => Some(after),
+                    None => None,
+                };
+                if let Some(after) = label {
+                    if symbols.insert(name.to_string(), pc as i64).is_some() {
+                        return Err(error(format!("{} is defined twice", name)));
+                    }
+                    labels.push((name.to_string(), pc as u16));
+                    rest = after.trim();
+                }
+            }
+
+            if rest.is_empty() {
+                continue;
+            }
+
+            let (word, operand) = match rest.find(char::is_whitespace) {
+                Some(end) => (&rest[..end], rest[end..].trim()),
+                None => (rest, ""),
+            };
+            let origin = if let Some(expr) = rest.strip_prefix('*') {
+                expr.trim_start().strip_prefix('=')
+            } else if word.eq_ignore_ascii_case(".org") {
+                Some(operand)
+            } else {
+                None
+            };
+            if let Some(expr) = origin {
+                match eval(expr, pc as u16, &symbols, false).map_err(error)? {
+                    Some(value @ 0..=0xFFFF) => pc = value as u32,
+                    Some(value) => return Err(error(format!("origin {} is out of range", value))),
+                    None => return Err(error("the origin has to be known in the first pass".to_string())),
+                }
+                continue;
+            }
+
+            let (item, len) = match word.to_ascii_lowercase().as_str() {
+                ".byte" | ".db" => {
+                    let values = split_top_level(operand);
+                    let len = values.iter().map(|value| string_literal(value).map_or(1, |s| s.len())).sum();
+                    (Item::Bytes(values), len)
+                }
+                ".word" | ".dw" => {
+                    let values = split_top_level(operand);
+                    let len = values.len() * 2;
+                    (Item::Words(values), len)
+                }
+                directive if directive.starts_with('.') => {
+                    return Err(error(format!("unknown directive {}", word)));
+                }
+                _ => {
+                    let mnemonic = word.to_ascii_uppercase();
+                    let (opcode, mode, operands) = self.choose_mode(&mnemonic, operand, pc as u16, &symbols).map_err(error)?;
+                    (Item::Instruction { opcode, mode, operands }, mode.bytes() as usize)
+                }
+            };
+
+            statements.push(Statement { line, pc: pc as u16, item });
+            pc += len as u32;
+            if pc > 0x10000 {
+                return Err(error("the program runs past $FFFF".to_string()));
+            }
+        }
+
+        // Constants that used a label from further down can be worked out now every label is
+        // known, they can depend on each other so keep going until nothing changes
+        while !pending.is_empty() {
+            let before = pending.len();
+            let mut unresolved = vec![];
+            for (line, name, expr) in pending {
+                match eval(&expr, 0, &symbols, false).map_err(|message| AsmError { line, message })? {
+                    Some(value) => {
+                        symbols.insert(name, value);
+                    }
+                    None => unresolved.push((line, name, expr)),
+                }
+            }
+            if unresolved.len() == before {
+                let (line, _, expr) = &unresolved[0];
+                let message = eval(expr, 0, &symbols, true).err().unwrap_or_default();
+                return Err(AsmError { line: *line, message });
+            }
+            pending = unresolved;
+        }
+
+        let mut segments: Vec<Segment> = vec![];
+        for statement in &statements {
+            let error = |message: String| AsmError { line: statement.line, message };
+            let bytes = encode(statement, &symbols).map_err(error)?;
+            match segments.last_mut() {
+                Some(last) if last.origin as usize + last.bytes.len() == statement.pc as usize => last.bytes.extend(bytes),
+                _ => segments.push(Segment { origin: statement.pc, bytes }),
+            }
+        }
+
+        let mut table = SymbolTable::default();
+        for (name, addr) in labels {
+            table.insert(&name, addr);
+        }
+        let lines = statements
+            .iter()
+            .filter(|statement| matches!(statement.item, Item::Instruction { .. }))
+            .map(|statement| (statement.line, statement.pc))
+            .collect();
+        Ok(Assembly { segments, symbols: table, lines })
+    }
+
+    // The opcode for an instruction, preferring the documented one when there is more than
+    // one, and the standard NOP over the 65C02's reserved single byte ones
+    fn find(&self, mnemonic: &str, mode: AddrMode) -> Option<u8> {
+        if mnemonic == "NOP" && mode == AddrMode::Implied {
+            return Some(0xEA);
+        }
+        let table = opcode_table(self.variant);
+        (0..256)
+            .filter(|&i| table[i].mnemonic == mnemonic && table[i].mode == mode)
+            .filter(|&i| self.undocumented_opcodes || !table[i].undocumented)
+            .min_by_key(|&i| table[i].undocumented)
+            .map(|i| i as u8)
+    }
+
+    fn is_mnemonic(&self, name: &str) -> bool {
+        opcode_table(self.variant)
+            .iter()
+            .any(|op| op.mnemonic.eq_ignore_ascii_case(name) && (self.undocumented_opcodes || !op.undocumented))
+    }
+
+    // Works out the addressing mode from the operand's syntax, returning the opcode, the mode
+    // and the expressions that make up the operand
+    fn choose_mode(
+        &self,
+        mnemonic: &str,
+        operand: &str,
+        pc: u16,
+        symbols: &HashMap<String, i64>,
+    ) -> Result<(u8, AddrMode, Vec<String>), String> {
+        let unsupported = || format!("{} doesn't take the operand '{}'", mnemonic, operand);
+        if !self.is_mnemonic(mnemonic) {
+            return Err(format!("unknown instruction {}", mnemonic));
+        }
+        let with = |mode: AddrMode, operands: &[&str]| {
+            let operands = operands.iter().map(|operand| operand.to_string()).collect();
+            self.find(mnemonic, mode).map(|opcode| (opcode, mode, operands)).ok_or_else(unsupported)
+        };
+        // Zero page when the value is known to fit and the instruction has that form
+        let sized = |zp: AddrMode, abs: AddrMode, expr: &str| -> Result<_, String> {
+            let fits = matches!(eval(expr, pc, symbols, false)?, Some(0..=0xFF));
+            match (self.find(mnemonic, zp), self.find(mnemonic, abs)) {
+                (Some(_), _) if fits => with(zp, &[expr]),
+                (_, Some(_)) => with(abs, &[expr]),
+                (Some(_), None) => with(zp, &[expr]),
+                (None, None) => Err(unsupported()),
+            }
+        };
+
+        if operand.is_empty() {
+            return with(AddrMode::Implied, &[]).or_else(|_| with(AddrMode::Accumulator, &[]));
+        }
+        if operand.eq_ignore_ascii_case("A") && self.find(mnemonic, AddrMode::Accumulator).is_some() {
+            return with(AddrMode::Accumulator, &[]);
+        }
+        if let Some(expr) = operand.strip_prefix('#') {
+            return with(AddrMode::Immediate, &[expr]);
+        }
+
+        if operand.starts_with('(')
+            && let Some(close) = matching_paren(operand)
+        {
+            let inner = &operand[1..close];
+            let after: String = operand[close + 1..].chars().filter(|c| !c.is_whitespace()).collect();
+            let parts = split_top_level(inner);
+            if after.is_empty() {
+                return match parts.as_slice() {
+                    [expr, index] if index.eq_ignore_ascii_case("X") => {
+                        sized(AddrMode::IndirectX, AddrMode::AbsoluteXIndirect, expr)
+                    }
+                    [expr] => sized(AddrMode::ZeroPageIndirect, AddrMode::Indirect, expr),
+                    _ => Err(unsupported()),
+                };
+            }
+            if after.eq_ignore_ascii_case(",Y") && parts.len() == 1 {
+                return with(AddrMode::IndirectY, &[parts[0].as_str()]);
+            }
+        }
+
+        match split_top_level(operand).as_slice() {
+            [expr] if self.find(mnemonic, AddrMode::Relative).is_some() => with(AddrMode::Relative, &[expr]),
+            [expr] => sized(AddrMode::ZeroPage, AddrMode::Absolute, expr),
+            [expr, index] if index.eq_ignore_ascii_case("X") => sized(AddrMode::ZeroPageX, AddrMode::AbsoluteX, expr),
+            [expr, index] if index.eq_ignore_ascii_case("Y") => sized(AddrMode::ZeroPageY, AddrMode::AbsoluteY, expr),
+            [zp, target] => with(AddrMode::ZeroPageRelative, &[zp, target]),
+            _ => Err(unsupported()),
+        }
+    }
+}
+
+fn encode(statement: &Statement, symbols: &HashMap<String, i64>) -> Result<Vec<u8>, String> {
+    let pc = statement.pc;
+    let value = |expr: &str| eval(expr, pc, symbols, true).map(|value| value.unwrap_or_default());
+
+    match &statement.item {
+        Item::Bytes(values) => {
+            let mut bytes = vec![];
+            for expr in values {
+                match string_literal(expr) {
+                    Some(text) => bytes.extend(text.bytes()),
+                    None => bytes.push(byte(value(expr)?)?),
+                }
+            }
+            Ok(bytes)
+        }
+        Item::Words(values) => {
+            let mut bytes = vec![];
+            for expr in values {
+                bytes.extend(word(value(expr)?)?.to_le_bytes());
+            }
+            Ok(bytes)
+        }
+        Item::Instruction { opcode, mode, operands } => {
+            let mut bytes = vec![*opcode];
+            match mode {
+                AddrMode::Implied | AddrMode::Accumulator => {}
+                AddrMode::Immediate => bytes.push(byte(value(&operands[0])?)?),
+                AddrMode::ZeroPage
+                | AddrMode::ZeroPageX
+                | AddrMode::ZeroPageY
+                | AddrMode::IndirectX
+                | AddrMode::IndirectY
+                | AddrMode::ZeroPageIndirect => bytes.push(zero_page(value(&operands[0])?)?),
+                AddrMode::Absolute
+                | AddrMode::AbsoluteX
+                | AddrMode::AbsoluteY
+                | AddrMode::Indirect
+                | AddrMode::AbsoluteXIndirect => bytes.extend(address(value(&operands[0])?)?.to_le_bytes()),
+                AddrMode::Relative => bytes.push(branch(pc, 2, value(&operands[0])?)?),
+                AddrMode::ZeroPageRelative => {
+                    bytes.push(zero_page(value(&operands[0])?)?);
+                    bytes.push(branch(pc, 3, value(&operands[1])?)?);
+                }
+            }
+            Ok(bytes)
+        }
+    }
+}
+
+fn byte(value: i64) -> Result<u8, String> {
+    match value {
+        -128..=0xFF => Ok(value as u8),
+        _ => Err(format!("{} doesn't fit in a byte", value)),
+    }
+}
+
+fn word(value: i64) -> Result<u16, String> {
+    match value {
+        -32768..=0xFFFF => Ok(value as u16),
+        _ => Err(format!("{} doesn't fit in a word", value)),
+    }
+}
+
+fn zero_page(value: i64) -> Result<u8, String> {
+    match value {
+        0..=0xFF => Ok(value as u8),
+        _ => Err(format!("${:X} isn't in the zero page", value)),
+    }
+}
+
+fn address(value: i64) -> Result<u16, String> {
+    match value {
+        0..=0xFFFF => Ok(value as u16),
+        _ => Err(format!("{} isn't an address", value)),
+    }
+}
+
+fn branch(pc: u16, len: i64, target: i64) -> Result<u8, String> {
+    let offset = target - (pc as i64 + len);
+    match offset {
+        -128..=127 => Ok(offset as u8),
+        _ => Err(format!("branch to ${:04X} is out of range", target)),
+    }
+}
+
+// Everything before a `;` that isn't in quotes
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (None, ';') => return &line[..i],
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if q == c => quote = None,
+            _ => {}
+        }
+    }
+    line
+}
+
+// Splits an identifier off the front of `text`
+fn split_ident(text: &str) -> Option<(&str, &str)> {
+    if !text.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        return None;
+    }
+    let end = text.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(text.len());
+    Some((&text[..end], &text[end..]))
+}
+
+// Splits on commas that aren't in brackets or quotes
+fn split_top_level(text: &str) -> Vec<String> {
+    let mut parts = vec![];
+    let mut depth = 0;
+    let mut quote = None;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(q), _) if q == c => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                parts.push(text[start..i].trim().to_string());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(text[start..].trim().to_string());
+    parts
+}
+
+// Index of the bracket that closes the one `text` starts with
+fn matching_paren(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote = None;
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(q), _) if q == c => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn string_literal(text: &str) -> Option<&str> {
+    text.strip_prefix('"')?.strip_suffix('"')
+}
+
+// Evaluates an expression, `*` is the address of the current instruction. Labels that aren't
+// defined yet give None, or an error when `strict` is set
+fn eval(text: &str, pc: u16, symbols: &HashMap<String, i64>, strict: bool) -> Result<Option<i64>, String> {
+    let mut parser = Parser { text: text.as_bytes(), pos: 0, pc, symbols, strict };
+    let value = parser.binary(0)?;
+    parser.skip_space();
+    if parser.pos < parser.text.len() {
+        return Err(format!("unexpected '{}' in '{}'", &text[parser.pos..], text.trim()));
+    }
+    Ok(value)
+}
+
+// Binary operators from the loosest binding to the tightest
+const PRECEDENCE: [&[&str]; 6] = [&["|"], &["^"], &["&"], &["<<", ">>"], &["+", "-"], &["*", "/", "%"]];
+
+struct Parser<'a> {
+    text: &'a [u8],
+    pos: usize,
+    pc: u16,
+    symbols: &'a HashMap<String, i64>,
+    strict: bool,
+}
+
+impl Parser<'_> {
+    fn skip_space(&mut self) {
+        while self.text.get(self.pos).is_some_and(|c| c.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_space();
+        if self.text[self.pos..].starts_with(token.as_bytes()) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Option<i64>, String> {
+        if level == PRECEDENCE.len() {
+            return self.unary();
+        }
+        let mut left = self.binary(level + 1)?;
+        'operators: loop {
+            for op in PRECEDENCE[level] {
+                if self.eat(op) {
+                    let right = self.binary(level + 1)?;
+                    left = match (left, right) {
+                        (Some(left), Some(right)) => Some(apply(op, left, right)?),
+                        _ => None,
+                    };
+                    continue 'operators;
+                }
+            }
+            return Ok(left);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Option<i64>, String> {
+        for op in ["-", "<", ">", "~"] {
+            if self.eat(op) {
+                let value = self.unary()?;
+                return Ok(value.map(|value| match op {
+                    "-" => -value,
+                    "<" => value & 0xFF,
+                    ">" => (value >> 8) & 0xFF,
+                    _ => !value,
+                }));
+            }
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Option<i64>, String> {
+        self.skip_space();
+        let rest = &self.text[self.pos..];
+        let digits = |radix: u32, text: &[u8]| text.iter().take_while(|c| (**c as char).is_digit(radix)).count();
+
+        let (radix, skip) = match rest.first() {
+            None => return Err("expected a value".to_string()),
+            Some(b'$') => (16, 1),
+            Some(b'%') => (2, 1),
+            Some(c) if c.is_ascii_digit() => (10, 0),
+            Some(b'*') => {
+                self.pos += 1;
+                return Ok(Some(self.pc as i64));
+            }
+            Some(b'(') => {
+                self.pos += 1;
+                let value = self.binary(0)?;
+                if !self.eat(")") {
+                    return Err("missing )".to_string());
+                }
+                return Ok(value);
+            }
+            Some(b'\'') => {
+                return match rest {
+                    [b'\'', c, b'\'', ..] => {
+                        self.pos += 3;
+                        Ok(Some(*c as i64))
+                    }
+                    _ => Err("bad character literal".to_string()),
+                };
+            }
+            Some(_) => {
+                let text = std::str::from_utf8(rest).unwrap_or_default();
+                let Some((name, _)) = split_ident(text) else {
+                    return Err(format!("unexpected '{}'", text));
+                };
+                self.pos += name.len();
+                return match self.symbols.get(name) {
+                    Some(value) => Ok(Some(*value)),
+                    None if self.strict => Err(format!("unknown label {}", name)),
+                    None => Ok(None),
+                };
+            }
+        };
+
+        let len = digits(radix, &rest[skip..]);
+        if len == 0 {
+            return Err("expected digits".to_string());
+        }
+        let text = std::str::from_utf8(&rest[skip..skip + len]).unwrap_or_default();
+        self.pos += skip + len;
+        i64::from_str_radix(text, radix).map(Some).map_err(|e| format!("bad number {}: {}", text, e))
+    }
+}
+
+fn apply(op: &str, left: i64, right: i64) -> Result<i64, String> {
+    Ok(match op {
+        "|" => left | right,
+        "^" => left ^ right,
+        "&" => left & right,
+        "<<" => left.checked_shl(right as u32).unwrap_or(0),
+        ">>" => left.checked_shr(right as u32).unwrap_or(0),
+        "+" => left.wrapping_add(right),
+        "-" => left.wrapping_sub(right),
+        "*" => left.wrapping_mul(right),
+        "/" | "%" if right == 0 => return Err("division by zero".to_string()),
+        "/" => left / right,
+        _ => left % right,
+    })
+}
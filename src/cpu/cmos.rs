@@ -0,0 +1,135 @@
+use super::{
+    addresing::{Access, AddrMode},
+    cpu::CPU,
+};
+use crate::bus::Bus;
+
+impl CPU {
+    // TSB and TRB set Z from A AND memory, then set or clear the bits of A in memory
+    fn tsb(&mut self, value: u8) -> u8 {
+        self.set_status(self.a & value == 0, 1);
+        value | self.a
+    }
+
+    fn trb(&mut self, value: u8) -> u8 {
+        self.set_status(self.a & value == 0, 1);
+        value & !self.a
+    }
+
+    // BBR and BBS read their zero page byte twice before the branch offset
+    fn test_zero_page(&mut self, bus: &mut Bus) -> u8 {
+        let (addr, _, _) = self.resolve_adress(bus, AddrMode::ZeroPage, Access::Read);
+        let value = self.read_byte(bus, addr);
+        self.read_byte(bus, addr);
+        value
+    }
+}
+
+// Handlers for the opcodes the 65C02 adds over the NMOS 6502, the (zp) forms of the ALU
+// opcodes and INC A / DEC A share the documented handlers
+
+pub(super) fn bra(cpu: &mut CPU, bus: &mut Bus, _mode: AddrMode) {
+    cpu.relative_adressing(bus, true);
+}
+
+pub(super) fn phx(cpu: &mut CPU, bus: &mut Bus, _mode: AddrMode) {
+    cpu.implied_adressing(bus);
+    cpu.push_byte_stack(bus, cpu.x);
+}
+
+pub(super) fn phy(cpu: &mut CPU, bus: &mut Bus, _mode: AddrMode) {
+    cpu.implied_adressing(bus);
+    cpu.push_byte_stack(bus, cpu.y);
+}
+
+pub(super) fn plx(cpu: &mut CPU, bus: &mut Bus, _mode: AddrMode) {
+    cpu.implied_adressing(bus);
+    cpu.discard_read(bus, 0x0100 + cpu.sp as u16);
+    cpu.x = cpu.pull_byte_stack(bus);
+    cpu.ld_set_status(cpu.x);
+}
+
+pub(super) fn ply(cpu: &mut CPU, bus: &mut Bus, _mode: AddrMode) {
+    cpu.implied_adressing(bus);
+    cpu.discard_read(bus, 0x0100 + cpu.sp as u16);
+    cpu.y = cpu.pull_byte_stack(bus);
+    cpu.ld_set_status(cpu.y);
+}
+
+// STZ: store zero
+pub(super) fn stz(cpu: &mut CPU, bus: &mut Bus, mode: AddrMode) {
+    cpu.write_operand(bus, mode, 0);
+}
+
+pub(super) fn tsb(cpu: &mut CPU, bus: &mut Bus, mode: AddrMode) {
+    cpu.modify_operand(bus, mode, CPU::tsb);
+}
+
+pub(super) fn trb(cpu: &mut CPU, bus: &mut Bus, mode: AddrMode) {
+    cpu.modify_operand(bus, mode, CPU::trb);
+}
+
+// BIT immediate only affects Z
+pub(super) fn bit_immediate(cpu: &mut CPU, bus: &mut Bus, _mode: AddrMode) {
+    let value = cpu.immediate_adressing(bus);
+    cpu.set_status(cpu.a & value == 0, 1);
+}
+
+pub(super) fn jmp_absolute_x_indirect(cpu: &mut CPU, bus: &mut Bus, _mode: AddrMode) {
+    let base = cpu.get_absolute_adress(bus);
+    cpu.dummy_read(bus, base);
+    let addr = base.wrapping_add(cpu.x as u16);
+    let lo = cpu.read_byte(bus, addr);
+    let hi = cpu.read_byte(bus, addr.wrapping_add(1));
+    cpu.pc = u16::from_le_bytes([lo, hi]);
+}
+
+// RMB and SMB: clear or set bit n of a zero page byte
+pub(super) fn rmb<const BIT: u8>(cpu: &mut CPU, bus: &mut Bus, _mode: AddrMode) {
+    let (addr, _, _) = cpu.resolve_adress(bus, AddrMode::ZeroPage, Access::Modify);
+    let value = cpu.read_byte(bus, addr);
+    cpu.read_byte(bus, addr);
+    cpu.write_byte(bus, addr, value & !(1 << BIT));
+}
+
+pub(super) fn smb<const BIT: u8>(cpu: &mut CPU, bus: &mut Bus, _mode: AddrMode) {
+    let (addr, _, _) = cpu.resolve_adress(bus, AddrMode::ZeroPage, Access::Modify);
+    let value = cpu.read_byte(bus, addr);
+    cpu.read_byte(bus, addr);
+    cpu.write_byte(bus, addr, value | (1 << BIT));
+}
+
+// BBR and BBS: branch if bit n of a zero page byte is clear or set
+pub(super) fn bbr<const BIT: u8>(cpu: &mut CPU, bus: &mut Bus, _mode: AddrMode) {
+    let value = cpu.test_zero_page(bus);
+    cpu.relative_adressing(bus, value & (1 << BIT) == 0);
+}
+
+pub(super) fn bbs<const BIT: u8>(cpu: &mut CPU, bus: &mut Bus, _mode: AddrMode) {
+    let value = cpu.test_zero_page(bus);
+    cpu.relative_adressing(bus, value & (1 << BIT) != 0);
+}
+
+pub(super) fn wai(cpu: &mut CPU, bus: &mut Bus, _mode: AddrMode) {
+    cpu.implied_adressing(bus);
+    cpu.implied_adressing(bus);
+    cpu.waiting = true;
+}
+
+// STP, halts like a JAM until reset
+pub(super) fn stp(cpu: &mut CPU, bus: &mut Bus, _mode: AddrMode) {
+    cpu.implied_adressing(bus);
+    cpu.implied_adressing(bus);
+    cpu.jammed = true;
+}
+
+// The unused x3 and xB opcodes are one byte NOPs that take a single cycle
+pub(super) fn nop_single_cycle(_cpu: &mut CPU, _bus: &mut Bus, _mode: AddrMode) {}
+
+// NOP $5C, the odd one out at 8 cycles
+pub(super) fn nop_long(cpu: &mut CPU, bus: &mut Bus, _mode: AddrMode) {
+    let addr = cpu.get_absolute_adress(bus);
+    for _ in 0..5 {
+        cpu.read_byte(bus, addr);
+    }
+}
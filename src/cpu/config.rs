@@ -1,16 +1,42 @@
-use crate::platform::logging::{Logger, Stdout};
+use super::trace::Tracer;
+
+// Which member of the 6502 family is being emulated
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CpuVariant {
+    // The original NMOS 6502
+    Nmos6502,
+    // The CMOS 65C02 with the Rockwell bit instructions and WDC's WAI and STP
+    Wdc65C02,
+}
 
 pub struct CpuConfig {
+    pub variant: CpuVariant,
     pub emulate_indirect_jmp_bug: bool,
-    pub logger: Box<dyn Logger>,
+    // Execute the undocumented NMOS opcodes, when off they are skipped as unknown.
+    // Has no effect on the 65C02 where every unused opcode is a NOP
+    pub undocumented_opcodes: bool,
+    // Magic constants for the unstable ANE ($8B) and LXA ($AB) opcodes, A = (A | magic) & ...
+    pub ane_magic: u8,
+    pub lxa_magic: u8,
+    // Tick the bus after every CPU cycle so devices run in lockstep with the CPU's accesses,
+    // rather than leaving the caller to tick them in a burst after `step`
+    pub cycle_accurate: bool,
+    // Receives an event for every instruction and interrupt, None turns tracing off
+    pub tracer: Option<Box<dyn Tracer>>,
     pub speed: f64,
 }
 
 impl CpuConfig{
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> Self{
         Self {
+            variant: CpuVariant::Nmos6502,
             emulate_indirect_jmp_bug: false,
-            logger: Box::new(Stdout{}),
+            undocumented_opcodes: true,
+            ane_magic: 0xEE,
+            lxa_magic: 0xEE,
+            cycle_accurate: false,
+            tracer: None,
             speed: 1.0,
         }
     }
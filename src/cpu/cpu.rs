@@ -1,5 +1,16 @@
 use crate::{bus::Bus, cpu::config::CpuConfig};
 
+// A copy of the programmer visible registers
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct Registers {
+    pub pc: u16,
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub sp: u8,
+    pub status: u8,
+}
+
 pub struct CPU {
     pub pc: u16,
     pub(super) sp: u8,
@@ -10,10 +21,27 @@ pub struct CPU {
 
     pub(super) status: u8,
 
+    pub(super) irq_line: bool,
+    pub(super) nmi_line: bool,
+    pub(super) nmi_pending: bool,
+
+    pub(super) jammed: bool,
+    pub(super) waiting: bool,
+
+    // Total bus cycles since the CPU was created
+    pub(super) cycles: u64,
+
+    // The bytes fetched for the current instruction and the address its operand resolved to,
+    // kept for the tracer
+    pub(super) fetched: [u8; 3],
+    pub(super) fetch_count: usize,
+    pub(super) effective_address: Option<u16>,
+
     pub config: CpuConfig,
 }
 
 impl CPU {
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> Self {
         Self {
             pc: 0,
@@ -22,6 +50,15 @@ impl CPU {
             x: 0,
             y: 0,
             status: 0,
+            irq_line: false,
+            nmi_line: false,
+            nmi_pending: false,
+            jammed: false,
+            waiting: false,
+            cycles: 0,
+            fetched: [0; 3],
+            fetch_count: 0,
+            effective_address: None,
             config: CpuConfig::default(),
         }
     }
@@ -33,6 +70,9 @@ impl CPU {
 
         self.sp = 0xFD;
         self.status = 0x24;
+        self.nmi_pending = false;
+        self.jammed = false;
+        self.waiting = false;
         self.set_pc(bus);
     }
 
@@ -55,4 +95,49 @@ impl CPU {
     pub fn read_status(&self) -> u8 {
         self.status
     }
+
+    pub fn read_cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    pub fn write_acc(&mut self, value: u8) {
+        self.a = value;
+    }
+
+    pub fn write_x(&mut self, value: u8) {
+        self.x = value;
+    }
+
+    pub fn write_y(&mut self, value: u8) {
+        self.y = value;
+    }
+
+    pub fn write_sp(&mut self, value: u8) {
+        self.sp = value;
+    }
+
+    pub fn write_status(&mut self, value: u8) {
+        self.status = value;
+    }
+
+    pub fn registers(&self) -> Registers {
+        Registers {
+            pc: self.pc,
+            a: self.a,
+            x: self.x,
+            y: self.y,
+            sp: self.sp,
+            status: self.status,
+        }
+    }
+
+    pub fn set_registers(&mut self, registers: Registers) {
+        let Registers { pc, a, x, y, sp, status } = registers;
+        self.pc = pc;
+        self.a = a;
+        self.x = x;
+        self.y = y;
+        self.sp = sp;
+        self.status = status;
+    }
 }
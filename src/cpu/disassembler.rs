@@ -0,0 +1,175 @@
+use std::fmt;
+
+use super::{
+    addresing::AddrMode,
+    config::CpuVariant,
+    cpu::CPU,
+    opcodes::{opcode_table, Opcode},
+};
+use crate::{bus::Bus, debug::symbols::SymbolTable};
+
+// Formats the operand of an instruction at `pc` in the usual assembler syntax, branch
+// offsets are shown as the address they go to. Addresses with a name in `symbols` are shown
+// by name, immediates never are
+pub fn format_operand(mode: AddrMode, operands: &[u8], pc: u16, symbols: Option<&SymbolTable>) -> String {
+    let label = |addr: u16| symbols.and_then(|symbols| symbols.name_at(addr)).map(|name| name.to_string());
+    let zp = || label(operands[0] as u16).unwrap_or_else(|| format!("${:02X}", operands[0]));
+    let abs = |addr: u16| label(addr).unwrap_or_else(|| format!("${:04X}", addr));
+    let word = || abs(u16::from_le_bytes([operands[0], operands[1]]));
+    let branch = |offset: u8, len: u16| abs(branch_target(pc, offset, len));
+
+    match mode {
+        AddrMode::Implied => String::new(),
+        AddrMode::Accumulator => "A".to_string(),
+        AddrMode::Immediate => format!("#${:02X}", operands[0]),
+        AddrMode::ZeroPage => zp(),
+        AddrMode::ZeroPageX => format!("{},X", zp()),
+        AddrMode::ZeroPageY => format!("{},Y", zp()),
+        AddrMode::Absolute => word(),
+        AddrMode::AbsoluteX => format!("{},X", word()),
+        AddrMode::AbsoluteY => format!("{},Y", word()),
+        AddrMode::Indirect => format!("({})", word()),
+        AddrMode::IndirectX => format!("({},X)", zp()),
+        AddrMode::IndirectY => format!("({}),Y", zp()),
+        AddrMode::Relative => branch(operands[0], 2),
+        AddrMode::ZeroPageIndirect => format!("({})", zp()),
+        AddrMode::AbsoluteXIndirect => format!("({},X)", word()),
+        AddrMode::ZeroPageRelative => format!("{},{}", zp(), branch(operands[1], 3)),
+    }
+}
+
+// The mnemonic followed by its operand, if it has one
+pub fn format_instruction(
+    mnemonic: &str,
+    mode: AddrMode,
+    operands: &[u8],
+    pc: u16,
+    symbols: Option<&SymbolTable>,
+) -> String {
+    let operand = format_operand(mode, operands, pc, symbols);
+    if operand.is_empty() {
+        mnemonic.to_string()
+    } else {
+        format!("{} {}", mnemonic, operand)
+    }
+}
+
+fn branch_target(pc: u16, offset: u8, len: u16) -> u16 {
+    pc.wrapping_add(len).wrapping_add_signed(offset as i8 as i16)
+}
+
+// One decoded instruction
+#[derive(Clone, Copy)]
+pub struct Disassembled {
+    pub address: u16,
+    // The opcode followed by its operands, `len` of them are used
+    pub bytes: [u8; 3],
+    pub len: u8,
+    // None for a byte the CPU wouldn't run as an instruction, it's shown as `.byte`
+    pub opcode: Option<&'static Opcode>,
+}
+
+impl Disassembled {
+    pub fn operands(&self) -> &[u8] {
+        &self.bytes[1..self.len as usize]
+    }
+
+    // Address of the instruction after this one
+    pub fn next(&self) -> u16 {
+        self.address.wrapping_add(self.len as u16)
+    }
+
+    // Where a branch or an absolute JMP/JSR goes to
+    pub fn target(&self) -> Option<u16> {
+        let opcode = self.opcode?;
+        let operands = self.operands();
+        match (opcode.mode, opcode.mnemonic) {
+            (AddrMode::Relative, _) => Some(branch_target(self.address, operands[0], 2)),
+            (AddrMode::ZeroPageRelative, _) => Some(branch_target(self.address, operands[1], 3)),
+            (AddrMode::Absolute, "JMP" | "JSR") => Some(u16::from_le_bytes([operands[0], operands[1]])),
+            _ => None,
+        }
+    }
+
+    // The instruction in assembler syntax, with labels from `symbols` if given
+    pub fn text(&self, symbols: Option<&SymbolTable>) -> String {
+        match self.opcode {
+            Some(opcode) => format_instruction(opcode.mnemonic, opcode.mode, self.operands(), self.address, symbols),
+            None => format!(".byte ${:02X}", self.bytes[0]),
+        }
+    }
+
+    // Address, bytes and instruction, like `C000  4C F5 C5  JMP $C5F5`, or `JMP OSWRCH` with
+    // a symbol table that names $FFEE
+    pub fn format(&self, symbols: Option<&SymbolTable>) -> String {
+        let bytes: Vec<String> = self.bytes[..self.len as usize].iter().map(|b| format!("{:02X}", b)).collect();
+        format!("{:04X}  {:<8}  {}", self.address, bytes.join(" "), self.text(symbols))
+    }
+}
+
+impl fmt::Display for Disassembled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format(None))
+    }
+}
+
+// Decodes memory as the given CPU variant would run it. Memory is read with `Bus::peek` so
+// disassembling never changes the state of a device
+pub struct Disassembler {
+    pub variant: CpuVariant,
+    // When off the undocumented NMOS opcodes are shown as `.byte`, like the CPU skips them
+    pub undocumented_opcodes: bool,
+}
+
+impl Disassembler {
+    pub fn new(variant: CpuVariant) -> Self {
+        Self {
+            variant,
+            undocumented_opcodes: true,
+        }
+    }
+
+    // Decodes the way `cpu` is configured to run
+    pub fn for_cpu(cpu: &CPU) -> Self {
+        Self {
+            variant: cpu.config.variant,
+            undocumented_opcodes: cpu.config.undocumented_opcodes,
+        }
+    }
+
+    pub fn decode(&self, bus: &mut Bus, addr: u16) -> Disassembled {
+        let ins = bus.peek(addr);
+        let opcode = &opcode_table(self.variant)[ins as usize];
+        if opcode.undocumented && !self.undocumented_opcodes {
+            return Disassembled {
+                address: addr,
+                bytes: [ins, 0, 0],
+                len: 1,
+                opcode: None,
+            };
+        }
+
+        let mut bytes = [ins, 0, 0];
+        for i in 1..opcode.bytes as usize {
+            bytes[i] = bus.peek(addr.wrapping_add(i as u16));
+        }
+        Disassembled {
+            address: addr,
+            bytes,
+            len: opcode.bytes,
+            opcode: Some(opcode),
+        }
+    }
+
+    // `count` instructions one after the other starting at `addr`
+    pub fn disassemble(&self, bus: &mut Bus, addr: u16, count: usize) -> Vec<Disassembled> {
+        let mut lines = Vec::with_capacity(count);
+        let mut addr = addr;
+        for _ in 0..count {
+            let line = self.decode(bus, addr);
+            addr = line.next();
+            lines.push(line);
+        }
+        lines
+    }
+}
@@ -0,0 +1,158 @@
+use std::{fmt, fs, io};
+
+use super::{
+    cpu::{Registers, CPU},
+    trace::{InstructionTrace, RingBuffer, TraceEvent},
+};
+use crate::bus::Bus;
+
+// The fields of a nestest style log line that get compared, the disassembly isn't since every
+// emulator annotates operands a little differently
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LogLine {
+    pub pc: u16,
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub status: u8,
+    pub sp: u8,
+    pub cycles: Option<u64>,
+}
+
+impl LogLine {
+    // Reads PC from the start of the line and the registers from their `A:` style tags, lines
+    // that don't have them all give None
+    pub fn parse(line: &str) -> Option<Self> {
+        let pc = u16::from_str_radix(line.get(0..4)?, 16).ok()?;
+        let register = |tag: &str| {
+            let start = line.find(tag)? + tag.len();
+            u8::from_str_radix(line.get(start..start + 2)?, 16).ok()
+        };
+        let cycles = line.find("CYC:").and_then(|start| {
+            let rest = line[start + 4..].trim_start();
+            let end = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+            rest[..end].parse().ok()
+        });
+
+        Some(Self {
+            pc,
+            a: register(" A:")?,
+            x: register(" X:")?,
+            y: register(" Y:")?,
+            status: register(" P:")?,
+            sp: register(" SP:")?,
+            cycles,
+        })
+    }
+
+    fn from_trace(ins: &InstructionTrace) -> Self {
+        let Registers { pc, a, x, y, sp, status } = ins.before;
+        Self { pc, a, x, y, status, sp, cycles: Some(ins.start_cycle) }
+    }
+}
+
+// The first line where the CPU stopped matching the golden log
+#[derive(Clone, Debug, PartialEq)]
+pub struct Divergence {
+    // 1 based line number in the golden log
+    pub line: usize,
+    pub expected: String,
+    pub actual: String,
+    // Names of the fields that differ, none if the CPU stopped before this line
+    pub fields: Vec<&'static str>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.fields.is_empty() {
+            writeln!(f, "Diverged at line {}", self.line)?;
+        } else {
+            writeln!(f, "Diverged at line {} ({})", self.line, self.fields.join(", "))?;
+        }
+        writeln!(f, "expected: {}", self.expected)?;
+        write!(f, "actual:   {}", self.actual)
+    }
+}
+
+impl CPU {
+    // Runs the CPU one instruction per line of the golden log at `path` and returns the first
+    // line that doesn't match, or None if the whole log does. A CPU that jams or waits before
+    // the log ends diverges at the first line it didn't run. Lines that can't be parsed are
+    // skipped. The cycle count is compared relative to the first line since emulators
+    // disagree on what to count during reset
+    pub fn compare_with_log(&mut self, bus: &mut Bus, path: &str) -> io::Result<Option<Divergence>> {
+        let log = fs::read_to_string(path)?;
+        let buffer = RingBuffer::new(1);
+        let tracer = self.config.tracer.replace(Box::new(buffer.clone()));
+
+        let mut cycle_offset = None;
+        let mut divergence = None;
+        for (number, text) in log.lines().enumerate() {
+            let Some(expected) = LogLine::parse(text) else {
+                continue;
+            };
+            let Some(ins) = self.next_instruction(bus, &buffer) else {
+                let reason = if self.waiting { "waiting in WAI" } else { "jammed" };
+                divergence = Some(Divergence {
+                    line: number + 1,
+                    expected: text.to_string(),
+                    actual: format!("CPU stopped ({})", reason),
+                    fields: vec![],
+                });
+                break;
+            };
+            let actual = LogLine::from_trace(&ins);
+
+            let offset = *cycle_offset.get_or_insert(expected.cycles.unwrap_or(0) as i64 - ins.start_cycle as i64);
+            let fields = diff(&expected, &actual, offset);
+            if !fields.is_empty() {
+                divergence = Some(Divergence {
+                    line: number + 1,
+                    expected: text.to_string(),
+                    actual: ins.to_string(),
+                    fields,
+                });
+                break;
+            }
+        }
+
+        self.config.tracer = tracer;
+        Ok(divergence)
+    }
+
+    // Steps until an instruction runs, interrupt sequences don't get a line of their own.
+    // Gives up if the CPU has jammed or is sleeping in WAI
+    fn next_instruction(&mut self, bus: &mut Bus, buffer: &RingBuffer) -> Option<InstructionTrace> {
+        while !self.jammed && !self.waiting {
+            buffer.clear();
+            self.step(bus, 1);
+            if let Some(TraceEvent::Instruction(ins)) = buffer.events().pop() {
+                return Some(ins);
+            }
+        }
+        None
+    }
+}
+
+fn diff(expected: &LogLine, actual: &LogLine, cycle_offset: i64) -> Vec<&'static str> {
+    let mut fields = vec![];
+    let registers = [
+        ("PC", expected.pc == actual.pc),
+        ("A", expected.a == actual.a),
+        ("X", expected.x == actual.x),
+        ("Y", expected.y == actual.y),
+        ("P", expected.status == actual.status),
+        ("SP", expected.sp == actual.sp),
+    ];
+    for (name, same) in registers {
+        if !same {
+            fields.push(name);
+        }
+    }
+    if let (Some(expected), Some(actual)) = (expected.cycles, actual.cycles)
+        && expected as i64 != actual as i64 + cycle_offset
+    {
+        fields.push("CYC");
+    }
+    fields
+}
@@ -0,0 +1,217 @@
+use super::{
+    addresing::{Access, AddrMode},
+    cpu::CPU,
+};
+use crate::bus::Bus;
+
+// Handlers for the 105 opcodes the NMOS 6502 does not document, the undocumented NOPs use
+// the plain NOP handler
+
+pub(super) fn jam(cpu: &mut CPU, bus: &mut Bus, _mode: AddrMode) {
+    cpu.discard_read(bus, cpu.pc);
+    cpu.jammed = true;
+    cpu.pc = cpu.pc.wrapping_sub(1);
+}
+
+// SLO: ASL memory then ORA
+pub(super) fn slo(cpu: &mut CPU, bus: &mut Bus, mode: AddrMode) {
+    cpu.modify_operand(bus, mode, CPU::slo);
+}
+
+// RLA: ROL memory then AND
+pub(super) fn rla(cpu: &mut CPU, bus: &mut Bus, mode: AddrMode) {
+    cpu.modify_operand(bus, mode, CPU::rla);
+}
+
+// SRE: LSR memory then EOR
+pub(super) fn sre(cpu: &mut CPU, bus: &mut Bus, mode: AddrMode) {
+    cpu.modify_operand(bus, mode, CPU::sre);
+}
+
+// RRA: ROR memory then ADC
+pub(super) fn rra(cpu: &mut CPU, bus: &mut Bus, mode: AddrMode) {
+    cpu.modify_operand(bus, mode, CPU::rra);
+}
+
+// DCP: DEC memory then CMP
+pub(super) fn dcp(cpu: &mut CPU, bus: &mut Bus, mode: AddrMode) {
+    cpu.modify_operand(bus, mode, CPU::dcp);
+}
+
+// ISC: INC memory then SBC
+pub(super) fn isc(cpu: &mut CPU, bus: &mut Bus, mode: AddrMode) {
+    cpu.modify_operand(bus, mode, CPU::isc);
+}
+
+// LAX: LDA and LDX at once
+pub(super) fn lax(cpu: &mut CPU, bus: &mut Bus, mode: AddrMode) {
+    let value = cpu.read_operand(bus, mode);
+    cpu.a = value;
+    cpu.x = value;
+    cpu.ld_set_status(value);
+}
+
+// SAX: store A AND X
+pub(super) fn sax(cpu: &mut CPU, bus: &mut Bus, mode: AddrMode) {
+    cpu.write_operand(bus, mode, cpu.a & cpu.x);
+}
+
+pub(super) fn anc(cpu: &mut CPU, bus: &mut Bus, mode: AddrMode) {
+    let value = cpu.read_operand(bus, mode);
+    cpu.and(value);
+    cpu.set_status(cpu.a & 0b10000000 != 0, 0);
+}
+
+pub(super) fn alr(cpu: &mut CPU, bus: &mut Bus, mode: AddrMode) {
+    let value = cpu.read_operand(bus, mode);
+    cpu.and(value);
+    cpu.a = cpu.lsr(cpu.a);
+}
+
+pub(super) fn arr(cpu: &mut CPU, bus: &mut Bus, mode: AddrMode) {
+    let value = cpu.read_operand(bus, mode);
+    cpu.arr(value);
+}
+
+// ANE (unstable, uses the configured magic constant)
+pub(super) fn ane(cpu: &mut CPU, bus: &mut Bus, mode: AddrMode) {
+    let value = cpu.read_operand(bus, mode);
+    cpu.a = (cpu.a | cpu.config.ane_magic) & cpu.x & value;
+    cpu.ld_set_status(cpu.a);
+}
+
+// LXA (unstable, uses the configured magic constant)
+pub(super) fn lxa(cpu: &mut CPU, bus: &mut Bus, mode: AddrMode) {
+    let value = cpu.read_operand(bus, mode);
+    cpu.a = (cpu.a | cpu.config.lxa_magic) & value;
+    cpu.x = cpu.a;
+    cpu.ld_set_status(cpu.a);
+}
+
+pub(super) fn sbx(cpu: &mut CPU, bus: &mut Bus, mode: AddrMode) {
+    let value = cpu.read_operand(bus, mode);
+    let ax = cpu.a & cpu.x;
+    cpu.x = ax.wrapping_sub(value);
+    cpu.set_status(ax >= value, 0);
+    cpu.ld_set_status(cpu.x);
+}
+
+pub(super) fn sha(cpu: &mut CPU, bus: &mut Bus, mode: AddrMode) {
+    cpu.undocumented_sh(bus, mode, cpu.a & cpu.x);
+}
+
+pub(super) fn shx(cpu: &mut CPU, bus: &mut Bus, mode: AddrMode) {
+    cpu.undocumented_sh(bus, mode, cpu.x);
+}
+
+pub(super) fn shy(cpu: &mut CPU, bus: &mut Bus, mode: AddrMode) {
+    cpu.undocumented_sh(bus, mode, cpu.y);
+}
+
+pub(super) fn tas(cpu: &mut CPU, bus: &mut Bus, mode: AddrMode) {
+    cpu.sp = cpu.a & cpu.x;
+    cpu.undocumented_sh(bus, mode, cpu.sp);
+}
+
+pub(super) fn las(cpu: &mut CPU, bus: &mut Bus, mode: AddrMode) {
+    let value = cpu.read_operand(bus, mode) & cpu.sp;
+    cpu.a = value;
+    cpu.x = value;
+    cpu.sp = value;
+    cpu.ld_set_status(value);
+}
+
+impl CPU {
+    // The SH* stores AND the value with the high byte of the base address plus one, when the
+    // index crosses a page that value also replaces the high byte of the target address
+    fn undocumented_sh(&mut self, bus: &mut Bus, mode: AddrMode, value: u8) {
+        let (addr, base, crossed) = self.resolve_adress(bus, mode, Access::Write);
+        let value = value & ((base >> 8) as u8).wrapping_add(1);
+        let addr = if crossed {
+            ((value as u16) << 8) | (addr & 0x00FF)
+        } else {
+            addr
+        };
+        self.effective_address = Some(addr);
+        self.write_byte(bus, addr, value);
+    }
+
+    fn slo(&mut self, value: u8) -> u8 {
+        let result = value << 1;
+        self.set_status(value & 0b10000000 != 0, 0);
+        self.a |= result;
+        self.ld_set_status(self.a);
+        result
+    }
+
+    fn rla(&mut self, value: u8) -> u8 {
+        let result = (value << 1) | (self.status & 0b00000001);
+        self.set_status(value & 0b10000000 != 0, 0);
+        self.a &= result;
+        self.ld_set_status(self.a);
+        result
+    }
+
+    fn sre(&mut self, value: u8) -> u8 {
+        let result = value >> 1;
+        self.set_status(value & 0b00000001 != 0, 0);
+        self.a ^= result;
+        self.ld_set_status(self.a);
+        result
+    }
+
+    fn rra(&mut self, value: u8) -> u8 {
+        let result = (value >> 1) | ((self.status & 0b00000001) << 7);
+        self.set_status(value & 0b00000001 != 0, 0);
+        self.add_with_carry(result);
+        result
+    }
+
+    fn dcp(&mut self, value: u8) -> u8 {
+        let result = value.wrapping_sub(1);
+        self.cmp_set_status(result);
+        result
+    }
+
+    fn isc(&mut self, value: u8) -> u8 {
+        let result = value.wrapping_add(1);
+        self.subtract_with_carry(result);
+        result
+    }
+
+    // AND then ROR A, with the flags coming out of the adder rather than the shifter
+    fn arr(&mut self, value: u8) {
+        let and = self.a & value;
+        let carry_in = self.status & 0b00000001;
+        let mut result = (and >> 1) | (carry_in << 7);
+
+        if self.status & 0b00001000 == 0 {
+            self.a = result;
+            self.ld_set_status(result);
+            self.set_status(result & 0b01000000 != 0, 0);
+            self.set_status(((result >> 6) ^ (result >> 5)) & 1 != 0, 6);
+            return;
+        }
+
+        // NMOS decimal mode fixes up each nibble of the rotated value
+        self.set_status(carry_in != 0, 7);
+        self.set_status(result == 0, 1);
+        self.set_status((result ^ and) & 0b01000000 != 0, 6);
+
+        let lo = and & 0x0F;
+        let hi = and >> 4;
+        if lo + (lo & 1) > 5 {
+            result = (result & 0xF0) | (result.wrapping_add(6) & 0x0F);
+        }
+        let carry = hi + (hi & 1) > 5;
+        if carry {
+            result = result.wrapping_add(0x60);
+        }
+        self.set_status(carry, 0);
+        self.a = result;
+    }
+
+    pub fn is_jammed(&self) -> bool {
+        self.jammed
+    }
+}
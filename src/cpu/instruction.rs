@@ -1,6 +1,6 @@
-use super::cpu::CPU;
+use super::{config::CpuVariant, cpu::CPU};
 
-use crate::bus::Bus;
+use crate::bus::{Bus, ReadKind};
 
 impl CPU {
     pub(super) fn set_pc(&mut self, bus: &mut Bus) {
@@ -9,17 +9,70 @@ impl CPU {
         self.pc = (hi << 8) | lo;
     }
 
+    pub(super) fn fetch_opcode(&mut self, bus: &mut Bus) -> u8 {
+        self.fetch(bus, ReadKind::Opcode)
+    }
+
     pub(super) fn fetch_byte(&mut self, bus: &mut Bus) -> u8 {
-        let data = bus.read(self.pc);
+        self.fetch(bus, ReadKind::Operand)
+    }
+
+    fn fetch(&mut self, bus: &mut Bus, kind: ReadKind) -> u8 {
+        let data = bus.read_as(self.pc, kind);
+        self.end_cycle(bus);
         self.pc = self.pc.wrapping_add(1);
+        if self.fetch_count < self.fetched.len() {
+            self.fetched[self.fetch_count] = data;
+            self.fetch_count += 1;
+        }
         data
     }
 
-    pub(super) fn read_byte(bus: &mut Bus, addr: u16) -> u8 {
-        bus.read(addr)
+    // Every read and write the CPU makes goes through these two, each one is a single cycle
+    pub(super) fn read_byte(&mut self, bus: &mut Bus, addr: u16) -> u8 {
+        let value = bus.read(addr);
+        self.end_cycle(bus);
+        value
+    }
+
+    // A read made only because every cycle drives the bus, the value is thrown away
+    pub(super) fn discard_read(&mut self, bus: &mut Bus, addr: u16) {
+        bus.read_as(addr, ReadKind::Dummy);
+        self.end_cycle(bus);
+    }
+
+    pub(super) fn write_byte(&mut self, bus: &mut Bus, addr: u16, value: u8) {
+        bus.write(addr, value);
+        self.end_cycle(bus);
+    }
+
+    // A cycle where the CPU leaves the bus alone, only WAI, STP and JAM do this
+    pub(super) fn idle_cycle(&mut self, bus: &mut Bus) {
+        self.end_cycle(bus);
+    }
+
+    // In cycle accurate mode the devices are ticked after every access and the interrupt
+    // lines are sampled straight away, otherwise the caller ticks them after `step`
+    fn end_cycle(&mut self, bus: &mut Bus) {
+        self.cycles += 1;
+        if self.config.cycle_accurate {
+            bus.tick();
+            self.set_irq(bus.irq());
+            self.set_nmi(bus.nmi());
+        }
+    }
+
+    pub(super) fn adc(&mut self, bus: &mut Bus, value: u8) {
+        self.decimal_fixup_cycle(bus);
+        self.add_with_carry(value);
     }
 
-    pub(super) fn adc(&mut self, value: u8) {
+    pub(super) fn add_with_carry(&mut self, value: u8) {
+        if self.status & 0b00001000 != 0 {
+            self.adc_decimal(value);
+            return;
+        }
+
         let carry_in = (self.status & 0x01) as u16;
 
         let a = self.a as u16;
@@ -37,179 +90,178 @@ impl CPU {
         self.adc_set_status(carry_out, overflow);
     }
 
-    pub(super) fn and(&mut self, value: u8) {
-        self.a = value & self.a;
-        self.and_set_status();
+    // NMOS decimal add, Z comes from the binary sum while N and V come from the
+    // sum after the low nibble is adjusted but before the high nibble is
+    fn adc_decimal(&mut self, value: u8) {
+        let carry_in = (self.status & 0x01) as u16;
+
+        let a = self.a as u16;
+        let v = value as u16;
+
+        let binary = (a + v + carry_in) as u8;
+
+        let mut lo = (a & 0x0F) + (v & 0x0F) + carry_in;
+        if lo >= 0x0A {
+            lo = ((lo + 0x06) & 0x0F) + 0x10;
+        }
+
+        let mut sum = (a & 0xF0) + (v & 0xF0) + lo;
+
+        let negative = sum & 0x80 != 0;
+        let overflow = (!(a ^ v) & (a ^ sum) & 0x80) != 0;
+
+        if sum >= 0xA0 {
+            sum += 0x60;
+        }
+
+        self.a = (sum & 0xFF) as u8;
+        self.adc_decimal_set_status(sum > 0xFF, binary == 0, overflow, negative);
+
+        if self.config.variant == CpuVariant::Wdc65C02 {
+            // The 65C02 sets N and Z from the decimal result
+            self.ld_set_status(self.a);
+        }
     }
 
-    pub(super) fn asl_acc(&mut self, value: u8) {
-        let c = self.a & 0b10000000 != 0;
-        self.a = value << 1;
-        self.asl_set_status(self.a, c);
+    // The 65C02 takes an extra cycle for ADC and SBC in decimal mode
+    fn decimal_fixup_cycle(&mut self, bus: &mut Bus) {
+        if self.config.variant == CpuVariant::Wdc65C02 && self.status & 0b00001000 != 0 {
+            self.discard_read(bus, self.pc);
+        }
     }
 
-    pub(super) fn asl_mem(&mut self, bus: &mut Bus, addr: u16, ticks: &mut u32) {
-        let i = bus.read(addr);
-        let c = i & 0b10000000 != 0;
-        let v = i << 1;
-        *ticks += 3;
-        bus.write(addr, v);
-        self.asl_set_status(v, c);
+    pub(super) fn and(&mut self, value: u8) {
+        self.a &= value;
+        self.and_set_status();
     }
 
-    pub(super) fn lsr_acc(&mut self, value: u8) {
-        let c = self.a & 0b00000001 != 0;
-        self.a = value >> 1;
-        self.asl_set_status(self.a, c);
+    pub(super) fn asl(&mut self, value: u8) -> u8 {
+        let result = value << 1;
+        self.asl_set_status(result, value & 0b10000000 != 0);
+        result
     }
 
-    pub(super) fn lsr_mem(&mut self, bus: &mut Bus, addr: u16, ticks: &mut u32) {
-        let i = bus.read(addr);
-        let c = i & 0b00000001 != 0;
-        let v = i >> 1;
-        *ticks += 3;
-        bus.write(addr, v);
-        self.asl_set_status(v, c);
+    pub(super) fn lsr(&mut self, value: u8) -> u8 {
+        let result = value >> 1;
+        self.asl_set_status(result, value & 0b00000001 != 0);
+        result
     }
 
-    pub(super) fn bit_test(&mut self, value: u8, ticks: &mut u32) {
+    pub(super) fn bit_test(&mut self, value: u8) {
         self.set_status(self.a & value == 0, 1); // Set the zero flag
         self.set_status(value & 0b01000000 != 0, 6);
         self.set_status(value & 0b10000000 != 0, 7);
-        *ticks += 1;
     }
 
-    pub fn brk(&mut self, bus: &mut Bus, ticks: &mut u32) {
-        self.set_status(true, 4);
-        self.pc += 1;
+    pub(super) fn brk(&mut self, bus: &mut Bus) {
+        // BRK skips the byte after it, the real chip reads it as a padding operand
+        self.fetch_byte(bus);
         self.push_byte_stack(bus, (self.pc >> 8) as u8);
         self.push_byte_stack(bus, (self.pc & 0xFF) as u8);
         let status = self.status | 0b00110000;
         self.push_byte_stack(bus, status);
         self.set_status(true, 2);
+        if self.config.variant == CpuVariant::Wdc65C02 {
+            self.set_status(false, 3);
+        }
 
-        self.pc = u16::from_le_bytes([bus.read(0xFFFE), bus.read(0xFFFF)]);
-        *ticks += 7;
+        let lo = self.read_byte(bus, 0xFFFE);
+        let hi = self.read_byte(bus, 0xFFFF);
+        self.pc = u16::from_le_bytes([lo, hi]);
     }
 
-    pub(super) fn dec(&mut self, bus: &mut Bus, ticks: &mut u32, addr: u16) {
-        let value = bus.read(addr).wrapping_sub(1);
-        *ticks += 3;
-        bus.write(addr, value);
-        self.dec_set_status(value);
+    pub(super) fn dec(&mut self, value: u8) -> u8 {
+        let result = value.wrapping_sub(1);
+        self.dec_set_status(result);
+        result
     }
 
-    pub(super) fn decx(&mut self, ticks: &mut u32) {
-        *ticks += 1;
+    pub(super) fn decx(&mut self) {
         self.x = self.x.wrapping_sub(1);
         self.dec_set_status(self.x);
     }
 
-    pub(super) fn decy(&mut self, ticks: &mut u32) {
-        *ticks += 1;
+    pub(super) fn decy(&mut self) {
         self.y = self.y.wrapping_sub(1);
         self.dec_set_status(self.y);
     }
 
-    pub(super) fn eor(&mut self, ticks: &mut u32, value: u8) {
-        *ticks += 1;
-        self.a = self.a ^ value;
+    pub(super) fn eor(&mut self, value: u8) {
+        self.a ^= value;
         self.eor_set_status(self.a);
     }
 
-    pub(super) fn inc(&mut self, bus: &mut Bus, ticks: &mut u32, addr: u16) {
-        let value = bus.read(addr).wrapping_add(1);
-        *ticks += 3;
-        bus.write(addr, value);
-        self.dec_set_status(value);
+    pub(super) fn inc(&mut self, value: u8) -> u8 {
+        let result = value.wrapping_add(1);
+        self.dec_set_status(result);
+        result
     }
 
-    pub(super) fn incx(&mut self, ticks: &mut u32) {
-        *ticks += 1;
+    pub(super) fn incx(&mut self) {
         self.x = self.x.wrapping_add(1);
         self.dec_set_status(self.x);
     }
 
-    pub(super) fn incy(&mut self, ticks: &mut u32) {
-        *ticks += 1;
+    pub(super) fn incy(&mut self) {
         self.y = self.y.wrapping_add(1);
         self.dec_set_status(self.y);
     }
 
-    pub(super) fn jsr(&mut self, bus: &mut Bus, ticks: &mut u32, target: u16) {
-        *ticks += 2;
-
-        let ret_addr = self.pc - 1;
-        self.push_byte_stack(bus, (ret_addr >> 8) as u8);
-        self.push_byte_stack(bus, (ret_addr & 0xFF) as u8);
+    pub(super) fn jsr(&mut self, bus: &mut Bus) {
+        // The high byte of the target is fetched after the return address is pushed
+        let lo = self.fetch_byte(bus);
+        self.discard_read(bus, 0x0100 + self.sp as u16);
+        self.push_byte_stack(bus, (self.pc >> 8) as u8);
+        self.push_byte_stack(bus, (self.pc & 0xFF) as u8);
+        let hi = self.fetch_byte(bus);
 
-        self.pc = target;
+        self.pc = u16::from_le_bytes([lo, hi]);
     }
 
-    pub(super) fn ora(&mut self, value: u8, ticks: &mut u32) {
-        *ticks += 1;
-        self.a = self.a | value;
+    pub(super) fn ora(&mut self, value: u8) {
+        self.a |= value;
         self.eor_set_status(self.a);
     }
 
-    pub(super) fn pha(&mut self, bus: &mut Bus, ticks: &mut u32) {
+    pub(super) fn pha(&mut self, bus: &mut Bus) {
+        self.implied_adressing(bus);
         self.push_byte_stack(bus, self.a);
-        *ticks += 2;
     }
 
-    pub(super) fn php(&mut self, bus: &mut Bus, ticks: &mut u32) {
+    pub(super) fn php(&mut self, bus: &mut Bus) {
+        self.implied_adressing(bus);
         let status = self.status | 0b00110000;
         self.push_byte_stack(bus, status);
-        *ticks += 2;
     }
 
-    pub(super) fn pla(&mut self, bus: &mut Bus, ticks: &mut u32) {
+    pub(super) fn pla(&mut self, bus: &mut Bus) {
+        self.implied_adressing(bus);
+        self.discard_read(bus, 0x0100 + self.sp as u16);
         self.a = self.pull_byte_stack(bus);
         self.ld_set_status(self.a);
-        *ticks += 3;
     }
 
-    pub(super) fn plp(&mut self, bus: &mut Bus, ticks: &mut u32) {
+    pub(super) fn plp(&mut self, bus: &mut Bus) {
+        self.implied_adressing(bus);
+        self.discard_read(bus, 0x0100 + self.sp as u16);
         self.status = self.pull_byte_stack(bus) | 0b00100000;
-        *ticks += 3;
-    }
-
-    pub(super) fn rol_a(&mut self, ticks: &mut u32) {
-        let msb = self.a & 0b10000000 != 0;
-        self.a = (self.a.rotate_left(1) & 0b11111110) | (self.status & 0b00000001);
-        *ticks += 1;
-        self.rotate_set_status(self.a, msb);
     }
 
-    pub(super) fn rol(&mut self, bus: &mut Bus, addr: u16, ticks: &mut u32) {
-        let ov = bus.read(addr);
-        let msb = ov & 0b10000000 != 0;
-        let v = (ov.rotate_left(1) & 0b11111110) | (self.status & 0b00000001);
-        bus.write(addr, v);
-        *ticks += 3;
-        self.rotate_set_status(v, msb);
+    pub(super) fn rol(&mut self, value: u8) -> u8 {
+        let result = (value << 1) | (self.status & 0b00000001);
+        self.rotate_set_status(result, value & 0b10000000 != 0);
+        result
     }
 
-    pub(super) fn ror_a(&mut self, ticks: &mut u32) {
-        let lsb = self.a & 0b00000001 != 0;
-        let carry_in = (self.status & 0b00000001) != 0;
-        self.a = (self.a >> 1) | ((carry_in as u8) << 7);
-        *ticks += 1;
-        self.rotate_set_status(self.a, lsb);
+    pub(super) fn ror(&mut self, value: u8) -> u8 {
+        let result = (value >> 1) | ((self.status & 0b00000001) << 7);
+        self.rotate_set_status(result, value & 0b00000001 != 0);
+        result
     }
 
-    pub(super) fn ror(&mut self, bus: &mut Bus, addr: u16, ticks: &mut u32) {
-        let ov = bus.read(addr);
-        let lsb = ov & 0b00000001 != 0;
-        let carry_in = (self.status & 0b00000001) != 0;
-        let v = (ov >> 1) | ((carry_in as u8) << 7);
-        bus.write(addr, v);
-        *ticks += 3;
-        self.rotate_set_status(v, lsb);
-    }
-
-    pub(super) fn rti(&mut self, bus: &mut Bus, ticks: &mut u32) {
-        *ticks += 5;
+    pub(super) fn rti(&mut self, bus: &mut Bus) {
+        self.implied_adressing(bus);
+        self.discard_read(bus, 0x0100 + self.sp as u16);
         self.status = self.pull_byte_stack(bus);
         self.set_status(false, 4);
         let pc_lsb = self.pull_byte_stack(bus) as u16;
@@ -217,78 +269,117 @@ impl CPU {
         self.pc = pc_msb << 8 | pc_lsb;
     }
 
-    pub(super) fn rts(&mut self, bus: &mut Bus, ticks: &mut u32) {
-        *ticks += 5;
+    pub(super) fn rts(&mut self, bus: &mut Bus) {
+        self.implied_adressing(bus);
+        self.discard_read(bus, 0x0100 + self.sp as u16);
         let pc_lsb = self.pull_byte_stack(bus) as u16;
         let pc_msb = self.pull_byte_stack(bus) as u16;
-        self.pc = (pc_msb << 8 | pc_lsb).wrapping_add(1);
+        self.pc = pc_msb << 8 | pc_lsb;
+        // The pulled address is the last byte of the JSR, the final cycle reads it while incrementing past it
+        self.fetch_byte(bus);
     }
 
-    pub(super) fn sbc(&mut self, value: u8) {
-        let carry_in = (1 - (self.status & 0x01)) as u16;
+    pub(super) fn sbc(&mut self, bus: &mut Bus, value: u8) {
+        self.decimal_fixup_cycle(bus);
+        self.subtract_with_carry(value);
+    }
+
+    pub(super) fn subtract_with_carry(&mut self, value: u8) {
+        let carry_in = (self.status & 0x01) as u16;
 
         let a = self.a as u16;
-        let v = value as u16;
+        let v = (!value) as u16;
 
-        let sum = a.wrapping_sub(v).wrapping_sub(carry_in);
+        let sum = a + v + carry_in;
 
         let result = (sum & 0xFF) as u8;
 
-        let carry_out = sum < 0b01111111;
+        let carry_out = sum > 0xFF;
 
         let overflow = ((self.a ^ value) & (self.a ^ result) & 0x80) != 0;
 
+        // NMOS decimal subtract sets every flag from the binary result
+        let decimal = match self.config.variant {
+            CpuVariant::Nmos6502 => self.sbc_decimal(value),
+            CpuVariant::Wdc65C02 => self.sbc_decimal_cmos(value),
+        };
+
         self.a = result;
         self.sbc_set_status(carry_out, overflow);
-    }
 
-    pub(super) fn sta(&mut self, bus: &mut Bus, addr: u16, ticks: &mut u32) {
-        bus.write(addr, self.a);
-        *ticks += 1;
+        if self.status & 0b00001000 != 0 {
+            self.a = decimal;
+            if self.config.variant == CpuVariant::Wdc65C02 {
+                self.ld_set_status(self.a);
+            }
+        }
     }
 
-    pub(super) fn stx(&mut self, bus: &mut Bus, addr: u16, ticks: &mut u32) {
-        bus.write(addr, self.x);
-        *ticks += 1;
+    // The 65C02 adjusts the whole binary difference rather than each nibble, which only
+    // differs from the NMOS result for invalid BCD operands
+    fn sbc_decimal_cmos(&self, value: u8) -> u8 {
+        let carry_in = (self.status & 0x01) as i16;
+
+        let a = self.a as i16;
+        let v = value as i16;
+
+        let lo = (a & 0x0F) - (v & 0x0F) + carry_in - 1;
+        let mut sum = a - v + carry_in - 1;
+        if sum < 0 {
+            sum -= 0x60;
+        }
+        if lo < 0 {
+            sum -= 0x06;
+        }
+
+        (sum & 0xFF) as u8
     }
 
-    pub(super) fn sty(&mut self, bus: &mut Bus, addr: u16, ticks: &mut u32) {
-        bus.write(addr, self.y);
-        *ticks += 1;
+    fn sbc_decimal(&self, value: u8) -> u8 {
+        let carry_in = (self.status & 0x01) as i16;
+
+        let a = self.a as i16;
+        let v = value as i16;
+
+        let mut lo = (a & 0x0F) - (v & 0x0F) + carry_in - 1;
+        if lo < 0 {
+            lo = ((lo - 0x06) & 0x0F) - 0x10;
+        }
+
+        let mut sum = (a & 0xF0) - (v & 0xF0) + lo;
+        if sum < 0 {
+            sum -= 0x60;
+        }
+
+        (sum & 0xFF) as u8
     }
 
-    pub(super) fn tax(&mut self, ticks: &mut u32) {
+    pub(super) fn tax(&mut self) {
         self.x = self.a;
         self.transfer_set_status(self.x);
-        *ticks += 1;
     }
 
-    pub(super) fn tay(&mut self, ticks: &mut u32) {
+    pub(super) fn tay(&mut self) {
         self.y = self.a;
         self.transfer_set_status(self.y);
-        *ticks += 1;
     }
 
-    pub(super) fn tsx(&mut self, ticks: &mut u32) {
+    pub(super) fn tsx(&mut self) {
         self.x = self.sp;
         self.transfer_set_status(self.x);
-        *ticks += 1;
     }
 
-    pub(super) fn txa(&mut self, ticks: &mut u32) {
+    pub(super) fn txa(&mut self) {
         self.a = self.x;
         self.transfer_set_status(self.a);
-        *ticks += 1;
     }
 
-    pub(super) fn txs(&mut self, ticks: &mut u32) {
+    pub(super) fn txs(&mut self) {
         self.sp = self.x;
-        *ticks += 1;
     }
 
-    pub(super) fn tya(&mut self, ticks: &mut u32) {
+    pub(super) fn tya(&mut self) {
         self.a = self.y;
         self.transfer_set_status(self.a);
-        *ticks += 1;
     }
 }
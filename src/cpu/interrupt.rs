@@ -0,0 +1,77 @@
+use super::{
+    config::CpuVariant,
+    cpu::CPU,
+    trace::{InterruptKind, TraceEvent},
+};
+use crate::bus::Bus;
+
+const NMI_VECTOR: u16 = 0xFFFA;
+const IRQ_VECTOR: u16 = 0xFFFE;
+
+impl CPU {
+    // Drive the level triggered IRQ input, it stays asserted until the source releases it
+    pub fn set_irq(&mut self, asserted: bool) {
+        self.irq_line = asserted;
+    }
+
+    // Drive the edge triggered NMI input, an NMI is latched when the line goes from released to asserted
+    pub fn set_nmi(&mut self, asserted: bool) {
+        if asserted && !self.nmi_line {
+            self.nmi_pending = true;
+        }
+        self.nmi_line = asserted;
+    }
+
+    pub fn irq_asserted(&self) -> bool {
+        self.irq_line
+    }
+
+    pub fn nmi_pending(&self) -> bool {
+        self.nmi_pending
+    }
+
+    // Sampled at instruction boundaries, NMI wins over IRQ and IRQ is masked by the I flag
+    pub(super) fn poll_interrupts(&mut self, bus: &mut Bus) -> bool {
+        if self.nmi_pending {
+            self.nmi_pending = false;
+            self.trace_interrupt(InterruptKind::Nmi);
+            self.interrupt(bus, NMI_VECTOR);
+            return true;
+        }
+
+        if self.irq_line && self.status & 0b00000100 == 0 {
+            self.trace_interrupt(InterruptKind::Irq);
+            self.interrupt(bus, IRQ_VECTOR);
+            return true;
+        }
+
+        false
+    }
+
+    fn trace_interrupt(&mut self, kind: InterruptKind) {
+        if self.config.tracer.is_some() {
+            let event = TraceEvent::Interrupt { kind, before: self.registers(), start_cycle: self.cycles };
+            self.trace(&event);
+        }
+    }
+
+    // Hardware interrupt sequence, same as BRK but the pushed status has B clear
+    fn interrupt(&mut self, bus: &mut Bus, vector: u16) {
+        // Two cycles reading the next opcode without incrementing the PC, in place of the
+        // opcode and operand fetches of BRK
+        self.discard_read(bus, self.pc);
+        self.discard_read(bus, self.pc);
+        self.push_byte_stack(bus, (self.pc >> 8) as u8);
+        self.push_byte_stack(bus, (self.pc & 0xFF) as u8);
+        let status = (self.status & !0b00010000) | 0b00100000;
+        self.push_byte_stack(bus, status);
+        self.set_status(true, 2);
+        if self.config.variant == CpuVariant::Wdc65C02 {
+            self.set_status(false, 3);
+        }
+
+        let lo = self.read_byte(bus, vector);
+        let hi = self.read_byte(bus, vector + 1);
+        self.pc = u16::from_le_bytes([lo, hi]);
+    }
+}
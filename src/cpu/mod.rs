@@ -1,8 +1,18 @@
 pub mod addresing;
+pub mod assembler;
+pub mod cmos;
+#[allow(clippy::module_inception)]
 pub mod cpu;
+pub mod disassembler;
 pub mod execute;
+pub mod golden;
+pub mod illegal;
 pub mod instruction;
+pub mod interrupt;
 pub mod stack;
 pub mod status;
 pub mod config;
-pub mod load;
\ No newline at end of file
+pub mod load;
+pub mod opcodes;
+pub mod trace;
+pub mod state;
@@ -0,0 +1,575 @@
+use super::{
+    addresing::AddrMode::{self, *},
+    cmos::*,
+    config::CpuVariant,
+    cpu::CPU,
+    execute::*,
+    illegal::*,
+};
+use crate::bus::Bus;
+
+// One entry of the opcode table, everything that knows about an opcode (dispatch, cycle counts,
+// instruction lengths) reads it from here
+pub struct Opcode {
+    pub mnemonic: &'static str,
+    pub mode: AddrMode,
+    // Length of the instruction including the opcode
+    pub bytes: u8,
+    // Cycles taken without a page cross, branches take one more when they are taken
+    pub cycles: u8,
+    // One more cycle when indexing, or a taken branch, crosses a page
+    pub page_penalty: bool,
+    // One of the undocumented NMOS opcodes, only run when `config.undocumented_opcodes` is set
+    pub undocumented: bool,
+    pub(super) handler: Handler,
+}
+
+// Runs an opcode that has already been fetched, given the addressing mode from its entry
+pub(super) type Handler = fn(&mut CPU, &mut Bus, AddrMode);
+
+const fn op(mnemonic: &'static str, mode: AddrMode, cycles: u8, page_penalty: bool, handler: Handler) -> Opcode {
+    Opcode {
+        mnemonic,
+        mode,
+        bytes: mode.bytes(),
+        cycles,
+        page_penalty,
+        undocumented: false,
+        handler,
+    }
+}
+
+const fn ill(mnemonic: &'static str, mode: AddrMode, cycles: u8, page_penalty: bool, handler: Handler) -> Opcode {
+    Opcode {
+        undocumented: true,
+        ..op(mnemonic, mode, cycles, page_penalty, handler)
+    }
+}
+
+// The opcode table for the selected CPU
+pub fn opcode_table(variant: CpuVariant) -> &'static [Opcode; 256] {
+    match variant {
+        CpuVariant::Nmos6502 => &NMOS_OPCODES,
+        CpuVariant::Wdc65C02 => &CMOS_OPCODES,
+    }
+}
+
+pub static NMOS_OPCODES: [Opcode; 256] = [
+    op("BRK", Implied, 7, false, brk), // 00
+    op("ORA", IndirectX, 6, false, ora), // 01
+    ill("JAM", Implied, 2, false, jam), // 02
+    ill("SLO", IndirectX, 8, false, slo), // 03
+    ill("NOP", ZeroPage, 3, false, nop), // 04
+    op("ORA", ZeroPage, 3, false, ora), // 05
+    op("ASL", ZeroPage, 5, false, asl), // 06
+    ill("SLO", ZeroPage, 5, false, slo), // 07
+    op("PHP", Implied, 3, false, php), // 08
+    op("ORA", Immediate, 2, false, ora), // 09
+    op("ASL", Accumulator, 2, false, asl), // 0A
+    ill("ANC", Immediate, 2, false, anc), // 0B
+    ill("NOP", Absolute, 4, false, nop), // 0C
+    op("ORA", Absolute, 4, false, ora), // 0D
+    op("ASL", Absolute, 6, false, asl), // 0E
+    ill("SLO", Absolute, 6, false, slo), // 0F
+    op("BPL", Relative, 2, true, bpl), // 10
+    op("ORA", IndirectY, 5, true, ora), // 11
+    ill("JAM", Implied, 2, false, jam), // 12
+    ill("SLO", IndirectY, 8, false, slo), // 13
+    ill("NOP", ZeroPageX, 4, false, nop), // 14
+    op("ORA", ZeroPageX, 4, false, ora), // 15
+    op("ASL", ZeroPageX, 6, false, asl), // 16
+    ill("SLO", ZeroPageX, 6, false, slo), // 17
+    op("CLC", Implied, 2, false, clc), // 18
+    op("ORA", AbsoluteY, 4, true, ora), // 19
+    ill("NOP", Implied, 2, false, nop), // 1A
+    ill("SLO", AbsoluteY, 7, false, slo), // 1B
+    ill("NOP", AbsoluteX, 4, true, nop), // 1C
+    op("ORA", AbsoluteX, 4, true, ora), // 1D
+    op("ASL", AbsoluteX, 7, false, asl), // 1E
+    ill("SLO", AbsoluteX, 7, false, slo), // 1F
+    op("JSR", Absolute, 6, false, jsr), // 20
+    op("AND", IndirectX, 6, false, and), // 21
+    ill("JAM", Implied, 2, false, jam), // 22
+    ill("RLA", IndirectX, 8, false, rla), // 23
+    op("BIT", ZeroPage, 3, false, bit), // 24
+    op("AND", ZeroPage, 3, false, and), // 25
+    op("ROL", ZeroPage, 5, false, rol), // 26
+    ill("RLA", ZeroPage, 5, false, rla), // 27
+    op("PLP", Implied, 4, false, plp), // 28
+    op("AND", Immediate, 2, false, and), // 29
+    op("ROL", Accumulator, 2, false, rol), // 2A
+    ill("ANC", Immediate, 2, false, anc), // 2B
+    op("BIT", Absolute, 4, false, bit), // 2C
+    op("AND", Absolute, 4, false, and), // 2D
+    op("ROL", Absolute, 6, false, rol), // 2E
+    ill("RLA", Absolute, 6, false, rla), // 2F
+    op("BMI", Relative, 2, true, bmi), // 30
+    op("AND", IndirectY, 5, true, and), // 31
+    ill("JAM", Implied, 2, false, jam), // 32
+    ill("RLA", IndirectY, 8, false, rla), // 33
+    ill("NOP", ZeroPageX, 4, false, nop), // 34
+    op("AND", ZeroPageX, 4, false, and), // 35
+    op("ROL", ZeroPageX, 6, false, rol), // 36
+    ill("RLA", ZeroPageX, 6, false, rla), // 37
+    op("SEC", Implied, 2, false, sec), // 38
+    op("AND", AbsoluteY, 4, true, and), // 39
+    ill("NOP", Implied, 2, false, nop), // 3A
+    ill("RLA", AbsoluteY, 7, false, rla), // 3B
+    ill("NOP", AbsoluteX, 4, true, nop), // 3C
+    op("AND", AbsoluteX, 4, true, and), // 3D
+    op("ROL", AbsoluteX, 7, false, rol), // 3E
+    ill("RLA", AbsoluteX, 7, false, rla), // 3F
+    op("RTI", Implied, 6, false, rti), // 40
+    op("EOR", IndirectX, 6, false, eor), // 41
+    ill("JAM", Implied, 2, false, jam), // 42
+    ill("SRE", IndirectX, 8, false, sre), // 43
+    ill("NOP", ZeroPage, 3, false, nop), // 44
+    op("EOR", ZeroPage, 3, false, eor), // 45
+    op("LSR", ZeroPage, 5, false, lsr), // 46
+    ill("SRE", ZeroPage, 5, false, sre), // 47
+    op("PHA", Implied, 3, false, pha), // 48
+    op("EOR", Immediate, 2, false, eor), // 49
+    op("LSR", Accumulator, 2, false, lsr), // 4A
+    ill("ALR", Immediate, 2, false, alr), // 4B
+    op("JMP", Absolute, 3, false, jmp), // 4C
+    op("EOR", Absolute, 4, false, eor), // 4D
+    op("LSR", Absolute, 6, false, lsr), // 4E
+    ill("SRE", Absolute, 6, false, sre), // 4F
+    op("BVC", Relative, 2, true, bvc), // 50
+    op("EOR", IndirectY, 5, true, eor), // 51
+    ill("JAM", Implied, 2, false, jam), // 52
+    ill("SRE", IndirectY, 8, false, sre), // 53
+    ill("NOP", ZeroPageX, 4, false, nop), // 54
+    op("EOR", ZeroPageX, 4, false, eor), // 55
+    op("LSR", ZeroPageX, 6, false, lsr), // 56
+    ill("SRE", ZeroPageX, 6, false, sre), // 57
+    op("CLI", Implied, 2, false, cli), // 58
+    op("EOR", AbsoluteY, 4, true, eor), // 59
+    ill("NOP", Implied, 2, false, nop), // 5A
+    ill("SRE", AbsoluteY, 7, false, sre), // 5B
+    ill("NOP", AbsoluteX, 4, true, nop), // 5C
+    op("EOR", AbsoluteX, 4, true, eor), // 5D
+    op("LSR", AbsoluteX, 7, false, lsr), // 5E
+    ill("SRE", AbsoluteX, 7, false, sre), // 5F
+    op("RTS", Implied, 6, false, rts), // 60
+    op("ADC", IndirectX, 6, false, adc), // 61
+    ill("JAM", Implied, 2, false, jam), // 62
+    ill("RRA", IndirectX, 8, false, rra), // 63
+    ill("NOP", ZeroPage, 3, false, nop), // 64
+    op("ADC", ZeroPage, 3, false, adc), // 65
+    op("ROR", ZeroPage, 5, false, ror), // 66
+    ill("RRA", ZeroPage, 5, false, rra), // 67
+    op("PLA", Implied, 4, false, pla), // 68
+    op("ADC", Immediate, 2, false, adc), // 69
+    op("ROR", Accumulator, 2, false, ror), // 6A
+    ill("ARR", Immediate, 2, false, arr), // 6B
+    op("JMP", Indirect, 5, false, jmp), // 6C
+    op("ADC", Absolute, 4, false, adc), // 6D
+    op("ROR", Absolute, 6, false, ror), // 6E
+    ill("RRA", Absolute, 6, false, rra), // 6F
+    op("BVS", Relative, 2, true, bvs), // 70
+    op("ADC", IndirectY, 5, true, adc), // 71
+    ill("JAM", Implied, 2, false, jam), // 72
+    ill("RRA", IndirectY, 8, false, rra), // 73
+    ill("NOP", ZeroPageX, 4, false, nop), // 74
+    op("ADC", ZeroPageX, 4, false, adc), // 75
+    op("ROR", ZeroPageX, 6, false, ror), // 76
+    ill("RRA", ZeroPageX, 6, false, rra), // 77
+    op("SEI", Implied, 2, false, sei), // 78
+    op("ADC", AbsoluteY, 4, true, adc), // 79
+    ill("NOP", Implied, 2, false, nop), // 7A
+    ill("RRA", AbsoluteY, 7, false, rra), // 7B
+    ill("NOP", AbsoluteX, 4, true, nop), // 7C
+    op("ADC", AbsoluteX, 4, true, adc), // 7D
+    op("ROR", AbsoluteX, 7, false, ror), // 7E
+    ill("RRA", AbsoluteX, 7, false, rra), // 7F
+    ill("NOP", Immediate, 2, false, nop), // 80
+    op("STA", IndirectX, 6, false, sta), // 81
+    ill("NOP", Immediate, 2, false, nop), // 82
+    ill("SAX", IndirectX, 6, false, sax), // 83
+    op("STY", ZeroPage, 3, false, sty), // 84
+    op("STA", ZeroPage, 3, false, sta), // 85
+    op("STX", ZeroPage, 3, false, stx), // 86
+    ill("SAX", ZeroPage, 3, false, sax), // 87
+    op("DEY", Implied, 2, false, dey), // 88
+    ill("NOP", Immediate, 2, false, nop), // 89
+    op("TXA", Implied, 2, false, txa), // 8A
+    ill("ANE", Immediate, 2, false, ane), // 8B
+    op("STY", Absolute, 4, false, sty), // 8C
+    op("STA", Absolute, 4, false, sta), // 8D
+    op("STX", Absolute, 4, false, stx), // 8E
+    ill("SAX", Absolute, 4, false, sax), // 8F
+    op("BCC", Relative, 2, true, bcc), // 90
+    op("STA", IndirectY, 6, false, sta), // 91
+    ill("JAM", Implied, 2, false, jam), // 92
+    ill("SHA", IndirectY, 6, false, sha), // 93
+    op("STY", ZeroPageX, 4, false, sty), // 94
+    op("STA", ZeroPageX, 4, false, sta), // 95
+    op("STX", ZeroPageY, 4, false, stx), // 96
+    ill("SAX", ZeroPageY, 4, false, sax), // 97
+    op("TYA", Implied, 2, false, tya), // 98
+    op("STA", AbsoluteY, 5, false, sta), // 99
+    op("TXS", Implied, 2, false, txs), // 9A
+    ill("TAS", AbsoluteY, 5, false, tas), // 9B
+    ill("SHY", AbsoluteX, 5, false, shy), // 9C
+    op("STA", AbsoluteX, 5, false, sta), // 9D
+    ill("SHX", AbsoluteY, 5, false, shx), // 9E
+    ill("SHA", AbsoluteY, 5, false, sha), // 9F
+    op("LDY", Immediate, 2, false, ldy), // A0
+    op("LDA", IndirectX, 6, false, lda), // A1
+    op("LDX", Immediate, 2, false, ldx), // A2
+    ill("LAX", IndirectX, 6, false, lax), // A3
+    op("LDY", ZeroPage, 3, false, ldy), // A4
+    op("LDA", ZeroPage, 3, false, lda), // A5
+    op("LDX", ZeroPage, 3, false, ldx), // A6
+    ill("LAX", ZeroPage, 3, false, lax), // A7
+    op("TAY", Implied, 2, false, tay), // A8
+    op("LDA", Immediate, 2, false, lda), // A9
+    op("TAX", Implied, 2, false, tax), // AA
+    ill("LXA", Immediate, 2, false, lxa), // AB
+    op("LDY", Absolute, 4, false, ldy), // AC
+    op("LDA", Absolute, 4, false, lda), // AD
+    op("LDX", Absolute, 4, false, ldx), // AE
+    ill("LAX", Absolute, 4, false, lax), // AF
+    op("BCS", Relative, 2, true, bcs), // B0
+    op("LDA", IndirectY, 5, true, lda), // B1
+    ill("JAM", Implied, 2, false, jam), // B2
+    ill("LAX", IndirectY, 5, true, lax), // B3
+    op("LDY", ZeroPageX, 4, false, ldy), // B4
+    op("LDA", ZeroPageX, 4, false, lda), // B5
+    op("LDX", ZeroPageY, 4, false, ldx), // B6
+    ill("LAX", ZeroPageY, 4, false, lax), // B7
+    op("CLV", Implied, 2, false, clv), // B8
+    op("LDA", AbsoluteY, 4, true, lda), // B9
+    op("TSX", Implied, 2, false, tsx), // BA
+    ill("LAS", AbsoluteY, 4, true, las), // BB
+    op("LDY", AbsoluteX, 4, true, ldy), // BC
+    op("LDA", AbsoluteX, 4, true, lda), // BD
+    op("LDX", AbsoluteY, 4, true, ldx), // BE
+    ill("LAX", AbsoluteY, 4, true, lax), // BF
+    op("CPY", Immediate, 2, false, cpy), // C0
+    op("CMP", IndirectX, 6, false, cmp), // C1
+    ill("NOP", Immediate, 2, false, nop), // C2
+    ill("DCP", IndirectX, 8, false, dcp), // C3
+    op("CPY", ZeroPage, 3, false, cpy), // C4
+    op("CMP", ZeroPage, 3, false, cmp), // C5
+    op("DEC", ZeroPage, 5, false, dec), // C6
+    ill("DCP", ZeroPage, 5, false, dcp), // C7
+    op("INY", Implied, 2, false, iny), // C8
+    op("CMP", Immediate, 2, false, cmp), // C9
+    op("DEX", Implied, 2, false, dex), // CA
+    ill("SBX", Immediate, 2, false, sbx), // CB
+    op("CPY", Absolute, 4, false, cpy), // CC
+    op("CMP", Absolute, 4, false, cmp), // CD
+    op("DEC", Absolute, 6, false, dec), // CE
+    ill("DCP", Absolute, 6, false, dcp), // CF
+    op("BNE", Relative, 2, true, bne), // D0
+    op("CMP", IndirectY, 5, true, cmp), // D1
+    ill("JAM", Implied, 2, false, jam), // D2
+    ill("DCP", IndirectY, 8, false, dcp), // D3
+    ill("NOP", ZeroPageX, 4, false, nop), // D4
+    op("CMP", ZeroPageX, 4, false, cmp), // D5
+    op("DEC", ZeroPageX, 6, false, dec), // D6
+    ill("DCP", ZeroPageX, 6, false, dcp), // D7
+    op("CLD", Implied, 2, false, cld), // D8
+    op("CMP", AbsoluteY, 4, true, cmp), // D9
+    ill("NOP", Implied, 2, false, nop), // DA
+    ill("DCP", AbsoluteY, 7, false, dcp), // DB
+    ill("NOP", AbsoluteX, 4, true, nop), // DC
+    op("CMP", AbsoluteX, 4, true, cmp), // DD
+    op("DEC", AbsoluteX, 7, false, dec), // DE
+    ill("DCP", AbsoluteX, 7, false, dcp), // DF
+    op("CPX", Immediate, 2, false, cpx), // E0
+    op("SBC", IndirectX, 6, false, sbc), // E1
+    ill("NOP", Immediate, 2, false, nop), // E2
+    ill("ISC", IndirectX, 8, false, isc), // E3
+    op("CPX", ZeroPage, 3, false, cpx), // E4
+    op("SBC", ZeroPage, 3, false, sbc), // E5
+    op("INC", ZeroPage, 5, false, inc), // E6
+    ill("ISC", ZeroPage, 5, false, isc), // E7
+    op("INX", Implied, 2, false, inx), // E8
+    op("SBC", Immediate, 2, false, sbc), // E9
+    op("NOP", Implied, 2, false, nop), // EA
+    ill("USBC", Immediate, 2, false, sbc), // EB
+    op("CPX", Absolute, 4, false, cpx), // EC
+    op("SBC", Absolute, 4, false, sbc), // ED
+    op("INC", Absolute, 6, false, inc), // EE
+    ill("ISC", Absolute, 6, false, isc), // EF
+    op("BEQ", Relative, 2, true, beq), // F0
+    op("SBC", IndirectY, 5, true, sbc), // F1
+    ill("JAM", Implied, 2, false, jam), // F2
+    ill("ISC", IndirectY, 8, false, isc), // F3
+    ill("NOP", ZeroPageX, 4, false, nop), // F4
+    op("SBC", ZeroPageX, 4, false, sbc), // F5
+    op("INC", ZeroPageX, 6, false, inc), // F6
+    ill("ISC", ZeroPageX, 6, false, isc), // F7
+    op("SED", Implied, 2, false, sed), // F8
+    op("SBC", AbsoluteY, 4, true, sbc), // F9
+    ill("NOP", Implied, 2, false, nop), // FA
+    ill("ISC", AbsoluteY, 7, false, isc), // FB
+    ill("NOP", AbsoluteX, 4, true, nop), // FC
+    op("SBC", AbsoluteX, 4, true, sbc), // FD
+    op("INC", AbsoluteX, 7, false, inc), // FE
+    ill("ISC", AbsoluteX, 7, false, isc), // FF
+];
+
+// The 65C02 keeps every documented opcode, fixes the page cross cycle of the abs,X shifts and
+// fills the unused slots with its own instructions and fixed length NOPs
+pub static CMOS_OPCODES: [Opcode; 256] = [
+    op("BRK", Implied, 7, false, brk), // 00
+    op("ORA", IndirectX, 6, false, ora), // 01
+    op("NOP", Immediate, 2, false, nop), // 02
+    op("NOP", Implied, 1, false, nop_single_cycle), // 03
+    op("TSB", ZeroPage, 5, false, tsb), // 04
+    op("ORA", ZeroPage, 3, false, ora), // 05
+    op("ASL", ZeroPage, 5, false, asl), // 06
+    op("RMB0", ZeroPage, 5, false, rmb::<0>), // 07
+    op("PHP", Implied, 3, false, php), // 08
+    op("ORA", Immediate, 2, false, ora), // 09
+    op("ASL", Accumulator, 2, false, asl), // 0A
+    op("NOP", Implied, 1, false, nop_single_cycle), // 0B
+    op("TSB", Absolute, 6, false, tsb), // 0C
+    op("ORA", Absolute, 4, false, ora), // 0D
+    op("ASL", Absolute, 6, false, asl), // 0E
+    op("BBR0", ZeroPageRelative, 5, true, bbr::<0>), // 0F
+    op("BPL", Relative, 2, true, bpl), // 10
+    op("ORA", IndirectY, 5, true, ora), // 11
+    op("ORA", ZeroPageIndirect, 5, false, ora), // 12
+    op("NOP", Implied, 1, false, nop_single_cycle), // 13
+    op("TRB", ZeroPage, 5, false, trb), // 14
+    op("ORA", ZeroPageX, 4, false, ora), // 15
+    op("ASL", ZeroPageX, 6, false, asl), // 16
+    op("RMB1", ZeroPage, 5, false, rmb::<1>), // 17
+    op("CLC", Implied, 2, false, clc), // 18
+    op("ORA", AbsoluteY, 4, true, ora), // 19
+    op("INC", Accumulator, 2, false, inc), // 1A
+    op("NOP", Implied, 1, false, nop_single_cycle), // 1B
+    op("TRB", Absolute, 6, false, trb), // 1C
+    op("ORA", AbsoluteX, 4, true, ora), // 1D
+    op("ASL", AbsoluteX, 6, true, asl), // 1E
+    op("BBR1", ZeroPageRelative, 5, true, bbr::<1>), // 1F
+    op("JSR", Absolute, 6, false, jsr), // 20
+    op("AND", IndirectX, 6, false, and), // 21
+    op("NOP", Immediate, 2, false, nop), // 22
+    op("NOP", Implied, 1, false, nop_single_cycle), // 23
+    op("BIT", ZeroPage, 3, false, bit), // 24
+    op("AND", ZeroPage, 3, false, and), // 25
+    op("ROL", ZeroPage, 5, false, rol), // 26
+    op("RMB2", ZeroPage, 5, false, rmb::<2>), // 27
+    op("PLP", Implied, 4, false, plp), // 28
+    op("AND", Immediate, 2, false, and), // 29
+    op("ROL", Accumulator, 2, false, rol), // 2A
+    op("NOP", Implied, 1, false, nop_single_cycle), // 2B
+    op("BIT", Absolute, 4, false, bit), // 2C
+    op("AND", Absolute, 4, false, and), // 2D
+    op("ROL", Absolute, 6, false, rol), // 2E
+    op("BBR2", ZeroPageRelative, 5, true, bbr::<2>), // 2F
+    op("BMI", Relative, 2, true, bmi), // 30
+    op("AND", IndirectY, 5, true, and), // 31
+    op("AND", ZeroPageIndirect, 5, false, and), // 32
+    op("NOP", Implied, 1, false, nop_single_cycle), // 33
+    op("BIT", ZeroPageX, 4, false, bit), // 34
+    op("AND", ZeroPageX, 4, false, and), // 35
+    op("ROL", ZeroPageX, 6, false, rol), // 36
+    op("RMB3", ZeroPage, 5, false, rmb::<3>), // 37
+    op("SEC", Implied, 2, false, sec), // 38
+    op("AND", AbsoluteY, 4, true, and), // 39
+    op("DEC", Accumulator, 2, false, dec), // 3A
+    op("NOP", Implied, 1, false, nop_single_cycle), // 3B
+    op("BIT", AbsoluteX, 4, true, bit), // 3C
+    op("AND", AbsoluteX, 4, true, and), // 3D
+    op("ROL", AbsoluteX, 6, true, rol), // 3E
+    op("BBR3", ZeroPageRelative, 5, true, bbr::<3>), // 3F
+    op("RTI", Implied, 6, false, rti), // 40
+    op("EOR", IndirectX, 6, false, eor), // 41
+    op("NOP", Immediate, 2, false, nop), // 42
+    op("NOP", Implied, 1, false, nop_single_cycle), // 43
+    op("NOP", ZeroPage, 3, false, nop), // 44
+    op("EOR", ZeroPage, 3, false, eor), // 45
+    op("LSR", ZeroPage, 5, false, lsr), // 46
+    op("RMB4", ZeroPage, 5, false, rmb::<4>), // 47
+    op("PHA", Implied, 3, false, pha), // 48
+    op("EOR", Immediate, 2, false, eor), // 49
+    op("LSR", Accumulator, 2, false, lsr), // 4A
+    op("NOP", Implied, 1, false, nop_single_cycle), // 4B
+    op("JMP", Absolute, 3, false, jmp), // 4C
+    op("EOR", Absolute, 4, false, eor), // 4D
+    op("LSR", Absolute, 6, false, lsr), // 4E
+    op("BBR4", ZeroPageRelative, 5, true, bbr::<4>), // 4F
+    op("BVC", Relative, 2, true, bvc), // 50
+    op("EOR", IndirectY, 5, true, eor), // 51
+    op("EOR", ZeroPageIndirect, 5, false, eor), // 52
+    op("NOP", Implied, 1, false, nop_single_cycle), // 53
+    op("NOP", ZeroPageX, 4, false, nop), // 54
+    op("EOR", ZeroPageX, 4, false, eor), // 55
+    op("LSR", ZeroPageX, 6, false, lsr), // 56
+    op("RMB5", ZeroPage, 5, false, rmb::<5>), // 57
+    op("CLI", Implied, 2, false, cli), // 58
+    op("EOR", AbsoluteY, 4, true, eor), // 59
+    op("PHY", Implied, 3, false, phy), // 5A
+    op("NOP", Implied, 1, false, nop_single_cycle), // 5B
+    op("NOP", Absolute, 8, false, nop_long), // 5C
+    op("EOR", AbsoluteX, 4, true, eor), // 5D
+    op("LSR", AbsoluteX, 6, true, lsr), // 5E
+    op("BBR5", ZeroPageRelative, 5, true, bbr::<5>), // 5F
+    op("RTS", Implied, 6, false, rts), // 60
+    op("ADC", IndirectX, 6, false, adc), // 61
+    op("NOP", Immediate, 2, false, nop), // 62
+    op("NOP", Implied, 1, false, nop_single_cycle), // 63
+    op("STZ", ZeroPage, 3, false, stz), // 64
+    op("ADC", ZeroPage, 3, false, adc), // 65
+    op("ROR", ZeroPage, 5, false, ror), // 66
+    op("RMB6", ZeroPage, 5, false, rmb::<6>), // 67
+    op("PLA", Implied, 4, false, pla), // 68
+    op("ADC", Immediate, 2, false, adc), // 69
+    op("ROR", Accumulator, 2, false, ror), // 6A
+    op("NOP", Implied, 1, false, nop_single_cycle), // 6B
+    op("JMP", Indirect, 6, false, jmp), // 6C
+    op("ADC", Absolute, 4, false, adc), // 6D
+    op("ROR", Absolute, 6, false, ror), // 6E
+    op("BBR6", ZeroPageRelative, 5, true, bbr::<6>), // 6F
+    op("BVS", Relative, 2, true, bvs), // 70
+    op("ADC", IndirectY, 5, true, adc), // 71
+    op("ADC", ZeroPageIndirect, 5, false, adc), // 72
+    op("NOP", Implied, 1, false, nop_single_cycle), // 73
+    op("STZ", ZeroPageX, 4, false, stz), // 74
+    op("ADC", ZeroPageX, 4, false, adc), // 75
+    op("ROR", ZeroPageX, 6, false, ror), // 76
+    op("RMB7", ZeroPage, 5, false, rmb::<7>), // 77
+    op("SEI", Implied, 2, false, sei), // 78
+    op("ADC", AbsoluteY, 4, true, adc), // 79
+    op("PLY", Implied, 4, false, ply), // 7A
+    op("NOP", Implied, 1, false, nop_single_cycle), // 7B
+    op("JMP", AbsoluteXIndirect, 6, false, jmp_absolute_x_indirect), // 7C
+    op("ADC", AbsoluteX, 4, true, adc), // 7D
+    op("ROR", AbsoluteX, 6, true, ror), // 7E
+    op("BBR7", ZeroPageRelative, 5, true, bbr::<7>), // 7F
+    op("BRA", Relative, 2, true, bra), // 80
+    op("STA", IndirectX, 6, false, sta), // 81
+    op("NOP", Immediate, 2, false, nop), // 82
+    op("NOP", Implied, 1, false, nop_single_cycle), // 83
+    op("STY", ZeroPage, 3, false, sty), // 84
+    op("STA", ZeroPage, 3, false, sta), // 85
+    op("STX", ZeroPage, 3, false, stx), // 86
+    op("SMB0", ZeroPage, 5, false, smb::<0>), // 87
+    op("DEY", Implied, 2, false, dey), // 88
+    op("BIT", Immediate, 2, false, bit_immediate), // 89
+    op("TXA", Implied, 2, false, txa), // 8A
+    op("NOP", Implied, 1, false, nop_single_cycle), // 8B
+    op("STY", Absolute, 4, false, sty), // 8C
+    op("STA", Absolute, 4, false, sta), // 8D
+    op("STX", Absolute, 4, false, stx), // 8E
+    op("BBS0", ZeroPageRelative, 5, true, bbs::<0>), // 8F
+    op("BCC", Relative, 2, true, bcc), // 90
+    op("STA", IndirectY, 6, false, sta), // 91
+    op("STA", ZeroPageIndirect, 5, false, sta), // 92
+    op("NOP", Implied, 1, false, nop_single_cycle), // 93
+    op("STY", ZeroPageX, 4, false, sty), // 94
+    op("STA", ZeroPageX, 4, false, sta), // 95
+    op("STX", ZeroPageY, 4, false, stx), // 96
+    op("SMB1", ZeroPage, 5, false, smb::<1>), // 97
+    op("TYA", Implied, 2, false, tya), // 98
+    op("STA", AbsoluteY, 5, false, sta), // 99
+    op("TXS", Implied, 2, false, txs), // 9A
+    op("NOP", Implied, 1, false, nop_single_cycle), // 9B
+    op("STZ", Absolute, 4, false, stz), // 9C
+    op("STA", AbsoluteX, 5, false, sta), // 9D
+    op("STZ", AbsoluteX, 5, false, stz), // 9E
+    op("BBS1", ZeroPageRelative, 5, true, bbs::<1>), // 9F
+    op("LDY", Immediate, 2, false, ldy), // A0
+    op("LDA", IndirectX, 6, false, lda), // A1
+    op("LDX", Immediate, 2, false, ldx), // A2
+    op("NOP", Implied, 1, false, nop_single_cycle), // A3
+    op("LDY", ZeroPage, 3, false, ldy), // A4
+    op("LDA", ZeroPage, 3, false, lda), // A5
+    op("LDX", ZeroPage, 3, false, ldx), // A6
+    op("SMB2", ZeroPage, 5, false, smb::<2>), // A7
+    op("TAY", Implied, 2, false, tay), // A8
+    op("LDA", Immediate, 2, false, lda), // A9
+    op("TAX", Implied, 2, false, tax), // AA
+    op("NOP", Implied, 1, false, nop_single_cycle), // AB
+    op("LDY", Absolute, 4, false, ldy), // AC
+    op("LDA", Absolute, 4, false, lda), // AD
+    op("LDX", Absolute, 4, false, ldx), // AE
+    op("BBS2", ZeroPageRelative, 5, true, bbs::<2>), // AF
+    op("BCS", Relative, 2, true, bcs), // B0
+    op("LDA", IndirectY, 5, true, lda), // B1
+    op("LDA", ZeroPageIndirect, 5, false, lda), // B2
+    op("NOP", Implied, 1, false, nop_single_cycle), // B3
+    op("LDY", ZeroPageX, 4, false, ldy), // B4
+    op("LDA", ZeroPageX, 4, false, lda), // B5
+    op("LDX", ZeroPageY, 4, false, ldx), // B6
+    op("SMB3", ZeroPage, 5, false, smb::<3>), // B7
+    op("CLV", Implied, 2, false, clv), // B8
+    op("LDA", AbsoluteY, 4, true, lda), // B9
+    op("TSX", Implied, 2, false, tsx), // BA
+    op("NOP", Implied, 1, false, nop_single_cycle), // BB
+    op("LDY", AbsoluteX, 4, true, ldy), // BC
+    op("LDA", AbsoluteX, 4, true, lda), // BD
+    op("LDX", AbsoluteY, 4, true, ldx), // BE
+    op("BBS3", ZeroPageRelative, 5, true, bbs::<3>), // BF
+    op("CPY", Immediate, 2, false, cpy), // C0
+    op("CMP", IndirectX, 6, false, cmp), // C1
+    op("NOP", Immediate, 2, false, nop), // C2
+    op("NOP", Implied, 1, false, nop_single_cycle), // C3
+    op("CPY", ZeroPage, 3, false, cpy), // C4
+    op("CMP", ZeroPage, 3, false, cmp), // C5
+    op("DEC", ZeroPage, 5, false, dec), // C6
+    op("SMB4", ZeroPage, 5, false, smb::<4>), // C7
+    op("INY", Implied, 2, false, iny), // C8
+    op("CMP", Immediate, 2, false, cmp), // C9
+    op("DEX", Implied, 2, false, dex), // CA
+    op("WAI", Implied, 3, false, wai), // CB
+    op("CPY", Absolute, 4, false, cpy), // CC
+    op("CMP", Absolute, 4, false, cmp), // CD
+    op("DEC", Absolute, 6, false, dec), // CE
+    op("BBS4", ZeroPageRelative, 5, true, bbs::<4>), // CF
+    op("BNE", Relative, 2, true, bne), // D0
+    op("CMP", IndirectY, 5, true, cmp), // D1
+    op("CMP", ZeroPageIndirect, 5, false, cmp), // D2
+    op("NOP", Implied, 1, false, nop_single_cycle), // D3
+    op("NOP", ZeroPageX, 4, false, nop), // D4
+    op("CMP", ZeroPageX, 4, false, cmp), // D5
+    op("DEC", ZeroPageX, 6, false, dec), // D6
+    op("SMB5", ZeroPage, 5, false, smb::<5>), // D7
+    op("CLD", Implied, 2, false, cld), // D8
+    op("CMP", AbsoluteY, 4, true, cmp), // D9
+    op("PHX", Implied, 3, false, phx), // DA
+    op("STP", Implied, 3, false, stp), // DB
+    op("NOP", Absolute, 4, false, nop), // DC
+    op("CMP", AbsoluteX, 4, true, cmp), // DD
+    op("DEC", AbsoluteX, 7, false, dec), // DE
+    op("BBS5", ZeroPageRelative, 5, true, bbs::<5>), // DF
+    op("CPX", Immediate, 2, false, cpx), // E0
+    op("SBC", IndirectX, 6, false, sbc), // E1
+    op("NOP", Immediate, 2, false, nop), // E2
+    op("NOP", Implied, 1, false, nop_single_cycle), // E3
+    op("CPX", ZeroPage, 3, false, cpx), // E4
+    op("SBC", ZeroPage, 3, false, sbc), // E5
+    op("INC", ZeroPage, 5, false, inc), // E6
+    op("SMB6", ZeroPage, 5, false, smb::<6>), // E7
+    op("INX", Implied, 2, false, inx), // E8
+    op("SBC", Immediate, 2, false, sbc), // E9
+    op("NOP", Implied, 2, false, nop), // EA
+    op("NOP", Implied, 1, false, nop_single_cycle), // EB
+    op("CPX", Absolute, 4, false, cpx), // EC
+    op("SBC", Absolute, 4, false, sbc), // ED
+    op("INC", Absolute, 6, false, inc), // EE
+    op("BBS6", ZeroPageRelative, 5, true, bbs::<6>), // EF
+    op("BEQ", Relative, 2, true, beq), // F0
+    op("SBC", IndirectY, 5, true, sbc), // F1
+    op("SBC", ZeroPageIndirect, 5, false, sbc), // F2
+    op("NOP", Implied, 1, false, nop_single_cycle), // F3
+    op("NOP", ZeroPageX, 4, false, nop), // F4
+    op("SBC", ZeroPageX, 4, false, sbc), // F5
+    op("INC", ZeroPageX, 6, false, inc), // F6
+    op("SMB7", ZeroPage, 5, false, smb::<7>), // F7
+    op("SED", Implied, 2, false, sed), // F8
+    op("SBC", AbsoluteY, 4, true, sbc), // F9
+    op("PLX", Implied, 4, false, plx), // FA
+    op("NOP", Implied, 1, false, nop_single_cycle), // FB
+    op("NOP", Absolute, 4, false, nop), // FC
+    op("SBC", AbsoluteX, 4, true, sbc), // FD
+    op("INC", AbsoluteX, 7, false, inc), // FE
+    op("BBS7", ZeroPageRelative, 5, true, bbs::<7>), // FF
+];
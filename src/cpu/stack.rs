@@ -4,11 +4,31 @@ use crate::bus::Bus;
 impl CPU {
     pub(super) fn pull_byte_stack(&mut self, bus: &mut Bus) -> u8 {
         self.sp = self.sp.wrapping_add(1);
-        bus.read(0x0100 + self.sp as u16)
+        self.read_byte(bus, 0x0100 + self.sp as u16)
     }
 
     pub(super) fn push_byte_stack(&mut self, bus: &mut Bus, value: u8) {
-        bus.write(self.sp as u16 + 0x0100, value);
+        self.write_byte(bus, self.sp as u16 + 0x0100, value);
         self.sp = self.sp.wrapping_sub(1);
     }
+
+    // The JSRs that led to where the CPU is, innermost first, worked out from the return
+    // addresses on the stack. Anything else pushed is skipped by only counting words that
+    // point just past a JSR, so data that happens to look like one can still show up
+    pub fn call_stack(&self, bus: &mut Bus) -> Vec<u16> {
+        let mut calls = vec![];
+        let mut offset = self.sp as u16 + 1;
+        while offset < 0xFF {
+            let ret = u16::from_le_bytes([bus.peek(0x0100 + offset), bus.peek(0x0100 + offset + 1)]);
+            // A JSR pushes the address of its last byte
+            let call = ret.wrapping_sub(2);
+            if bus.peek(call) == 0x20 {
+                calls.push(call);
+                offset += 2;
+            } else {
+                offset += 1;
+            }
+        }
+        calls
+    }
 }
@@ -0,0 +1,67 @@
+use super::{
+    config::{CpuConfig, CpuVariant},
+    cpu::CPU,
+};
+use crate::snapshot::{StateReader, StateWriter};
+
+impl CPU {
+    // The registers, the interrupt lines, the cycle count and the config
+    pub fn save_state(&self, state: &mut StateWriter) {
+        state.u16(self.pc);
+        for value in [self.a, self.x, self.y, self.sp, self.status] {
+            state.u8(value);
+        }
+        for flag in [self.irq_line, self.nmi_line, self.nmi_pending, self.jammed, self.waiting] {
+            state.bool(flag);
+        }
+        state.u64(self.cycles);
+        self.config.save_state(state);
+    }
+
+    pub fn load_state(&mut self, state: &mut StateReader) -> Result<(), String> {
+        self.pc = state.u16()?;
+        self.a = state.u8()?;
+        self.x = state.u8()?;
+        self.y = state.u8()?;
+        self.sp = state.u8()?;
+        self.status = state.u8()?;
+        self.irq_line = state.bool()?;
+        self.nmi_line = state.bool()?;
+        self.nmi_pending = state.bool()?;
+        self.jammed = state.bool()?;
+        self.waiting = state.bool()?;
+        self.cycles = state.u64()?;
+        self.config.load_state(state)
+    }
+}
+
+impl CpuConfig {
+    // Everything but the tracer, which is watching this run rather than part of the machine
+    pub fn save_state(&self, state: &mut StateWriter) {
+        state.u8(match self.variant {
+            CpuVariant::Nmos6502 => 0,
+            CpuVariant::Wdc65C02 => 1,
+        });
+        state.bool(self.emulate_indirect_jmp_bug);
+        state.bool(self.undocumented_opcodes);
+        state.u8(self.ane_magic);
+        state.u8(self.lxa_magic);
+        state.bool(self.cycle_accurate);
+        state.f64(self.speed);
+    }
+
+    pub fn load_state(&mut self, state: &mut StateReader) -> Result<(), String> {
+        self.variant = match state.u8()? {
+            0 => CpuVariant::Nmos6502,
+            1 => CpuVariant::Wdc65C02,
+            variant => return Err(format!("unknown CPU variant {}", variant)),
+        };
+        self.emulate_indirect_jmp_bug = state.bool()?;
+        self.undocumented_opcodes = state.bool()?;
+        self.ane_magic = state.u8()?;
+        self.lxa_magic = state.u8()?;
+        self.cycle_accurate = state.bool()?;
+        self.speed = state.f64()?;
+        Ok(())
+    }
+}
@@ -17,6 +17,13 @@ impl CPU {
         self.set_status((self.a & 0b10000000) > 0, 7); // set n flag
     }
 
+    pub(super) fn adc_decimal_set_status(&mut self, is_carry: bool, is_zero: bool, is_over: bool, is_neg: bool) {
+        self.set_status(is_carry, 0); // set c flag
+        self.set_status(is_zero, 1); // set z flag
+        self.set_status(is_over, 6); // set v
+        self.set_status(is_neg, 7); // set n flag
+    }
+
     pub(super) fn and_set_status(&mut self) {
         self.set_status(self.a == 0, 1); // set z flag
         self.set_status((self.a & 0b10000000) > 0, 7); // set n flag
@@ -69,7 +76,7 @@ impl CPU {
     }
 
     pub(super) fn sbc_set_status(&mut self, carry_out: bool, is_over: bool) {
-        self.set_status(carry_out && !is_over, 0); // set c flag
+        self.set_status(carry_out, 0); // set c flag
         self.set_status(self.a == 0, 1); // set z flag
         self.set_status(is_over, 6); // set v
         self.set_status((self.a & 0b10000000) > 0, 7); // set n flag
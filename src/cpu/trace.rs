@@ -0,0 +1,197 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    fmt,
+    fs::File,
+    io::{self, BufWriter, Write},
+    rc::Rc,
+};
+
+use super::{addresing::AddrMode, cpu::Registers, disassembler::format_instruction};
+use crate::debug::symbols::SymbolTable;
+
+// One executed instruction, with the registers as they were before and after it
+#[derive(Clone, Debug, PartialEq)]
+pub struct InstructionTrace {
+    pub opcode: u8,
+    pub mnemonic: &'static str,
+    pub mode: AddrMode,
+    pub undocumented: bool,
+    // The opcode followed by its operands, `len` of them are used
+    pub bytes: [u8; 3],
+    pub len: u8,
+    // Where a memory operand resolved to, None for modes without one
+    pub effective_address: Option<u16>,
+    pub before: Registers,
+    pub after: Registers,
+    // Cycles the instruction took and the cycle count when it started
+    pub cycles: u32,
+    pub start_cycle: u64,
+}
+
+impl InstructionTrace {
+    pub fn operands(&self) -> &[u8] {
+        &self.bytes[1..self.len as usize]
+    }
+
+    // The nestest log layout that most emulators can produce: PC, the instruction bytes, the
+    // disassembly with undocumented opcodes marked by a *, then the registers and cycle count
+    // from before the instruction ran. With symbols the operands are named and the line ends
+    // with where the PC is, like `; main_loop+3`
+    pub fn format(&self, symbols: Option<&SymbolTable>) -> String {
+        let bytes: Vec<String> = self.bytes[..self.len as usize].iter().map(|b| format!("{:02X}", b)).collect();
+        let marker = if self.undocumented { '*' } else { ' ' };
+        let r = self.before;
+        let line = format!(
+            "{:04X}  {:<8} {}{:<31} A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} CYC:{}",
+            r.pc,
+            bytes.join(" "),
+            marker,
+            format_instruction(self.mnemonic, self.mode, self.operands(), r.pc, symbols),
+            r.a,
+            r.x,
+            r.y,
+            r.status,
+            r.sp,
+            self.start_cycle
+        );
+        match symbols.and_then(|symbols| symbols.label(r.pc)) {
+            Some(label) => format!("{}  ; {}", line, label),
+            None => line,
+        }
+    }
+}
+
+impl fmt::Display for InstructionTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format(None))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InterruptKind {
+    Irq,
+    Nmi,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TraceEvent {
+    Instruction(InstructionTrace),
+    // An interrupt sequence was taken, with the registers before it started
+    Interrupt {
+        kind: InterruptKind,
+        before: Registers,
+        start_cycle: u64,
+    },
+    // An undocumented opcode was skipped because `config.undocumented_opcodes` is off
+    UnknownOpcode { pc: u16, opcode: u8 },
+}
+
+impl TraceEvent {
+    pub fn format(&self, symbols: Option<&SymbolTable>) -> String {
+        match self {
+            TraceEvent::Instruction(ins) => ins.format(symbols),
+            TraceEvent::Interrupt { kind, before, start_cycle } => {
+                format!("{:04X}  {:?} CYC:{}", before.pc, kind, start_cycle)
+            }
+            TraceEvent::UnknownOpcode { pc, opcode } => format!("{:04X}  Unknown opcode {:02X}", pc, opcode),
+        }
+    }
+}
+
+impl fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format(None))
+    }
+}
+
+// Receives the CPU's trace events, set one with `config.tracer`. The events are only built
+// when a tracer is set so leaving it as None costs nothing
+pub trait Tracer {
+    fn trace(&mut self, event: &TraceEvent);
+}
+
+// Prints one line per event, naming addresses from `symbols` if it's set
+pub struct StdoutTracer {
+    pub symbols: Option<SymbolTable>,
+}
+
+impl StdoutTracer {
+    #[allow(clippy::should_implement_trait)]
+    pub fn default() -> Self {
+        Self { symbols: None }
+    }
+}
+
+impl Tracer for StdoutTracer {
+    fn trace(&mut self, event: &TraceEvent) {
+        println!("{}", event.format(self.symbols.as_ref()));
+    }
+}
+
+// Writes one line per event to a file
+pub struct FileTracer {
+    writer: BufWriter<File>,
+    symbols: Option<SymbolTable>,
+}
+
+impl FileTracer {
+    pub fn create(path: &str) -> io::Result<Self> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+            symbols: None,
+        })
+    }
+
+    // Names addresses in the trace from `symbols`
+    pub fn with_symbols(mut self, symbols: SymbolTable) -> Self {
+        self.symbols = Some(symbols);
+        self
+    }
+}
+
+impl Tracer for FileTracer {
+    fn trace(&mut self, event: &TraceEvent) {
+        // A trace is best effort, a failed write shouldn't stop the CPU
+        let _ = writeln!(self.writer, "{}", event.format(self.symbols.as_ref()));
+    }
+}
+
+// Keeps the last `capacity` events in memory. Clones share the same buffer, so keep one to
+// read the events back after handing the other to the CPU
+#[derive(Clone)]
+pub struct RingBuffer {
+    events: Rc<RefCell<VecDeque<TraceEvent>>>,
+    capacity: usize,
+}
+
+impl RingBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            events: Rc::new(RefCell::new(VecDeque::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    // The held events, oldest first
+    pub fn events(&self) -> Vec<TraceEvent> {
+        self.events.borrow().iter().cloned().collect()
+    }
+
+    pub fn clear(&self) {
+        self.events.borrow_mut().clear();
+    }
+}
+
+impl Tracer for RingBuffer {
+    fn trace(&mut self, event: &TraceEvent) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut events = self.events.borrow_mut();
+        if events.len() == self.capacity {
+            events.pop_front();
+        }
+        events.push_back(event.clone());
+    }
+}
@@ -0,0 +1,116 @@
+use std::{fmt::Write, fs, ops::RangeInclusive};
+
+use crate::debug::symbols::SymbolTable;
+
+// Flags recorded for each byte. CODE and DATA are the low two bits of an FCEUX code/data log,
+// the others are extra detail FCEUX doesn't keep, so they're only in the report and not saved
+pub const CODE: u8 = 0x01;
+pub const DATA: u8 = 0x02;
+pub const OPCODE: u8 = 0x04;
+pub const WRITTEN: u8 = 0x08;
+
+// How every byte of the address space has been used: fetched as an opcode or an operand,
+// read as data or written. Set one on the bus with `set_coverage` to start recording
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Coverage {
+    flags: Vec<u8>,
+}
+
+impl Coverage {
+    #[allow(clippy::should_implement_trait)]
+    pub fn default() -> Self {
+        Self { flags: vec![0; 0x10000] }
+    }
+
+    // A code/data log saved by `save` or FCEUX, whose first byte is for `start`. Only the code
+    // and data bits are kept, FCEUX uses the rest for the ROM bank and how a byte was reached
+    pub fn load(path: &str, start: u16) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| format!("couldn't read {}: {}", path, e))?;
+        if start as usize + bytes.len() > 0x10000 {
+            return Err(format!("{} doesn't fit after ${:04X}", path, start));
+        }
+        let mut coverage = Self::default();
+        for (flags, byte) in coverage.flags[start as usize..].iter_mut().zip(bytes) {
+            *flags = byte & (CODE | DATA);
+        }
+        Ok(coverage)
+    }
+
+    // Writes an FCEUX code/data log of `range`, one byte per address so a log of a ROM lines
+    // up with it. Only the code and data bits are set, which leaves the bank as 0
+    pub fn save(&self, path: &str, range: RangeInclusive<u16>) -> Result<(), String> {
+        let bytes: Vec<u8> = self.flags[*range.start() as usize..=*range.end() as usize].iter().map(|flags| flags & (CODE | DATA)).collect();
+        fs::write(path, bytes).map_err(|e| format!("couldn't write {}: {}", path, e))
+    }
+
+    pub fn flags(&self, addr: u16) -> u8 {
+        self.flags[addr as usize]
+    }
+
+    pub fn mark(&mut self, addr: u16, flags: u8) {
+        self.flags[addr as usize] |= flags;
+    }
+
+    // Adds what another run recorded
+    pub fn merge(&mut self, other: &Coverage) {
+        for (flags, other) in self.flags.iter_mut().zip(&other.flags) {
+            *flags |= other;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.flags.fill(0);
+    }
+
+    // Counts of the bytes in `range` with each flag and with none
+    pub fn summary(&self, range: RangeInclusive<u16>) -> String {
+        let flags = &self.flags[*range.start() as usize..=*range.end() as usize];
+        let count = |flag: u8| flags.iter().filter(|&&flags| flags & flag != 0).count();
+        format!(
+            "${:04X}-${:04X}: {} code ({} opcodes), {} data, {} written, {} unused",
+            range.start(),
+            range.end(),
+            count(CODE),
+            count(OPCODE),
+            count(DATA),
+            count(WRITTEN),
+            flags.iter().filter(|&&flags| flags == 0).count()
+        )
+    }
+
+    // The summary followed by one line per run of bytes used the same way, like
+    // `$C000-$C000  opcode` then `$C001-$C002  operand`, named from `symbols` where a run
+    // starts on a label
+    pub fn report(&self, range: RangeInclusive<u16>, symbols: Option<&SymbolTable>) -> String {
+        let mut text = self.summary(range.clone());
+        let mut start = *range.start();
+        loop {
+            let kind = describe(self.flags(start));
+            let mut end = start;
+            while end < *range.end() && describe(self.flags(end + 1)) == kind {
+                end += 1;
+            }
+            let _ = write!(text, "\n${:04X}-${:04X}  {}", start, end, kind);
+            if let Some(name) = symbols.and_then(|symbols| symbols.name_at(start)) {
+                let _ = write!(text, "  {}", name);
+            }
+            if end == *range.end() {
+                return text;
+            }
+            start = end + 1;
+        }
+    }
+}
+
+// How a byte was used, like `opcode`, `operand` or `data+written`. Code is split by whether
+// it was an opcode, which a log loaded from a file doesn't know, so its code all shows as
+// operands
+fn describe(flags: u8) -> String {
+    let code = if flags & OPCODE != 0 { "opcode" } else { "operand" };
+    let kinds: Vec<&str> = [(CODE, code), (DATA, "data"), (WRITTEN, "written")]
+        .into_iter()
+        .filter(|(flag, _)| flags & flag != 0)
+        .map(|(_, name)| name)
+        .collect();
+    if kinds.is_empty() { "unused".to_string() } else { kinds.join("+") }
+}
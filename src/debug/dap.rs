@@ -0,0 +1,653 @@
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, ErrorKind, Read, Write},
+    net::TcpListener,
+    path::Path,
+    sync::mpsc::{self, TryRecvError},
+    thread,
+};
+
+use super::{
+    debugger::{Debugger, StopReason},
+    expression::Expression,
+    json::Json,
+    rewind::Rewind,
+    symbols::SymbolTable,
+};
+use crate::{
+    bus::Bus,
+    cpu::{
+        assembler::{Assembler, Assembly},
+        cpu::CPU,
+    },
+};
+
+// Instructions run between checks for a pause or anything else the client sends
+const CHUNK: u64 = 10_000;
+
+// Steps that go on longer than this are given up on, so a JSR that never returns can't leave
+// the client waiting forever
+const STEP_LIMIT: u64 = 1_000_000;
+
+// There's only one CPU, so only one thread
+const THREAD: i64 = 1;
+
+// The `variablesReference`s of the two scopes
+const REGISTERS: i64 = 1;
+const FLAGS: i64 = 2;
+
+// A Debug Adapter Protocol server, so editors can debug programs running on the CPU. Like the
+// monitor and the gdb stub it's handed the CPU and bus of a machine and drives them through
+// a `Debugger`.
+//
+// The launch request takes either `source`, a file for the built in assembler whose line
+// numbers breakpoints can be set on, or `program`, a binary loaded at `address`. `symbols`
+// loads one or more symbol files, debug info from ca65 lets breakpoints be set in its source
+// files too. `start` sets the PC (the origin or load address if it's missing) and
+// `stopOnEntry` stops there rather than running. Snapshots are recorded from the launch on, so
+// the client can step back
+pub struct DapServer {
+    pub debugger: Debugger,
+    // Names for the call stack
+    pub symbols: SymbolTable,
+    // The source file and its assembly, when the program was launched from source
+    source: Option<(String, Assembly)>,
+    source_breakpoints: Vec<usize>,
+    instruction_breakpoints: Vec<usize>,
+    seq: i64,
+    launched: bool,
+    configured: bool,
+    stop_on_entry: bool,
+    running: bool,
+}
+
+impl DapServer {
+    #[allow(clippy::should_implement_trait)]
+    pub fn default() -> Self {
+        Self {
+            debugger: Debugger::default(),
+            symbols: SymbolTable::default(),
+            source: None,
+            source_breakpoints: vec![],
+            instruction_breakpoints: vec![],
+            seq: 1,
+            launched: false,
+            configured: false,
+            stop_on_entry: false,
+            running: false,
+        }
+    }
+
+    // Waits for one client on `addr`, like "127.0.0.1:4711", and serves it
+    pub fn listen(&mut self, cpu: &mut CPU, bus: &mut Bus, addr: &str) -> io::Result<()> {
+        let listener = TcpListener::bind(addr)?;
+        let (stream, _) = listener.accept()?;
+        stream.set_nodelay(true)?;
+        self.serve(cpu, bus, stream.try_clone()?, stream)
+    }
+
+    // Serves one client until it disconnects, reading requests from `input` and writing to
+    // `output`, which are stdin and stdout or both halves of a socket
+    pub fn serve(&mut self, cpu: &mut CPU, bus: &mut Bus, input: impl Read + Send + 'static, mut output: impl Write) -> io::Result<()> {
+        // Requests are read on their own thread so they can be seen while the program runs
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(input);
+            while let Ok(Some(message)) = read_message(&mut reader) {
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+
+        loop {
+            let request = if self.running {
+                match receiver.try_recv() {
+                    Ok(request) => request,
+                    Err(TryRecvError::Empty) => {
+                        let reason = self.debugger.run(cpu, bus, Some(CHUNK));
+                        if reason != StopReason::Limit {
+                            self.running = false;
+                            self.stopped(&mut output, reason)?;
+                        }
+                        continue;
+                    }
+                    Err(TryRecvError::Disconnected) => return Ok(()),
+                }
+            } else {
+                match receiver.recv() {
+                    Ok(request) => request,
+                    Err(_) => return Ok(()),
+                }
+            };
+            if !self.handle(cpu, bus, &mut output, &request)? {
+                return Ok(());
+            }
+        }
+    }
+
+    // Answers one request, false when the session is over
+    fn handle(&mut self, cpu: &mut CPU, bus: &mut Bus, output: &mut impl Write, request: &Json) -> io::Result<bool> {
+        let command = request.get("command").as_str().unwrap_or_default();
+        let args = request.get("arguments");
+        let was_running = self.running;
+        let started = self.launched && self.configured;
+        // Going backwards can fail, so it happens before the response
+        let mut went_back = None;
+        let result = match command {
+            "initialize" => Ok(capabilities()),
+            "launch" => self.launch(cpu, bus, args),
+            "setBreakpoints" => Ok(self.set_breakpoints(bus, args)),
+            "setInstructionBreakpoints" => Ok(self.set_instruction_breakpoints(bus, args)),
+            "setExceptionBreakpoints" => Ok(Json::object([("breakpoints", Json::Array(vec![]))])),
+            "configurationDone" => {
+                self.configured = true;
+                Ok(Json::Null)
+            }
+            "threads" => {
+                let thread = Json::object([("id", THREAD.into()), ("name", "6502".into())]);
+                Ok(Json::object([("threads", vec![thread].into())]))
+            }
+            "stackTrace" => Ok(self.stack_trace(cpu, bus)),
+            "scopes" => Ok(scopes()),
+            "variables" => Ok(variables(cpu, args)),
+            "setVariable" => set_variable(cpu, bus, args),
+            "readMemory" => self.read_memory(bus, args),
+            "writeMemory" => self.write_memory(bus, args),
+            "evaluate" => evaluate(cpu, bus, args),
+            "continue" => {
+                self.running = true;
+                Ok(Json::object([("allThreadsContinued", true.into())]))
+            }
+            "stepBack" => self.debugger.step_back(cpu, bus).map(|reason| {
+                went_back = Some(reason);
+                Json::Null
+            }),
+            "reverseContinue" => self.debugger.reverse_continue(cpu, bus).map(|reason| {
+                went_back = Some(reason);
+                Json::Null
+            }),
+            "next" | "stepIn" | "stepOut" | "pause" | "disconnect" | "terminate" => Ok(Json::Null),
+            _ => Err(format!("{} isn't supported", command)),
+        };
+        if command == "launch" && result.is_ok() {
+            self.launched = true;
+        }
+        self.respond(output, request, result)?;
+
+        // Events that have to come after the response
+        match command {
+            "initialize" => self.event(output, "initialized", Json::Null)?,
+            "launch" | "configurationDone" if !started && self.launched && self.configured => {
+                if self.stop_on_entry {
+                    let body = Json::object([("reason", "entry".into()), ("threadId", THREAD.into()), ("allThreadsStopped", true.into())]);
+                    self.event(output, "stopped", body)?;
+                } else {
+                    self.running = true;
+                }
+            }
+            "next" | "stepIn" | "stepOut" => {
+                self.running = false;
+                let reason = match command {
+                    "next" => self.debugger.step_over(cpu, bus, Some(STEP_LIMIT)),
+                    "stepIn" => self.debugger.step_into(cpu, bus),
+                    _ => self.debugger.step_out(cpu, bus, Some(STEP_LIMIT)),
+                };
+                self.stopped(output, reason)?;
+            }
+            "stepBack" | "reverseContinue" if let Some(reason) = went_back => {
+                self.running = false;
+                if reason == StopReason::Limit {
+                    let description = "Went back as far as the snapshots go";
+                    let body = Json::object([
+                        ("reason", "step".into()),
+                        ("threadId", THREAD.into()),
+                        ("allThreadsStopped", true.into()),
+                        ("description", description.into()),
+                        ("text", description.into()),
+                    ]);
+                    self.event(output, "stopped", body)?;
+                } else {
+                    self.stopped(output, reason)?;
+                }
+            }
+            "pause" if was_running => {
+                self.running = false;
+                let body = Json::object([("reason", "pause".into()), ("threadId", THREAD.into()), ("allThreadsStopped", true.into())]);
+                self.event(output, "stopped", body)?;
+            }
+            "disconnect" => return Ok(false),
+            "terminate" => {
+                self.event(output, "terminated", Json::Null)?;
+                return Ok(false);
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn launch(&mut self, cpu: &mut CPU, bus: &mut Bus, args: &Json) -> Result<Json, String> {
+        // Loaded first so `start` and `address` can be names
+        let symbols = match args.get("symbols") {
+            Json::String(path) => vec![Json::String(path.clone())],
+            files => files.as_array().to_vec(),
+        };
+        for path in symbols {
+            let table = SymbolTable::load(path.as_str().ok_or("symbols has to be file names")?)?;
+            self.symbols.merge(&table);
+        }
+
+        let origin = if let Some(path) = args.get("source").as_str() {
+            let text = fs::read_to_string(path).map_err(|e| format!("couldn't read {}: {}", path, e))?;
+            let assembly = Assembler::for_cpu(cpu).assemble(&text).map_err(|e| format!("{}: {}", path, e))?;
+            assembly.load(bus);
+            for (addr, name) in assembly.symbols.iter() {
+                self.symbols.insert(name, addr);
+            }
+            let origin = assembly.origin();
+            self.source = Some((path.to_string(), assembly));
+            origin
+        } else if let Some(path) = args.get("program").as_str() {
+            let addr = parse_address(args.get("address"), &self.symbols).unwrap_or(0);
+            if !cpu.load_rom(bus, path, addr) {
+                return Err(format!("couldn't load {}", path));
+            }
+            addr
+        } else {
+            return Err("launch needs a source or a program".to_string());
+        };
+
+        cpu.reset(bus);
+        cpu.pc = match args.get("start") {
+            Json::Null => origin,
+            start => parse_address(start, &self.symbols).ok_or("start isn't an address")?,
+        };
+        self.stop_on_entry = args.get("stopOnEntry").as_bool().unwrap_or(false);
+        if self.debugger.rewind.is_none() {
+            self.debugger.rewind = Some(Rewind::default());
+        }
+        Ok(Json::Null)
+    }
+
+    // Breakpoints on source lines, moved down to the next line with an instruction if
+    // there's nothing on the line itself
+    fn set_breakpoints(&mut self, bus: &mut Bus, args: &Json) -> Json {
+        for id in self.source_breakpoints.drain(..) {
+            self.debugger.remove(bus, id);
+        }
+
+        let path = args.get("source").get("path").as_str().unwrap_or_default();
+        let lines = self.source_lines(path);
+
+        let mut breakpoints = vec![];
+        for requested in args.get("breakpoints").as_array() {
+            let line = requested.get("line").as_i64().unwrap_or(0).max(0) as usize;
+            let found = lines.as_ref().and_then(|lines| lines.iter().find(|(number, _)| *number >= line));
+            let Some(&(line, addr)) = found else {
+                let message = if lines.is_some() { "there's no code here" } else { "not part of the program" };
+                breakpoints.push(unverified(line, message));
+                continue;
+            };
+            match self.add_breakpoint(addr, requested.get("condition")) {
+                Ok(id) => {
+                    self.source_breakpoints.push(id);
+                    breakpoints.push(Json::object([
+                        ("id", id.into()),
+                        ("verified", true.into()),
+                        ("line", line.into()),
+                        ("instructionReference", memory_reference(addr).into()),
+                    ]));
+                }
+                Err(message) => breakpoints.push(unverified(line, &message)),
+            }
+        }
+        Json::object([("breakpoints", breakpoints.into())])
+    }
+
+    // The line and address of every instruction in the file at `path`, in line order, from
+    // the launched source or debug info in the symbols. None if neither knows the file
+    fn source_lines(&self, path: &str) -> Option<Vec<(usize, u16)>> {
+        if let Some((source, assembly)) = &self.source
+            && same_file(source, path)
+        {
+            return Some(assembly.lines.clone());
+        }
+        let mut lines: Vec<(usize, u16)> =
+            self.symbols.lines().filter(|(_, source)| same_file(&source.file, path)).map(|(addr, source)| (source.line, addr)).collect();
+        lines.sort();
+        (!lines.is_empty()).then_some(lines)
+    }
+
+    // The file and line the instruction at `addr` came from
+    fn source_line(&self, addr: u16) -> Option<(&str, usize)> {
+        if let Some((path, assembly)) = &self.source
+            && let Some(line) = assembly.line_at(addr)
+        {
+            return Some((path, line));
+        }
+        self.symbols.line_at(addr).map(|source| (source.file.as_str(), source.line))
+    }
+
+    fn set_instruction_breakpoints(&mut self, bus: &mut Bus, args: &Json) -> Json {
+        for id in self.instruction_breakpoints.drain(..) {
+            self.debugger.remove(bus, id);
+        }
+
+        let mut breakpoints = vec![];
+        for requested in args.get("breakpoints").as_array() {
+            let addr = parse_address(requested.get("instructionReference"), &self.symbols)
+                .map(|addr| addr.wrapping_add(requested.get("offset").as_i64().unwrap_or(0) as u16));
+            let result = addr.ok_or("not an address".to_string()).and_then(|addr| self.add_breakpoint(addr, requested.get("condition")));
+            breakpoints.push(match result {
+                Ok(id) => {
+                    self.instruction_breakpoints.push(id);
+                    Json::object([("id", id.into()), ("verified", true.into())])
+                }
+                Err(message) => Json::object([("verified", false.into()), ("message", message.into())]),
+            });
+        }
+        Json::object([("breakpoints", breakpoints.into())])
+    }
+
+    fn add_breakpoint(&mut self, addr: u16, condition: &Json) -> Result<usize, String> {
+        let condition = match condition.as_str().filter(|condition| !condition.trim().is_empty()) {
+            Some(condition) => Some(Expression::parse(condition)?),
+            None => None,
+        };
+        let id = self.debugger.add_breakpoint(addr);
+        self.debugger.set_condition(id, condition);
+        Ok(id)
+    }
+
+    // The PC and then every JSR on the stack, frame ids are how deep each one is
+    fn stack_trace(&self, cpu: &CPU, bus: &mut Bus) -> Json {
+        let mut addresses = vec![cpu.pc];
+        addresses.extend(cpu.call_stack(bus));
+
+        let frames: Vec<Json> = addresses
+            .iter()
+            .enumerate()
+            .map(|(id, addr)| {
+                let name = match self.symbols.nearest(*addr) {
+                    Some((_, name)) => name.to_string(),
+                    None => format!("${:04X}", addr),
+                };
+                let mut frame = vec![
+                    ("id", id.into()),
+                    ("name", name.into()),
+                    ("line", 0.into()),
+                    ("column", 0.into()),
+                    ("instructionPointerReference", memory_reference(*addr).into()),
+                ];
+                if let Some((path, line)) = self.source_line(*addr) {
+                    let name = Path::new(path).file_name().map_or(path.to_string(), |name| name.to_string_lossy().to_string());
+                    frame[2] = ("line", line.into());
+                    frame[3] = ("column", 1.into());
+                    frame.push(("source", Json::object([("name", name.into()), ("path", path.into())])));
+                }
+                Json::object(frame)
+            })
+            .collect();
+        let total = frames.len();
+        Json::object([("stackFrames", frames.into()), ("totalFrames", total.into())])
+    }
+
+    fn read_memory(&self, bus: &mut Bus, args: &Json) -> Result<Json, String> {
+        let addr = self.memory_address(args)?;
+        let count = args.get("count").as_i64().unwrap_or(0).max(0) as usize;
+        // Nothing past $FFFF, rather than wrapping round
+        let readable = count.min(0x10000 - addr as usize);
+        let bytes: Vec<u8> = (0..readable).map(|i| bus.peek(addr + i as u16)).collect();
+        Ok(Json::object([
+            ("address", memory_reference(addr).into()),
+            ("data", base64_encode(&bytes).into()),
+            ("unreadableBytes", (count - readable).into()),
+        ]))
+    }
+
+    fn write_memory(&self, bus: &mut Bus, args: &Json) -> Result<Json, String> {
+        let addr = self.memory_address(args)?;
+        let data = base64_decode(args.get("data").as_str().unwrap_or_default()).ok_or("data isn't base64")?;
+        let writable = data.len().min(0x10000 - addr as usize);
+        for (i, byte) in data[..writable].iter().enumerate() {
+            bus.poke(addr + i as u16, *byte);
+        }
+        Ok(Json::object([("bytesWritten", writable.into())]))
+    }
+
+    // `memoryReference` plus `offset`
+    fn memory_address(&self, args: &Json) -> Result<u16, String> {
+        let addr = parse_address(args.get("memoryReference"), &self.symbols).ok_or("memoryReference isn't an address")?;
+        Ok(addr.wrapping_add(args.get("offset").as_i64().unwrap_or(0) as u16))
+    }
+
+    fn stopped(&mut self, output: &mut impl Write, reason: StopReason) -> io::Result<()> {
+        let (reason, description, hit) = match reason {
+            StopReason::Breakpoint { id, .. } => ("breakpoint", None, Some(id)),
+            StopReason::Watchpoint { id, .. } => ("data breakpoint", None, Some(id)),
+            StopReason::Stepped => ("step", None, None),
+            StopReason::Limit => ("pause", Some("The step didn't finish"), None),
+            StopReason::Jammed => ("exception", Some("A JAM opcode locked up the CPU"), None),
+            StopReason::Shutdown => {
+                self.event(output, "exited", Json::object([("exitCode", 0.into())]))?;
+                return self.event(output, "terminated", Json::Null);
+            }
+        };
+        let mut body = vec![("reason", reason.into()), ("threadId", THREAD.into()), ("allThreadsStopped", true.into())];
+        if let Some(description) = description {
+            body.push(("description", description.into()));
+            body.push(("text", description.into()));
+        }
+        if let Some(id) = hit {
+            body.push(("hitBreakpointIds", vec![id.into()].into()));
+        }
+        self.event(output, "stopped", Json::object(body))
+    }
+
+    fn respond(&mut self, output: &mut impl Write, request: &Json, result: Result<Json, String>) -> io::Result<()> {
+        let mut response = vec![
+            ("seq", self.take_seq()),
+            ("type", "response".into()),
+            ("request_seq", request.get("seq").clone()),
+            ("success", result.is_ok().into()),
+            ("command", request.get("command").clone()),
+        ];
+        match result {
+            Ok(Json::Null) => {}
+            Ok(body) => response.push(("body", body)),
+            Err(message) => response.push(("message", message.into())),
+        }
+        write_message(output, &Json::object(response))
+    }
+
+    fn event(&mut self, output: &mut impl Write, event: &str, body: Json) -> io::Result<()> {
+        let mut message = vec![("seq", self.take_seq()), ("type", "event".into()), ("event", event.into())];
+        if body != Json::Null {
+            message.push(("body", body));
+        }
+        write_message(output, &Json::object(message))
+    }
+
+    fn take_seq(&mut self) -> Json {
+        self.seq += 1;
+        (self.seq - 1).into()
+    }
+}
+
+fn capabilities() -> Json {
+    Json::object([
+        ("supportsConfigurationDoneRequest", true.into()),
+        ("supportsConditionalBreakpoints", true.into()),
+        ("supportsInstructionBreakpoints", true.into()),
+        ("supportsSetVariable", true.into()),
+        ("supportsEvaluateForHovers", true.into()),
+        ("supportsReadMemoryRequest", true.into()),
+        ("supportsWriteMemoryRequest", true.into()),
+        ("supportsTerminateRequest", true.into()),
+        ("supportsStepBack", true.into()),
+    ])
+}
+
+fn scopes() -> Json {
+    let scope = |name: &str, reference: i64| {
+        Json::object([("name", name.into()), ("variablesReference", reference.into()), ("expensive", false.into())])
+    };
+    Json::object([("scopes", vec![scope("Registers", REGISTERS), scope("Flags", FLAGS)].into())])
+}
+
+// The registers are the same in every frame, nothing else is known about the older ones
+fn variables(cpu: &CPU, args: &Json) -> Json {
+    let variable = |name: &str, value: String| {
+        Json::object([("name", name.into()), ("value", value.into()), ("variablesReference", 0.into())])
+    };
+    let variables = match args.get("variablesReference").as_i64() {
+        Some(REGISTERS) => vec![
+            variable("A", format!("${:02X}", cpu.read_acc())),
+            variable("X", format!("${:02X}", cpu.read_x())),
+            variable("Y", format!("${:02X}", cpu.read_y())),
+            variable("SP", format!("${:02X}", cpu.read_sp())),
+            variable("P", format!("${:02X}", cpu.read_status())),
+            variable("PC", format!("${:04X}", cpu.pc)),
+        ],
+        Some(FLAGS) => FLAG_NAMES.iter().map(|(name, bit)| variable(name, ((cpu.read_status() >> bit) & 1).to_string())).collect(),
+        _ => vec![],
+    };
+    Json::object([("variables", variables.into())])
+}
+
+const FLAG_NAMES: [(&str, u8); 7] = [("N", 7), ("V", 6), ("B", 4), ("D", 3), ("I", 2), ("Z", 1), ("C", 0)];
+
+// The new value can be any expression, so `$10` and `A + 1` both work
+fn set_variable(cpu: &mut CPU, bus: &mut Bus, args: &Json) -> Result<Json, String> {
+    let value = Expression::parse(args.get("value").as_str().unwrap_or_default())?.eval(cpu, bus, 0);
+    let name = args.get("name").as_str().unwrap_or_default();
+    let shown = match (args.get("variablesReference").as_i64(), name) {
+        (Some(REGISTERS), "PC") => {
+            cpu.pc = value as u16;
+            format!("${:04X}", cpu.pc)
+        }
+        (Some(REGISTERS), register) => {
+            match register {
+                "A" => cpu.write_acc(value as u8),
+                "X" => cpu.write_x(value as u8),
+                "Y" => cpu.write_y(value as u8),
+                "SP" => cpu.write_sp(value as u8),
+                "P" => cpu.write_status(value as u8),
+                _ => return Err(format!("unknown register {}", name)),
+            }
+            format!("${:02X}", value as u8)
+        }
+        (Some(FLAGS), flag) => {
+            let (_, bit) = FLAG_NAMES.iter().find(|(name, _)| *name == flag).ok_or(format!("unknown flag {}", flag))?;
+            let set = (value != 0) as u8;
+            cpu.write_status(cpu.read_status() & !(1 << bit) | set << bit);
+            set.to_string()
+        }
+        _ => return Err(format!("unknown variable {}", name)),
+    };
+    Ok(Json::object([("value", shown.into())]))
+}
+
+fn evaluate(cpu: &CPU, bus: &mut Bus, args: &Json) -> Result<Json, String> {
+    let value = Expression::parse(args.get("expression").as_str().unwrap_or_default())?.eval(cpu, bus, 0);
+    let result = match value {
+        0..=0xFF => format!("${:02X} ({})", value, value),
+        0x100..=0xFFFF => format!("${:04X} ({})", value, value),
+        _ => value.to_string(),
+    };
+    Ok(Json::object([("result", result.into()), ("variablesReference", 0.into())]))
+}
+
+// An address as a number or a string: hex with `0x` or `$`, decimal, or a symbol
+fn parse_address(value: &Json, symbols: &SymbolTable) -> Option<u16> {
+    if let Some(number) = value.as_i64() {
+        return u16::try_from(number).ok();
+    }
+    let text = value.as_str()?.trim();
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix('$')) {
+        return u16::from_str_radix(hex, 16).ok();
+    }
+    text.parse().ok().or_else(|| symbols.address_of(text))
+}
+
+fn memory_reference(addr: u16) -> String {
+    format!("0x{:04X}", addr)
+}
+
+fn unverified(line: usize, message: &str) -> Json {
+    Json::object([("verified", false.into()), ("line", line.into()), ("message", message.into())])
+}
+
+// Editors send absolute paths, the launch configuration might not have
+fn same_file(a: &str, b: &str) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+// Messages are JSON with a `Content-Length` header, None when the client has gone
+fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Json>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.trim().eq_ignore_ascii_case("Content-Length")
+        {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+    let length = length.ok_or(io::Error::new(ErrorKind::InvalidData, "missing Content-Length"))?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Json::parse(&String::from_utf8_lossy(&body)).map(Some).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+}
+
+fn write_message(output: &mut impl Write, message: &Json) -> io::Result<()> {
+    let text = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", text.len(), text)?;
+    output.flush()
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut text = String::new();
+    for chunk in bytes.chunks(3) {
+        let value = chunk.iter().enumerate().fold(0u32, |value, (i, byte)| value | (*byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(BASE64[(value >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let digits: Vec<u32> = text
+        .bytes()
+        .filter(|c| *c != b'=' && !c.is_ascii_whitespace())
+        .map(|c| BASE64.iter().position(|digit| *digit == c).map(|digit| digit as u32))
+        .collect::<Option<_>>()?;
+    let mut bytes = vec![];
+    for chunk in digits.chunks(4) {
+        if chunk.len() == 1 {
+            return None;
+        }
+        let value = chunk.iter().enumerate().fold(0, |value, (i, digit)| value | digit << (18 - 6 * i));
+        for i in 0..chunk.len() - 1 {
+            bytes.push((value >> (16 - 8 * i)) as u8);
+        }
+    }
+    Some(bytes)
+}
@@ -0,0 +1,315 @@
+use std::ops::RangeInclusive;
+
+use super::{expression::Expression, rewind::Rewind};
+use crate::{
+    bus::Bus,
+    cpu::{cpu::CPU, opcodes::opcode_table},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    // Either a read or a write
+    Access,
+    // An instruction starting in the range
+    Execute,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Breakpoint {
+    pub id: usize,
+    pub address: u16,
+    pub enabled: bool,
+    // Removed the first time it stops
+    pub temporary: bool,
+    // Times the CPU has reached it, whether or not the condition let it stop
+    pub hits: u32,
+    // Only stops when this is true
+    pub condition: Option<Expression>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Watchpoint {
+    pub id: usize,
+    pub range: RangeInclusive<u16>,
+    pub kind: WatchKind,
+    pub enabled: bool,
+    // Times it has been triggered, whether or not the condition let it stop
+    pub hits: u32,
+    // Only stops when this is true, checked after the instruction that triggered it
+    pub condition: Option<Expression>,
+}
+
+// Why the debugger handed control back
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    // About to run the instruction at `pc`
+    Breakpoint { id: usize, pc: u16 },
+    // The instruction that made the access has finished, `kind` is the access that was seen
+    // and `value` the byte read or written (the opcode for Execute)
+    Watchpoint { id: usize, kind: WatchKind, addr: u16, value: u8 },
+    // A step finished
+    Stepped,
+    // Ran the most instructions it was allowed to without stopping
+    Limit,
+    // A JAM opcode locked up the CPU
+    Jammed,
+    // A device asked for a shutdown
+    Shutdown,
+}
+
+// Breakpoints, watchpoints and stepping for a CPU and whatever machine is on its bus. It
+// doesn't own either, so it works with any machine that can hand them over. Devices are only
+// ticked if `config.cycle_accurate` is set, the same as calling `cpu.step` directly.
+// Breakpoints and watchpoints share one set of ids. Stepping backwards needs `rewind` set,
+// which takes its snapshots as the debugger runs
+pub struct Debugger {
+    breakpoints: Vec<Breakpoint>,
+    watchpoints: Vec<Watchpoint>,
+    next_id: usize,
+    pub rewind: Option<Rewind>,
+}
+
+impl Debugger {
+    #[allow(clippy::should_implement_trait)]
+    pub fn default() -> Self {
+        Self {
+            breakpoints: vec![],
+            watchpoints: vec![],
+            next_id: 1,
+            rewind: None,
+        }
+    }
+
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    pub fn add_breakpoint(&mut self, address: u16) -> usize {
+        self.insert_breakpoint(address, false)
+    }
+
+    pub fn add_temporary_breakpoint(&mut self, address: u16) -> usize {
+        self.insert_breakpoint(address, true)
+    }
+
+    fn insert_breakpoint(&mut self, address: u16, temporary: bool) -> usize {
+        let id = self.take_id();
+        self.breakpoints.push(Breakpoint { id, address, enabled: true, temporary, hits: 0, condition: None });
+        id
+    }
+
+    // Read and write watchpoints are registered with the bus so it can catch the accesses
+    pub fn add_watchpoint(&mut self, bus: &mut Bus, range: RangeInclusive<u16>, kind: WatchKind) -> usize {
+        let id = self.take_id();
+        let watchpoint = Watchpoint { id, range, kind, enabled: true, hits: 0, condition: None };
+        watch(bus, &watchpoint);
+        self.watchpoints.push(watchpoint);
+        id
+    }
+
+    fn take_id(&mut self) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    // Removes the breakpoint or watchpoint with `id`, false if there isn't one
+    pub fn remove(&mut self, bus: &mut Bus, id: usize) -> bool {
+        let count = self.breakpoints.len() + self.watchpoints.len();
+        self.breakpoints.retain(|breakpoint| breakpoint.id != id);
+        self.watchpoints.retain(|watchpoint| watchpoint.id != id);
+        bus.unwatch(id);
+        self.breakpoints.len() + self.watchpoints.len() != count
+    }
+
+    pub fn set_enabled(&mut self, bus: &mut Bus, id: usize, enabled: bool) -> bool {
+        if let Some(breakpoint) = self.breakpoints.iter_mut().find(|breakpoint| breakpoint.id == id) {
+            breakpoint.enabled = enabled;
+            return true;
+        }
+        let Some(watchpoint) = self.watchpoints.iter_mut().find(|watchpoint| watchpoint.id == id) else {
+            return false;
+        };
+        watchpoint.enabled = enabled;
+        bus.unwatch(id);
+        if enabled {
+            watch(bus, watchpoint);
+        }
+        true
+    }
+
+    // Sets or clears the condition of a breakpoint or watchpoint, false if there isn't one
+    // with `id`. The condition sees the hit count including the current hit
+    pub fn set_condition(&mut self, id: usize, condition: Option<Expression>) -> bool {
+        if let Some(breakpoint) = self.breakpoints.iter_mut().find(|breakpoint| breakpoint.id == id) {
+            breakpoint.condition = condition;
+            return true;
+        }
+        if let Some(watchpoint) = self.watchpoints.iter_mut().find(|watchpoint| watchpoint.id == id) {
+            watchpoint.condition = condition;
+            return true;
+        }
+        false
+    }
+
+    // Runs until a breakpoint or watchpoint stops it, or `limit` instructions have run. A
+    // breakpoint on the instruction it starts at doesn't stop it, so it can carry on from one
+    pub fn run(&mut self, cpu: &mut CPU, bus: &mut Bus, limit: Option<u64>) -> StopReason {
+        self.run_until(cpu, bus, limit, |_, _| false)
+    }
+
+    // Runs one instruction, or the interrupt sequence if one is pending
+    pub fn step_into(&mut self, cpu: &mut CPU, bus: &mut Bus) -> StopReason {
+        self.run_until(cpu, bus, Some(1), |_, _| true)
+    }
+
+    // Like `step_into`, but a JSR runs until the subroutine has returned to the next
+    // instruction
+    pub fn step_over(&mut self, cpu: &mut CPU, bus: &mut Bus, limit: Option<u64>) -> StopReason {
+        if self.mnemonic_at(cpu, bus) != "JSR" {
+            return self.step_into(cpu, bus);
+        }
+        let ret = cpu.pc.wrapping_add(3);
+        let sp = cpu.read_sp();
+        // Checking the stack too stops a recursive call returning to the same place early
+        self.run_until(cpu, bus, limit, |cpu, _| cpu.pc == ret && cpu.read_sp() >= sp)
+    }
+
+    // Runs until an RTS or RTI returns from the subroutine or interrupt handler the CPU is in
+    pub fn step_out(&mut self, cpu: &mut CPU, bus: &mut Bus, limit: Option<u64>) -> StopReason {
+        let sp = cpu.read_sp();
+        // Calls made on the way only bring the stack back to where it started, returning
+        // from this one takes it above
+        self.run_until(cpu, bus, limit, |cpu, mnemonic| matches!(mnemonic, "RTS" | "RTI") && cpu.read_sp() > sp)
+    }
+
+    // Goes back to the start of the instruction before this one
+    pub fn step_back(&mut self, cpu: &mut CPU, bus: &mut Bus) -> Result<StopReason, String> {
+        self.rewind.as_mut().ok_or("rewind isn't being recorded")?.step_back(cpu, bus)?;
+        Ok(StopReason::Stepped)
+    }
+
+    // Runs backwards to the last time an enabled breakpoint was reached, or Limit if it got
+    // to the oldest snapshot first. Conditions are ignored and hits aren't counted
+    pub fn reverse_continue(&mut self, cpu: &mut CPU, bus: &mut Bus) -> Result<StopReason, String> {
+        let breakpoints: Vec<(usize, u16)> =
+            self.breakpoints.iter().filter(|breakpoint| breakpoint.enabled).map(|breakpoint| (breakpoint.id, breakpoint.address)).collect();
+        let rewind = self.rewind.as_mut().ok_or("rewind isn't being recorded")?;
+        if !rewind.back_to(cpu, bus, |cpu| breakpoints.iter().any(|&(_, address)| address == cpu.pc))? {
+            return Ok(StopReason::Limit);
+        }
+        let &(id, pc) = breakpoints.iter().find(|&&(_, address)| address == cpu.pc).unwrap();
+        Ok(StopReason::Breakpoint { id, pc })
+    }
+
+    fn mnemonic_at(&self, cpu: &CPU, bus: &mut Bus) -> &'static str {
+        opcode_table(cpu.config.variant)[bus.peek(cpu.pc) as usize].mnemonic
+    }
+
+    // Steps until `done` says to stop, it's given the CPU after each instruction and the
+    // mnemonic of the instruction that ran
+    fn run_until(
+        &mut self,
+        cpu: &mut CPU,
+        bus: &mut Bus,
+        limit: Option<u64>,
+        mut done: impl FnMut(&CPU, &'static str) -> bool,
+    ) -> StopReason {
+        // Anything seen outside the debugger isn't a reason to stop now
+        bus.take_watch_hit();
+
+        let mut count = 0;
+        loop {
+            // Checked before the limit, running again in chunks would skip a breakpoint the
+            // last chunk ended on
+            if count > 0
+                && let Some(reason) = self.check_pc(cpu, bus)
+            {
+                return reason;
+            }
+            if limit.is_some_and(|limit| count >= limit) {
+                return StopReason::Limit;
+            }
+
+            if let Some(rewind) = &mut self.rewind {
+                rewind.record(cpu, bus);
+            }
+            let mnemonic = self.mnemonic_at(cpu, bus);
+            cpu.step(bus, 1);
+            count += 1;
+
+            if let Some(reason) = self.check_watch_hit(cpu, bus) {
+                return reason;
+            }
+            if cpu.is_jammed() {
+                return StopReason::Jammed;
+            }
+            if bus.shutdown_requested() {
+                return StopReason::Shutdown;
+            }
+            if done(cpu, mnemonic) {
+                return StopReason::Stepped;
+            }
+        }
+    }
+
+    // Breakpoints and execute watchpoints on the instruction about to run
+    fn check_pc(&mut self, cpu: &CPU, bus: &mut Bus) -> Option<StopReason> {
+        let pc = cpu.pc;
+        let mut stop = None;
+        for (index, breakpoint) in self.breakpoints.iter_mut().enumerate() {
+            if breakpoint.enabled && breakpoint.address == pc {
+                breakpoint.hits += 1;
+                if stop.is_none() && passes(&breakpoint.condition, cpu, bus, breakpoint.hits) {
+                    stop = Some((index, breakpoint.id));
+                }
+            }
+        }
+        if let Some((index, id)) = stop {
+            if self.breakpoints[index].temporary {
+                self.breakpoints.remove(index);
+            }
+            return Some(StopReason::Breakpoint { id, pc });
+        }
+
+        let mut stop = None;
+        for watchpoint in self.watchpoints.iter_mut() {
+            if watchpoint.enabled && watchpoint.kind == WatchKind::Execute && watchpoint.range.contains(&pc) {
+                watchpoint.hits += 1;
+                if stop.is_none() && passes(&watchpoint.condition, cpu, bus, watchpoint.hits) {
+                    stop = Some(watchpoint.id);
+                }
+            }
+        }
+        stop.map(|id| StopReason::Watchpoint { id, kind: WatchKind::Execute, addr: pc, value: bus.peek(pc) })
+    }
+
+    fn check_watch_hit(&mut self, cpu: &CPU, bus: &mut Bus) -> Option<StopReason> {
+        let hit = bus.take_watch_hit()?;
+        let watchpoint = self.watchpoints.iter_mut().find(|watchpoint| watchpoint.id == hit.id)?;
+        watchpoint.hits += 1;
+        if !passes(&watchpoint.condition, cpu, bus, watchpoint.hits) {
+            return None;
+        }
+        let kind = if hit.write { WatchKind::Write } else { WatchKind::Read };
+        Some(StopReason::Watchpoint { id: hit.id, kind, addr: hit.addr, value: hit.value })
+    }
+}
+
+fn passes(condition: &Option<Expression>, cpu: &CPU, bus: &mut Bus, hits: u32) -> bool {
+    condition.as_ref().is_none_or(|condition| condition.is_true(cpu, bus, hits))
+}
+
+fn watch(bus: &mut Bus, watchpoint: &Watchpoint) {
+    let reads = matches!(watchpoint.kind, WatchKind::Read | WatchKind::Access);
+    let writes = matches!(watchpoint.kind, WatchKind::Write | WatchKind::Access);
+    if watchpoint.enabled && (reads || writes) {
+        bus.watch(watchpoint.id, watchpoint.range.clone(), reads, writes);
+    }
+}
@@ -0,0 +1,258 @@
+use std::fmt;
+
+use crate::{bus::Bus, cpu::cpu::CPU};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Register {
+    A,
+    X,
+    Y,
+    Sp,
+    P,
+    Pc,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Number(i64),
+    Register(Register),
+    // A status flag as 0 or 1, given by its bit
+    Flag(u8),
+    Cycles,
+    Hits,
+    Byte(Box<Node>),
+    Word(Box<Node>),
+    Unary(&'static str, Box<Node>),
+    Binary(&'static str, Box<Node>, Box<Node>),
+}
+
+// A condition for a breakpoint or watchpoint, like `A == 0 && hits >= 300`. It can use the
+// registers (A, X, Y, SP, P, PC), the flags (C, Z, I, D, B, V, N), bytes and little endian
+// words of memory (`[$70]`, `w[$FFFC]`), `cycles` and `hits`, with C style arithmetic,
+// comparison and boolean operators. Anything that isn't zero counts as true
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expression {
+    node: Node,
+    text: String,
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+impl Expression {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut parser = Parser { text: text.as_bytes(), pos: 0 };
+        let node = parser.binary(0)?;
+        parser.skip_space();
+        if parser.pos < parser.text.len() {
+            return Err(format!("unexpected '{}'", &text[parser.pos..]));
+        }
+        Ok(Self { node, text: text.trim().to_string() })
+    }
+
+    // Memory is read with `bus.peek` so evaluating a condition never disturbs a device
+    pub fn eval(&self, cpu: &CPU, bus: &mut Bus, hits: u32) -> i64 {
+        eval(&self.node, cpu, bus, hits)
+    }
+
+    pub fn is_true(&self, cpu: &CPU, bus: &mut Bus, hits: u32) -> bool {
+        self.eval(cpu, bus, hits) != 0
+    }
+}
+
+fn eval(node: &Node, cpu: &CPU, bus: &mut Bus, hits: u32) -> i64 {
+    match node {
+        Node::Number(value) => *value,
+        Node::Register(register) => match register {
+            Register::A => cpu.read_acc() as i64,
+            Register::X => cpu.read_x() as i64,
+            Register::Y => cpu.read_y() as i64,
+            Register::Sp => cpu.read_sp() as i64,
+            Register::P => cpu.read_status() as i64,
+            Register::Pc => cpu.pc as i64,
+        },
+        Node::Flag(bit) => ((cpu.read_status() >> bit) & 1) as i64,
+        Node::Cycles => cpu.read_cycles() as i64,
+        Node::Hits => hits as i64,
+        Node::Byte(addr) => {
+            let addr = eval(addr, cpu, bus, hits) as u16;
+            bus.peek(addr) as i64
+        }
+        Node::Word(addr) => {
+            let addr = eval(addr, cpu, bus, hits) as u16;
+            u16::from_le_bytes([bus.peek(addr), bus.peek(addr.wrapping_add(1))]) as i64
+        }
+        Node::Unary(op, value) => {
+            let value = eval(value, cpu, bus, hits);
+            match *op {
+                "!" => (value == 0) as i64,
+                "-" => value.wrapping_neg(),
+                _ => !value,
+            }
+        }
+        Node::Binary(op, left, right) => {
+            let left = eval(left, cpu, bus, hits);
+            // Only look at the right hand side when it matters, so `[$70]` style reads
+            // after a false `&&` aren't made
+            match *op {
+                "&&" => return (left != 0 && eval(right, cpu, bus, hits) != 0) as i64,
+                "||" => return (left != 0 || eval(right, cpu, bus, hits) != 0) as i64,
+                _ => {}
+            }
+            let right = eval(right, cpu, bus, hits);
+            match *op {
+                "|" => left | right,
+                "^" => left ^ right,
+                "&" => left & right,
+                "==" => (left == right) as i64,
+                "!=" => (left != right) as i64,
+                "<" => (left < right) as i64,
+                "<=" => (left <= right) as i64,
+                ">" => (left > right) as i64,
+                ">=" => (left >= right) as i64,
+                "<<" => left.checked_shl(right as u32).unwrap_or(0),
+                ">>" => left.checked_shr(right as u32).unwrap_or(0),
+                "+" => left.wrapping_add(right),
+                "-" => left.wrapping_sub(right),
+                "*" => left.wrapping_mul(right),
+                "/" => left.checked_div(right).unwrap_or(0),
+                _ => left.checked_rem(right).unwrap_or(0),
+            }
+        }
+    }
+}
+
+// Every operator, longest first so `<=` isn't read as `<`
+const OPERATORS: [&str; 20] = [
+    "||", "&&", "==", "!=", "<=", ">=", "<<", ">>", "|", "^", "&", "<", ">", "+", "-", "*", "/", "%", "!", "~",
+];
+
+// Binary operators from the loosest binding to the tightest
+const PRECEDENCE: [&[&str]; 10] = [
+    &["||"],
+    &["&&"],
+    &["|"],
+    &["^"],
+    &["&"],
+    &["==", "!="],
+    &["<", "<=", ">", ">="],
+    &["<<", ">>"],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+
+struct Parser<'a> {
+    text: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn skip_space(&mut self) {
+        while self.text.get(self.pos).is_some_and(|c| c.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn operator(&mut self) -> Option<&'static str> {
+        self.skip_space();
+        OPERATORS.into_iter().find(|op| self.text[self.pos..].starts_with(op.as_bytes()))
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        self.skip_space();
+        if self.text.get(self.pos) == Some(&c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Node, String> {
+        if level == PRECEDENCE.len() {
+            return self.unary();
+        }
+        let mut left = self.binary(level + 1)?;
+        while let Some(op) = self.operator().filter(|op| PRECEDENCE[level].contains(op)) {
+            self.pos += op.len();
+            let right = self.binary(level + 1)?;
+            left = Node::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Node, String> {
+        if let Some(op) = self.operator().filter(|op| matches!(*op, "!" | "-" | "~")) {
+            self.pos += op.len();
+            return Ok(Node::Unary(op, Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Node, String> {
+        self.skip_space();
+        let rest = &self.text[self.pos..];
+        let (radix, skip) = match rest.first() {
+            None => return Err("expected a value".to_string()),
+            Some(b'$') => (16, 1),
+            Some(b'%') => (2, 1),
+            Some(c) if c.is_ascii_digit() => (10, 0),
+            Some(b'(') => {
+                self.pos += 1;
+                let node = self.binary(0)?;
+                return if self.eat(b')') { Ok(node) } else { Err("missing )".to_string()) };
+            }
+            Some(b'[') => return Ok(Node::Byte(Box::new(self.address()?))),
+            Some(c) if c.is_ascii_alphabetic() || *c == b'_' => return self.name(),
+            Some(_) => return Err(format!("unexpected '{}'", String::from_utf8_lossy(rest))),
+        };
+
+        let len = rest[skip..].iter().take_while(|c| (**c as char).is_digit(radix)).count();
+        if len == 0 {
+            return Err("expected digits".to_string());
+        }
+        let digits = String::from_utf8_lossy(&rest[skip..skip + len]).to_string();
+        self.pos += skip + len;
+        i64::from_str_radix(&digits, radix).map(Node::Number).map_err(|e| format!("bad number {}: {}", digits, e))
+    }
+
+    // The `[expr]` of a memory read
+    fn address(&mut self) -> Result<Node, String> {
+        if !self.eat(b'[') {
+            return Err("expected [".to_string());
+        }
+        let node = self.binary(0)?;
+        if !self.eat(b']') {
+            return Err("missing ]".to_string());
+        }
+        Ok(node)
+    }
+
+    fn name(&mut self) -> Result<Node, String> {
+        let len = self.text[self.pos..].iter().take_while(|c| c.is_ascii_alphanumeric() || **c == b'_').count();
+        let name = String::from_utf8_lossy(&self.text[self.pos..self.pos + len]).to_string();
+        self.pos += len;
+        Ok(match name.to_ascii_uppercase().as_str() {
+            "A" => Node::Register(Register::A),
+            "X" => Node::Register(Register::X),
+            "Y" => Node::Register(Register::Y),
+            "SP" => Node::Register(Register::Sp),
+            "P" => Node::Register(Register::P),
+            "PC" => Node::Register(Register::Pc),
+            "C" => Node::Flag(0),
+            "Z" => Node::Flag(1),
+            "I" => Node::Flag(2),
+            "D" => Node::Flag(3),
+            "B" => Node::Flag(4),
+            "V" => Node::Flag(6),
+            "N" => Node::Flag(7),
+            "CYCLES" => Node::Cycles,
+            "HITS" => Node::Hits,
+            "W" => Node::Word(Box::new(self.address()?)),
+            _ => return Err(format!("unknown name {}", name)),
+        })
+    }
+}
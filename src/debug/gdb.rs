@@ -0,0 +1,390 @@
+use std::{
+    collections::HashMap,
+    io::{self, ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+};
+
+use super::debugger::{Debugger, StopReason, WatchKind};
+use crate::{
+    bus::Bus,
+    cpu::cpu::{CPU, Registers},
+};
+
+// Registers in the order of the `g` packet, the status register has its flags broken out
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.emulate6502.cpu">
+    <flags id="status" size="1">
+      <field name="C" start="0" end="0"/>
+      <field name="Z" start="1" end="1"/>
+      <field name="I" start="2" end="2"/>
+      <field name="D" start="3" end="3"/>
+      <field name="B" start="4" end="4"/>
+      <field name="V" start="6" end="6"/>
+      <field name="N" start="7" end="7"/>
+    </flags>
+    <reg name="a" bitsize="8" type="uint8" regnum="0"/>
+    <reg name="x" bitsize="8" type="uint8"/>
+    <reg name="y" bitsize="8" type="uint8"/>
+    <reg name="p" bitsize="8" type="status"/>
+    <reg name="sp" bitsize="8" type="uint8"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+  </feature>
+</target>
+"#;
+
+// Instructions run between checks for the client interrupting a continue
+const CHUNK: u64 = 10_000;
+
+// Signals in stop replies
+const SIGINT: u8 = 2;
+const SIGILL: u8 = 4;
+const SIGTRAP: u8 = 5;
+
+// A GDB remote serial protocol stub, so gdb (or anything else that speaks the protocol) can
+// debug the CPU over TCP. Like the monitor it's handed the CPU and bus of any machine and
+// drives them through a `Debugger`
+pub struct GdbStub {
+    pub debugger: Debugger,
+    // Debugger ids of the breakpoints and watchpoints the client has set, by the type and
+    // address it gave them
+    points: HashMap<(u8, u16), usize>,
+    // The reply to `?`, the reason the CPU last stopped
+    last_stop: String,
+}
+
+impl GdbStub {
+    #[allow(clippy::should_implement_trait)]
+    pub fn default() -> Self {
+        Self {
+            debugger: Debugger::default(),
+            points: HashMap::new(),
+            last_stop: format!("S{:02x}", SIGTRAP),
+        }
+    }
+
+    // Waits for one client on `addr`, like "127.0.0.1:2345", and serves it until it detaches
+    // or kills the session
+    pub fn listen(&mut self, cpu: &mut CPU, bus: &mut Bus, addr: &str) -> io::Result<()> {
+        let listener = TcpListener::bind(addr)?;
+        let (stream, _) = listener.accept()?;
+        self.serve(cpu, bus, stream)
+    }
+
+    pub fn serve(&mut self, cpu: &mut CPU, bus: &mut Bus, stream: TcpStream) -> io::Result<()> {
+        stream.set_nodelay(true)?;
+        let mut connection = Connection { stream, pending: vec![], acks: true };
+        while let Some(packet) = connection.read_packet()? {
+            let reply = match packet {
+                // Nothing is running between packets, so there's nothing to interrupt
+                Packet::Interrupt => continue,
+                Packet::Command(command) => match self.handle(cpu, bus, &mut connection, &command)? {
+                    Some(reply) => reply,
+                    None => return Ok(()),
+                },
+            };
+            connection.send(&reply)?;
+        }
+        Ok(())
+    }
+
+    // The reply to one command, None when the session is over
+    fn handle(&mut self, cpu: &mut CPU, bus: &mut Bus, connection: &mut Connection, command: &str) -> io::Result<Option<String>> {
+        let (kind, body) = command.split_at(command.chars().next().map_or(0, |c| c.len_utf8()));
+        let reply = match kind {
+            "?" => self.last_stop.clone(),
+            "g" => {
+                let r = cpu.registers();
+                hex(&[r.a, r.x, r.y, r.status, r.sp, r.pc as u8, (r.pc >> 8) as u8])
+            }
+            "G" => match unhex(body).as_deref() {
+                Some(&[a, x, y, status, sp, lo, hi]) => {
+                    let pc = u16::from_le_bytes([lo, hi]);
+                    cpu.set_registers(Registers { pc, a, x, y, sp, status });
+                    "OK".to_string()
+                }
+                _ => "E01".to_string(),
+            },
+            "p" => match usize::from_str_radix(body, 16) {
+                Ok(5) => hex(&cpu.pc.to_le_bytes()),
+                Ok(n) if n < 5 => hex(&[register(cpu, n)]),
+                _ => "E01".to_string(),
+            },
+            "P" => write_register(cpu, body).unwrap_or_else(|| "E01".to_string()),
+            "m" => match parse_range(body) {
+                Some((addr, len)) => {
+                    let bytes: Vec<u8> = (0..len).map(|i| bus.peek(addr.wrapping_add(i))).collect();
+                    hex(&bytes)
+                }
+                None => "E01".to_string(),
+            },
+            "M" => {
+                let write = body.split_once(':').and_then(|(range, data)| Some((parse_range(range)?, unhex(data)?)));
+                match write {
+                    Some(((addr, len), data)) if data.len() == len as usize => {
+                        for (i, value) in data.iter().enumerate() {
+                            bus.poke(addr.wrapping_add(i as u16), *value);
+                        }
+                        "OK".to_string()
+                    }
+                    _ => "E01".to_string(),
+                }
+            }
+            "c" | "s" => {
+                if let Ok(addr) = u16::from_str_radix(body, 16) {
+                    cpu.pc = addr;
+                }
+                let reply = self.resume(cpu, bus, connection, kind == "s")?;
+                self.last_stop = reply.clone();
+                reply
+            }
+            "Z" | "z" => self.point(bus, kind == "Z", body).unwrap_or_else(|| "E01".to_string()),
+            "q" => self.query(body),
+            "Q" if body == "StartNoAckMode" => {
+                // This packet has already been acknowledged, only the ones after it aren't
+                connection.acks = false;
+                "OK".to_string()
+            }
+            "H" | "T" => "OK".to_string(),
+            "D" => {
+                connection.send("OK")?;
+                return Ok(None);
+            }
+            "k" => return Ok(None),
+            // An empty reply tells the client the command isn't supported
+            _ => String::new(),
+        };
+        Ok(Some(reply))
+    }
+
+    fn query(&self, body: &str) -> String {
+        if body.starts_with("Supported") {
+            return "PacketSize=4000;qXfer:features:read+;swbreak+;hwbreak+;QStartNoAckMode+".to_string();
+        }
+        if let Some(request) = body.strip_prefix("Xfer:features:read:target.xml:") {
+            let Some((offset, len)) = request.split_once(',') else {
+                return "E01".to_string();
+            };
+            let (Ok(offset), Ok(len)) = (usize::from_str_radix(offset, 16), usize::from_str_radix(len, 16)) else {
+                return "E01".to_string();
+            };
+            let start = offset.min(TARGET_XML.len());
+            let end = (start + len).min(TARGET_XML.len());
+            let more = if end < TARGET_XML.len() { 'm' } else { 'l' };
+            return format!("{}{}", more, &TARGET_XML[start..end]);
+        }
+        match body {
+            "Attached" => "1".to_string(),
+            "C" => "QC1".to_string(),
+            "fThreadInfo" => "m1".to_string(),
+            "sThreadInfo" => "l".to_string(),
+            _ => String::new(),
+        }
+    }
+
+    // `Z type,addr,kind` and `z type,addr,kind`, types 0 and 1 are software and hardware
+    // breakpoints and 2 to 4 are write, read and access watchpoints over `kind` bytes
+    fn point(&mut self, bus: &mut Bus, insert: bool, body: &str) -> Option<String> {
+        let mut fields = body.split(',');
+        let kind: u8 = fields.next()?.parse().ok()?;
+        let addr = u16::from_str_radix(fields.next()?, 16).ok()?;
+        let len = u16::from_str_radix(fields.next()?, 16).ok()?.max(1);
+
+        if !insert {
+            if let Some(id) = self.points.remove(&(kind, addr)) {
+                self.debugger.remove(bus, id);
+            }
+            return Some("OK".to_string());
+        }
+        if self.points.contains_key(&(kind, addr)) {
+            return Some("OK".to_string());
+        }
+        let end = addr.saturating_add(len - 1);
+        let id = match kind {
+            0 | 1 => self.debugger.add_breakpoint(addr),
+            2 => self.debugger.add_watchpoint(bus, addr..=end, WatchKind::Write),
+            3 => self.debugger.add_watchpoint(bus, addr..=end, WatchKind::Read),
+            4 => self.debugger.add_watchpoint(bus, addr..=end, WatchKind::Access),
+            _ => return Some(String::new()),
+        };
+        self.points.insert((kind, addr), id);
+        Some("OK".to_string())
+    }
+
+    // Steps or continues, checking for the client interrupting a continue every `CHUNK`
+    // instructions, and gives the stop reply
+    fn resume(&mut self, cpu: &mut CPU, bus: &mut Bus, connection: &mut Connection, step: bool) -> io::Result<String> {
+        let reason = if step {
+            self.debugger.step_into(cpu, bus)
+        } else {
+            loop {
+                let reason = self.debugger.run(cpu, bus, Some(CHUNK));
+                if reason != StopReason::Limit {
+                    break reason;
+                }
+                if connection.interrupted()? {
+                    return Ok(format!("S{:02x}", SIGINT));
+                }
+            }
+        };
+        Ok(self.stop_reply(reason))
+    }
+
+    fn stop_reply(&self, reason: StopReason) -> String {
+        let kind_of = |id: usize| self.points.iter().find(|(_, point)| **point == id).map(|((kind, _), _)| *kind);
+        match reason {
+            StopReason::Breakpoint { id, .. } => match kind_of(id) {
+                Some(1) => format!("T{:02x}hwbreak:;", SIGTRAP),
+                _ => format!("T{:02x}swbreak:;", SIGTRAP),
+            },
+            StopReason::Watchpoint { id, addr, .. } => {
+                let name = match kind_of(id) {
+                    Some(2) => "watch",
+                    Some(3) => "rwatch",
+                    _ => "awatch",
+                };
+                format!("T{:02x}{}:{:04x};", SIGTRAP, name, addr)
+            }
+            StopReason::Stepped | StopReason::Limit => format!("S{:02x}", SIGTRAP),
+            StopReason::Jammed => format!("S{:02x}", SIGILL),
+            // The machine has gone, which the protocol calls the process exiting
+            StopReason::Shutdown => "W00".to_string(),
+        }
+    }
+}
+
+fn register(cpu: &CPU, n: usize) -> u8 {
+    let r = cpu.registers();
+    [r.a, r.x, r.y, r.status, r.sp][n]
+}
+
+// `P n=value`, with the value in target byte order
+fn write_register(cpu: &mut CPU, body: &str) -> Option<String> {
+    let (n, value) = body.split_once('=')?;
+    let value = unhex(value)?;
+    let mut r = cpu.registers();
+    match (usize::from_str_radix(n, 16).ok()?, value.as_slice()) {
+        (0, [a]) => r.a = *a,
+        (1, [x]) => r.x = *x,
+        (2, [y]) => r.y = *y,
+        (3, [status]) => r.status = *status,
+        (4, [sp]) => r.sp = *sp,
+        (5, [lo, hi]) => r.pc = u16::from_le_bytes([*lo, *hi]),
+        _ => return None,
+    }
+    cpu.set_registers(r);
+    Some("OK".to_string())
+}
+
+// `addr,length` in hex
+fn parse_range(text: &str) -> Option<(u16, u16)> {
+    let (addr, len) = text.split_once(',')?;
+    Some((u16::from_str_radix(addr, 16).ok()?, u16::from_str_radix(len, 16).ok()?))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len()).step_by(2).map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok()).collect()
+}
+
+enum Packet {
+    Command(String),
+    // The client sent a Ctrl-C
+    Interrupt,
+}
+
+struct Connection {
+    stream: TcpStream,
+    // Bytes read but not handled yet
+    pending: Vec<u8>,
+    // Acknowledgements are sent until the client asks for no ack mode
+    acks: bool,
+}
+
+impl Connection {
+    // The next packet, or None when the client has gone
+    fn read_packet(&mut self) -> io::Result<Option<Packet>> {
+        loop {
+            if let Some(packet) = self.parse()? {
+                return Ok(Some(packet));
+            }
+            let mut buffer = [0; 1024];
+            let len = self.stream.read(&mut buffer)?;
+            if len == 0 {
+                return Ok(None);
+            }
+            self.pending.extend_from_slice(&buffer[..len]);
+        }
+    }
+
+    // Takes a whole packet off the front of `pending` if there is one
+    fn parse(&mut self) -> io::Result<Option<Packet>> {
+        while let Some(&first) = self.pending.first() {
+            match first {
+                0x03 => {
+                    self.pending.remove(0);
+                    return Ok(Some(Packet::Interrupt));
+                }
+                b'$' => break,
+                // Acks for our replies and anything else between packets
+                _ => {
+                    self.pending.remove(0);
+                }
+            }
+        }
+
+        let Some(end) = self.pending.iter().position(|&byte| byte == b'#') else {
+            return Ok(None);
+        };
+        if self.pending.len() < end + 3 {
+            return Ok(None);
+        }
+        let packet: Vec<u8> = self.pending.drain(..end + 3).collect();
+        let data = &packet[1..end];
+        let checksum = std::str::from_utf8(&packet[end + 1..]).ok().and_then(|sum| u8::from_str_radix(sum, 16).ok());
+        if checksum != Some(sum(data)) {
+            self.stream.write_all(b"-")?;
+            return Ok(None);
+        }
+        if self.acks {
+            self.stream.write_all(b"+")?;
+        }
+        Ok(Some(Packet::Command(String::from_utf8_lossy(data).to_string())))
+    }
+
+    fn send(&mut self, data: &str) -> io::Result<()> {
+        write!(self.stream, "${}#{:02x}", data, sum(data.as_bytes()))?;
+        self.stream.flush()
+    }
+
+    // Whether a Ctrl-C has come in, without waiting for one
+    fn interrupted(&mut self) -> io::Result<bool> {
+        self.stream.set_nonblocking(true)?;
+        let mut buffer = [0; 1024];
+        let read = self.stream.read(&mut buffer);
+        self.stream.set_nonblocking(false)?;
+        match read {
+            Ok(len) => self.pending.extend_from_slice(&buffer[..len]),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+            Err(e) => return Err(e),
+        }
+        match self.pending.iter().position(|&byte| byte == 0x03) {
+            Some(index) => {
+                self.pending.remove(index);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+fn sum(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+}
@@ -0,0 +1,10 @@
+pub mod coverage;
+pub mod dap;
+pub mod debugger;
+pub mod expression;
+pub mod gdb;
+pub mod json;
+pub mod monitor;
+pub mod profiler;
+pub mod rewind;
+pub mod symbols;
@@ -0,0 +1,741 @@
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+};
+
+use super::{
+    coverage::Coverage,
+    debugger::{Debugger, StopReason, WatchKind},
+    expression::Expression,
+    rewind::Rewind,
+    symbols::SymbolTable,
+};
+use crate::{
+    bus::{Bus, UnmappedAccesses},
+    cpu::{assembler::Assembler, cpu::CPU, disassembler::Disassembler},
+    snapshot::Snapshot,
+};
+
+const HELP: &str = "\
+Addresses and bytes are hex, with or without a $. Bytes can also be given as a \"string\"
+  m [start [end]]           dump memory
+  > addr byte...            write bytes to memory
+  d [start [end]]           disassemble
+  a addr instruction        assemble one instruction, in assembler syntax so hex needs a $
+  r [reg=value...]          show or set the registers (A X Y SP P PC)
+  f start end byte...       fill memory with a pattern
+  c start end dest          compare two blocks of memory
+  h start end byte...       hunt for a sequence of bytes
+  s file start end          save memory to a file
+  l file addr               load a file into memory
+  dump file                 save the whole machine's state
+  undump file               load a saved state into the machine
+  b [addr [if cond]]        list breakpoints or add one
+  tb addr [if cond]         add a temporary breakpoint
+  w [r|w|rw|x] start [end] [if cond]   add a watchpoint
+  cond id [cond]            set or clear a condition
+  del id, enable id, disable id
+  g [addr]                  run until something stops it
+  un addr                   run until addr
+  z [count]                 step into
+  n [count]                 step over
+  ret                       step out
+  rec [on|off]              show, start or stop recording snapshots to go back with
+  bk [count]                step back
+  rg                        run backwards to the last breakpoint
+  ll file                   load labels (ca65 .dbg, VICE .lbl or name = $addr lines)
+  al addr name              add a label
+  shl                       show the labels
+  cdl [on|off]              show, start or stop recording code/data coverage
+  cdl save file [start end] save the coverage as an FCEUX code/data log
+  cdl merge file [start]    add a saved log to the coverage, recording it from now on
+  cdl report [start end]    list which runs of bytes were opcodes, operands, data or unused
+  um [clear]                list or clear the accesses to pages nothing is mapped at
+  x                         leave the monitor";
+
+// Lines shown by `m` and `d` when they aren't given an end address
+const DUMP_LINES: u32 = 8;
+const DISASSEMBLE_LINES: usize = 16;
+
+// A machine language monitor in the style of VICE's, for looking at and changing a running
+// machine. Like the debugger it drives, it's handed the CPU and bus of any machine.
+// `execute` runs one command and gives back its output, `repl` reads commands from a terminal
+pub struct Monitor {
+    pub debugger: Debugger,
+    // Names used in disassembly and when the CPU stops
+    pub symbols: SymbolTable,
+    // Where `m` and `d` carry on from when they aren't given an address, `d` starts at the
+    // PC when this is None
+    next_dump: u16,
+    next_disassemble: Option<u16>,
+    // Pressing enter on an empty line repeats the last `m`, `d`, `z`, `n` or `bk`
+    repeat: Option<String>,
+}
+
+impl Monitor {
+    #[allow(clippy::should_implement_trait)]
+    pub fn default() -> Self {
+        Self {
+            debugger: Debugger::default(),
+            symbols: SymbolTable::default(),
+            next_dump: 0,
+            next_disassemble: None,
+            repeat: None,
+        }
+    }
+
+    // Reads and runs commands until `x` or the end of the input
+    pub fn repl(&mut self, cpu: &mut CPU, bus: &mut Bus, input: &mut impl BufRead, output: &mut impl Write) -> io::Result<()> {
+        writeln!(output, "{}", self.current(cpu, bus))?;
+        let mut line = String::new();
+        loop {
+            write!(output, "({:04X}) ", cpu.pc)?;
+            output.flush()?;
+            line.clear();
+            if input.read_line(&mut line)? == 0 {
+                return Ok(());
+            }
+            match self.execute(cpu, bus, line.trim_end()) {
+                Some(text) if text.is_empty() => {}
+                Some(text) => writeln!(output, "{}", text)?,
+                None => return Ok(()),
+            }
+        }
+    }
+
+    // Runs one command and returns what it printed, or None for the command to leave
+    pub fn execute(&mut self, cpu: &mut CPU, bus: &mut Bus, line: &str) -> Option<String> {
+        let line = match (line.trim(), &self.repeat) {
+            ("", Some(repeat)) => repeat.clone(),
+            _ => line.trim().to_string(),
+        };
+        let words = split_words(&line);
+        let Some((command, args)) = words.split_first() else {
+            return Some(String::new());
+        };
+        let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+        let command = command.to_ascii_lowercase();
+
+        self.repeat = None;
+        let result = match command.as_str() {
+            "x" | "exit" | "quit" => return None,
+            "?" | "help" => Ok(HELP.to_string()),
+            "m" | "mem" => self.dump(bus, &args),
+            ">" => edit(bus, &args),
+            "d" | "disass" => self.disassemble(cpu, bus, &args),
+            "a" => assemble(cpu, bus, &args, rest_after(&line, 2)),
+            "r" | "registers" => registers(cpu, &args),
+            "f" | "fill" => fill(bus, &args),
+            "c" | "compare" => compare(bus, &args),
+            "h" | "hunt" => hunt(bus, &args),
+            "s" | "save" => save(bus, &args),
+            "l" | "load" => load(bus, &args),
+            "dump" => dump(cpu, bus, &args),
+            "undump" => self.undump(cpu, bus, &args),
+            "b" | "break" if args.is_empty() => Ok(self.list()),
+            "b" | "break" => self.breakpoint(&line, &args, false),
+            "tb" => self.breakpoint(&line, &args, true),
+            "w" | "watch" => self.watchpoint(bus, &line, &args),
+            "cond" | "condition" => self.condition(&line, &args),
+            "del" | "delete" => self.with_id(&args, |debugger, id| debugger.remove(bus, id)),
+            "enable" => self.with_id(&args, |debugger, id| debugger.set_enabled(bus, id, true)),
+            "disable" => self.with_id(&args, |debugger, id| debugger.set_enabled(bus, id, false)),
+            "g" | "go" => self.go(cpu, bus, &args),
+            "un" | "until" => self.until(cpu, bus, &args),
+            "z" | "step" => self.step(cpu, bus, &args, false),
+            "n" | "next" => self.step(cpu, bus, &args, true),
+            "rec" | "record" => self.record(cpu, &args),
+            "bk" | "back" => self.back(cpu, bus, &args),
+            "rg" | "reverse" => self.reverse(cpu, bus),
+            "ll" | "load_labels" => self.load_labels(&args),
+            "al" | "add_label" => self.add_label(&args),
+            "shl" | "show_labels" => Ok(self.show_labels()),
+            "cdl" => self.coverage(bus, &args),
+            "um" | "unmapped" => unmapped(bus, &args),
+            "ret" | "return" => {
+                let reason = self.debugger.step_out(cpu, bus, None);
+                Ok(self.stopped(cpu, bus, reason))
+            }
+            _ => Err(format!("unknown command {}, ? lists them", command)),
+        };
+
+        if matches!(command.as_str(), "m" | "mem" | "d" | "disass" | "z" | "step" | "n" | "next" | "bk" | "back") {
+            self.repeat = Some(command);
+        }
+        Some(result.unwrap_or_else(|message| format!("error: {}", message)))
+    }
+
+    fn dump(&mut self, bus: &mut Bus, args: &[&str]) -> Result<String, String> {
+        let start = args.first().map_or(Ok(self.next_dump), |arg| address(arg))? as u32;
+        let end = match args.get(1) {
+            Some(arg) => address(arg)? as u32,
+            None => (start + DUMP_LINES * 16 - 1).min(0xFFFF),
+        };
+
+        let mut lines = vec![];
+        for line in (start..=end).step_by(16) {
+            let bytes: Vec<u8> = (line..=end.min(line + 15)).map(|addr| bus.peek(addr as u16)).collect();
+            let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+            let text: String = bytes.iter().map(|&byte| if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' }).collect();
+            lines.push(format!("{:04X}  {:<47}  {}", line, hex.join(" "), text));
+        }
+        self.next_dump = (end + 1) as u16;
+        Ok(lines.join("\n"))
+    }
+
+    fn disassemble(&mut self, cpu: &CPU, bus: &mut Bus, args: &[&str]) -> Result<String, String> {
+        let mut addr = match args.first() {
+            Some(arg) => address(arg)?,
+            None => self.next_disassemble.unwrap_or(cpu.pc),
+        };
+        let end = args.get(1).map(|arg| address(arg)).transpose()?;
+
+        let disassembler = Disassembler::for_cpu(cpu);
+        let mut lines = vec![];
+        loop {
+            let line = disassembler.decode(bus, addr);
+            if let Some(name) = self.symbols.name_at(addr) {
+                lines.push(format!("{}:", name));
+            }
+            lines.push(line.format(Some(&self.symbols)));
+            let next = line.next();
+            // Stop at the end address, or when the address wraps around
+            let done = match end {
+                Some(end) => next > end || next < addr,
+                None => lines.len() == DISASSEMBLE_LINES,
+            };
+            addr = next;
+            if done {
+                break;
+            }
+        }
+        self.next_disassemble = Some(addr);
+        Ok(lines.join("\n"))
+    }
+
+    fn list(&self) -> String {
+        let mut lines = vec![];
+        for breakpoint in self.debugger.breakpoints() {
+            let kind = if breakpoint.temporary { "tb" } else { "b" };
+            lines.push(describe(breakpoint.id, format!("{} ${:04X}", kind, breakpoint.address), breakpoint.hits, breakpoint.enabled, &breakpoint.condition));
+        }
+        for watchpoint in self.debugger.watchpoints() {
+            let kind = match watchpoint.kind {
+                WatchKind::Read => "r",
+                WatchKind::Write => "w",
+                WatchKind::Access => "rw",
+                WatchKind::Execute => "x",
+            };
+            let what = format!("w {} ${:04X}-${:04X}", kind, watchpoint.range.start(), watchpoint.range.end());
+            lines.push(describe(watchpoint.id, what, watchpoint.hits, watchpoint.enabled, &watchpoint.condition));
+        }
+        if lines.is_empty() {
+            return "No breakpoints".to_string();
+        }
+        lines.join("\n")
+    }
+
+    fn breakpoint(&mut self, line: &str, args: &[&str], temporary: bool) -> Result<String, String> {
+        let (args, condition) = split_condition(line, args)?;
+        let [addr] = args else {
+            return Err("expected an address".to_string());
+        };
+        let addr = address(addr)?;
+        let id = if temporary {
+            self.debugger.add_temporary_breakpoint(addr)
+        } else {
+            self.debugger.add_breakpoint(addr)
+        };
+        self.debugger.set_condition(id, condition);
+        Ok(format!("Breakpoint {} at ${:04X}", id, addr))
+    }
+
+    fn watchpoint(&mut self, bus: &mut Bus, line: &str, args: &[&str]) -> Result<String, String> {
+        let (args, condition) = split_condition(line, args)?;
+        let (kind, args) = match args.first().map(|arg| arg.to_ascii_lowercase()).as_deref() {
+            Some("r") => (WatchKind::Read, &args[1..]),
+            Some("w") => (WatchKind::Write, &args[1..]),
+            Some("rw") => (WatchKind::Access, &args[1..]),
+            Some("x") => (WatchKind::Execute, &args[1..]),
+            _ => (WatchKind::Access, args),
+        };
+        let (start, end) = match args {
+            [start] => (address(start)?, address(start)?),
+            [start, end] => (address(start)?, address(end)?),
+            _ => return Err("expected a start address and an optional end".to_string()),
+        };
+        let id = self.debugger.add_watchpoint(bus, start..=end, kind);
+        self.debugger.set_condition(id, condition);
+        Ok(format!("Watchpoint {} at ${:04X}-${:04X}", id, start, end))
+    }
+
+    fn condition(&mut self, line: &str, args: &[&str]) -> Result<String, String> {
+        let id = id(args.first())?;
+        let text = rest_after(line, 2);
+        let condition = if text.is_empty() { None } else { Some(Expression::parse(text)?) };
+        if !self.debugger.set_condition(id, condition) {
+            return Err(format!("there's no breakpoint {}", id));
+        }
+        Ok(String::new())
+    }
+
+    fn with_id(&mut self, args: &[&str], action: impl FnOnce(&mut Debugger, usize) -> bool) -> Result<String, String> {
+        let id = id(args.first())?;
+        if !action(&mut self.debugger, id) {
+            return Err(format!("there's no breakpoint {}", id));
+        }
+        Ok(String::new())
+    }
+
+    fn go(&mut self, cpu: &mut CPU, bus: &mut Bus, args: &[&str]) -> Result<String, String> {
+        if let Some(arg) = args.first() {
+            cpu.pc = address(arg)?;
+        }
+        let reason = self.debugger.run(cpu, bus, None);
+        Ok(self.stopped(cpu, bus, reason))
+    }
+
+    fn until(&mut self, cpu: &mut CPU, bus: &mut Bus, args: &[&str]) -> Result<String, String> {
+        let [addr] = args else {
+            return Err("expected an address".to_string());
+        };
+        let id = self.debugger.add_temporary_breakpoint(address(addr)?);
+        let reason = self.debugger.run(cpu, bus, None);
+        // Something else might have stopped it first
+        self.debugger.remove(bus, id);
+        Ok(self.stopped(cpu, bus, reason))
+    }
+
+    fn step(&mut self, cpu: &mut CPU, bus: &mut Bus, args: &[&str], over: bool) -> Result<String, String> {
+        let count = count(args.first())?;
+        let mut reason = StopReason::Stepped;
+        for _ in 0..count {
+            reason = if over {
+                self.debugger.step_over(cpu, bus, None)
+            } else {
+                self.debugger.step_into(cpu, bus)
+            };
+            if reason != StopReason::Stepped {
+                break;
+            }
+        }
+        Ok(self.stopped(cpu, bus, reason))
+    }
+
+    fn record(&mut self, cpu: &CPU, args: &[&str]) -> Result<String, String> {
+        match args {
+            [] => match &self.debugger.rewind {
+                Some(rewind) => Ok(match rewind.oldest() {
+                    Some(oldest) => format!("{} snapshots, back to {} cycles ago", rewind.len(), cpu.read_cycles().saturating_sub(oldest)),
+                    None => "No snapshots yet".to_string(),
+                }),
+                None => Ok("Snapshots aren't being recorded".to_string()),
+            },
+            ["on"] => {
+                if self.debugger.rewind.is_none() {
+                    self.debugger.rewind = Some(Rewind::default());
+                }
+                Ok("Recording snapshots".to_string())
+            }
+            ["off"] => {
+                self.debugger.rewind = None;
+                Ok("Stopped recording snapshots".to_string())
+            }
+            _ => Err("expected on or off".to_string()),
+        }
+    }
+
+    fn back(&mut self, cpu: &mut CPU, bus: &mut Bus, args: &[&str]) -> Result<String, String> {
+        for _ in 0..count(args.first())? {
+            self.debugger.step_back(cpu, bus)?;
+        }
+        Ok(self.stopped(cpu, bus, StopReason::Stepped))
+    }
+
+    fn undump(&mut self, cpu: &mut CPU, bus: &mut Bus, args: &[&str]) -> Result<String, String> {
+        let [file] = args else {
+            return Err("expected a file".to_string());
+        };
+        Snapshot::load(unquote(file))?.restore(cpu, bus)?;
+        // The snapshots recorded so far are from another timeline
+        if let Some(rewind) = &mut self.debugger.rewind {
+            rewind.clear();
+        }
+        Ok(format!("Loaded {}\n{}", unquote(file), self.stopped(cpu, bus, StopReason::Stepped)))
+    }
+
+    fn reverse(&mut self, cpu: &mut CPU, bus: &mut Bus) -> Result<String, String> {
+        match self.debugger.reverse_continue(cpu, bus)? {
+            StopReason::Limit => Ok(format!("Went back as far as the snapshots go\n{}", self.stopped(cpu, bus, StopReason::Stepped))),
+            reason => Ok(self.stopped(cpu, bus, reason)),
+        }
+    }
+
+    // Why the CPU stopped, followed by the instruction it's on and the registers
+    fn stopped(&mut self, cpu: &CPU, bus: &mut Bus, reason: StopReason) -> String {
+        self.next_disassemble = None;
+        let why = match reason {
+            StopReason::Breakpoint { id, pc } => format!("Breakpoint {} at {}", id, self.describe_address(pc)),
+            StopReason::Watchpoint { id, kind, addr, value } => {
+                let access = match kind {
+                    WatchKind::Read => "read",
+                    WatchKind::Write => "write",
+                    _ => "execute",
+                };
+                format!("Watchpoint {}: {} ${:02X} at {}", id, access, value, self.describe_address(addr))
+            }
+            StopReason::Stepped => return self.current(cpu, bus),
+            StopReason::Limit => "Stopped at the instruction limit".to_string(),
+            StopReason::Jammed => "The CPU has jammed".to_string(),
+            StopReason::Shutdown => "The machine has shut down".to_string(),
+        };
+        format!("{}\n{}", why, self.current(cpu, bus))
+    }
+
+    // The instruction at the PC and the registers
+    fn current(&self, cpu: &CPU, bus: &mut Bus) -> String {
+        let line = Disassembler::for_cpu(cpu).decode(bus, cpu.pc);
+        format!("{:<32}{}", line.format(Some(&self.symbols)), show_registers(cpu))
+    }
+
+    // `$0405`, followed by its name if it has one, like `$0405 (main_loop+3)`
+    fn describe_address(&self, addr: u16) -> String {
+        match self.symbols.label(addr) {
+            Some(label) => format!("${:04X} ({})", addr, label),
+            None => format!("${:04X}", addr),
+        }
+    }
+
+    fn load_labels(&mut self, args: &[&str]) -> Result<String, String> {
+        let [file] = args else {
+            return Err("expected a file".to_string());
+        };
+        let table = SymbolTable::load(unquote(file))?;
+        self.symbols.merge(&table);
+        Ok(format!("Loaded {} labels", table.len()))
+    }
+
+    fn coverage(&self, bus: &mut Bus, args: &[&str]) -> Result<String, String> {
+        let range = |args: &[&str]| match args {
+            [] => Ok(0..=0xFFFF),
+            [start, end] => match (address(start)?, address(end)?) {
+                (start, end) if start > end => Err(format!("${:04X}-${:04X} is empty", start, end)),
+                (start, end) => Ok(start..=end),
+            },
+            _ => Err("expected a start and an end".to_string()),
+        };
+        match args {
+            [] => match bus.coverage() {
+                Some(coverage) => Ok(coverage.summary(0..=0xFFFF)),
+                None => Ok("Coverage isn't being recorded".to_string()),
+            },
+            ["on"] => {
+                if bus.coverage().is_none() {
+                    bus.set_coverage(Some(Coverage::default()));
+                }
+                Ok("Recording coverage".to_string())
+            }
+            ["off"] => {
+                bus.set_coverage(None);
+                Ok("Stopped recording coverage".to_string())
+            }
+            ["save", file, rest @ ..] => {
+                let coverage = bus.coverage().ok_or("coverage isn't being recorded")?;
+                let range = range(rest)?;
+                coverage.save(unquote(file), range.clone())?;
+                Ok(format!("Saved the coverage of ${:04X}-${:04X} to {}", range.start(), range.end(), unquote(file)))
+            }
+            ["merge", file, rest @ ..] => {
+                let start = match rest {
+                    [] => 0,
+                    [start] => address(start)?,
+                    _ => return Err("expected a file and a start".to_string()),
+                };
+                let saved = Coverage::load(unquote(file), start)?;
+                let mut coverage = bus.take_coverage().unwrap_or_else(Coverage::default);
+                coverage.merge(&saved);
+                bus.set_coverage(Some(coverage));
+                Ok(format!("Merged {} at ${:04X}", unquote(file), start))
+            }
+            ["report", rest @ ..] => {
+                let coverage = bus.coverage().ok_or("coverage isn't being recorded")?;
+                Ok(coverage.report(range(rest)?, Some(&self.symbols)))
+            }
+            _ => Err("expected on, off, save, merge or report".to_string()),
+        }
+    }
+
+    fn add_label(&mut self, args: &[&str]) -> Result<String, String> {
+        let [addr, name] = args else {
+            return Err("expected an address and a name".to_string());
+        };
+        self.symbols.insert(name.trim_start_matches('.'), address(addr)?);
+        Ok(String::new())
+    }
+
+    fn show_labels(&self) -> String {
+        if self.symbols.len() == 0 {
+            return "No labels".to_string();
+        }
+        let lines: Vec<String> = self.symbols.iter().map(|(addr, name)| format!("${:04X} {}", addr, name)).collect();
+        lines.join("\n")
+    }
+}
+
+fn show_registers(cpu: &CPU) -> String {
+    let r = cpu.registers();
+    format!(
+        "PC:{:04X} A:{:02X} X:{:02X} Y:{:02X} SP:{:02X} P:{:02X} NV-BDIZC:{:08b} CYC:{}",
+        r.pc,
+        r.a,
+        r.x,
+        r.y,
+        r.sp,
+        r.status,
+        r.status,
+        cpu.read_cycles()
+    )
+}
+
+fn registers(cpu: &mut CPU, args: &[&str]) -> Result<String, String> {
+    let mut registers = cpu.registers();
+    for arg in args {
+        let Some((name, value)) = arg.split_once('=') else {
+            return Err(format!("expected register=value, not {}", arg));
+        };
+        match name.to_ascii_uppercase().as_str() {
+            "PC" => registers.pc = address(value)?,
+            "A" => registers.a = byte(value)?,
+            "X" => registers.x = byte(value)?,
+            "Y" => registers.y = byte(value)?,
+            "SP" => registers.sp = byte(value)?,
+            "P" => registers.status = byte(value)?,
+            _ => return Err(format!("unknown register {}", name)),
+        }
+    }
+    cpu.set_registers(registers);
+    Ok(show_registers(cpu))
+}
+
+fn edit(bus: &mut Bus, args: &[&str]) -> Result<String, String> {
+    let Some((addr, values)) = args.split_first() else {
+        return Err("expected an address".to_string());
+    };
+    let addr = address(addr)?;
+    for (i, value) in bytes(values)?.into_iter().enumerate() {
+        bus.poke(addr.wrapping_add(i as u16), value);
+    }
+    Ok(String::new())
+}
+
+fn assemble(cpu: &CPU, bus: &mut Bus, args: &[&str], instruction: &str) -> Result<String, String> {
+    let Some(addr) = args.first() else {
+        return Err("expected an address".to_string());
+    };
+    let addr = address(addr)?;
+    let source = format!("*= ${:04X}\n {}", addr, instruction);
+    let assembly = Assembler::for_cpu(cpu).assemble(&source).map_err(|e| e.message)?;
+    assembly.load(bus);
+    Ok(Disassembler::for_cpu(cpu).decode(bus, addr).to_string())
+}
+
+fn fill(bus: &mut Bus, args: &[&str]) -> Result<String, String> {
+    let [start, end, pattern @ ..] = args else {
+        return Err("expected a start, an end and some bytes".to_string());
+    };
+    let pattern = bytes(pattern)?;
+    if pattern.is_empty() {
+        return Err("expected some bytes".to_string());
+    }
+    for (i, addr) in (address(start)?..=address(end)?).enumerate() {
+        bus.poke(addr, pattern[i % pattern.len()]);
+    }
+    Ok(String::new())
+}
+
+fn compare(bus: &mut Bus, args: &[&str]) -> Result<String, String> {
+    let [start, end, dest] = args else {
+        return Err("expected a start, an end and a destination".to_string());
+    };
+    let (start, dest) = (address(start)?, address(dest)?);
+    let mut lines = vec![];
+    for addr in start..=address(end)? {
+        let other = dest.wrapping_add(addr - start);
+        let (a, b) = (bus.peek(addr), bus.peek(other));
+        if a != b {
+            lines.push(format!("${:04X}: {:02X}  ${:04X}: {:02X}", addr, a, other, b));
+        }
+    }
+    if lines.is_empty() {
+        return Ok("No differences".to_string());
+    }
+    Ok(lines.join("\n"))
+}
+
+fn hunt(bus: &mut Bus, args: &[&str]) -> Result<String, String> {
+    let [start, end, pattern @ ..] = args else {
+        return Err("expected a start, an end and some bytes".to_string());
+    };
+    let pattern = bytes(pattern)?;
+    if pattern.is_empty() {
+        return Err("expected some bytes".to_string());
+    }
+    let (start, end) = (address(start)? as u32, address(end)? as u32);
+    let found: Vec<String> = (start..=end)
+        .filter(|&addr| addr + pattern.len() as u32 - 1 <= end)
+        .filter(|&addr| pattern.iter().enumerate().all(|(i, byte)| bus.peek((addr + i as u32) as u16) == *byte))
+        .map(|addr| format!("${:04X}", addr))
+        .collect();
+    if found.is_empty() {
+        return Ok("Not found".to_string());
+    }
+    Ok(found.join(" "))
+}
+
+fn save(bus: &mut Bus, args: &[&str]) -> Result<String, String> {
+    let [file, start, end] = args else {
+        return Err("expected a file, a start and an end".to_string());
+    };
+    let data: Vec<u8> = (address(start)?..=address(end)?).map(|addr| bus.peek(addr)).collect();
+    let file = unquote(file);
+    fs::write(file, &data).map_err(|e| format!("couldn't write {}: {}", file, e))?;
+    Ok(format!("Saved {} bytes to {}", data.len(), file))
+}
+
+fn dump(cpu: &CPU, bus: &Bus, args: &[&str]) -> Result<String, String> {
+    let [file] = args else {
+        return Err("expected a file".to_string());
+    };
+    Snapshot::take(cpu, bus).save(unquote(file))?;
+    Ok(format!("Saved the machine to {}", unquote(file)))
+}
+
+fn unmapped(bus: &mut Bus, args: &[&str]) -> Result<String, String> {
+    match args {
+        [] => {}
+        ["clear"] => {
+            bus.clear_unmapped_accesses();
+            return Ok(String::new());
+        }
+        _ => return Err("expected nothing or clear".to_string()),
+    }
+    let lines: Vec<String> = (0..=0xFF)
+        .filter_map(|page| {
+            let accesses = bus.unmapped_accesses(page);
+            (accesses != UnmappedAccesses::default())
+                .then(|| format!("${:02X}00-${:02X}FF  {} reads, {} writes", page, page, accesses.reads, accesses.writes))
+        })
+        .collect();
+    if lines.is_empty() {
+        return Ok("Nothing unmapped has been accessed".to_string());
+    }
+    Ok(lines.join("\n"))
+}
+
+fn load(bus: &mut Bus, args: &[&str]) -> Result<String, String> {
+    let [file, addr] = args else {
+        return Err("expected a file and an address".to_string());
+    };
+    let file = unquote(file);
+    let addr = address(addr)?;
+    let data = fs::read(file).map_err(|e| format!("couldn't read {}: {}", file, e))?;
+    if addr as usize + data.len() > 0x10000 {
+        return Err(format!("{} doesn't fit at ${:04X}", file, addr));
+    }
+    for (i, value) in data.iter().enumerate() {
+        bus.poke(addr + i as u16, *value);
+    }
+    Ok(format!("Loaded {} bytes at ${:04X}", data.len(), addr))
+}
+
+fn describe(id: usize, what: String, hits: u32, enabled: bool, condition: &Option<Expression>) -> String {
+    let mut line = format!("{}: {} hits {}", id, what, hits);
+    if let Some(condition) = condition {
+        line += &format!(" if {}", condition);
+    }
+    if !enabled {
+        line += " (disabled)";
+    }
+    line
+}
+
+// Splits `... if cond` into the arguments before the `if` and the parsed condition
+fn split_condition<'a, 'b>(line: &str, args: &'b [&'a str]) -> Result<(&'b [&'a str], Option<Expression>), String> {
+    match args.iter().position(|arg| arg.eq_ignore_ascii_case("if")) {
+        Some(index) => {
+            let condition = Expression::parse(rest_after(line, index + 2))?;
+            Ok((&args[..index], Some(condition)))
+        }
+        None => Ok((args, None)),
+    }
+}
+
+// Splits on whitespace, keeping quoted strings (and their quotes) together
+fn split_words(line: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut quoted = false;
+    for c in line.chars() {
+        if c == '"' {
+            quoted = !quoted;
+        }
+        if c.is_whitespace() && !quoted {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+        } else {
+            word.push(c);
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+// The text after the first `words` words, for the parts of a command that are free text
+fn rest_after(line: &str, words: usize) -> &str {
+    let mut rest = line.trim_start();
+    for _ in 0..words {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        rest = rest[end..].trim_start();
+    }
+    rest
+}
+
+fn unquote(text: &str) -> &str {
+    text.strip_prefix('"').and_then(|text| text.strip_suffix('"')).unwrap_or(text)
+}
+
+fn address(text: &str) -> Result<u16, String> {
+    u16::from_str_radix(text.trim_start_matches('$'), 16).map_err(|_| format!("bad address {}", text))
+}
+
+fn byte(text: &str) -> Result<u8, String> {
+    u8::from_str_radix(text.trim_start_matches('$'), 16).map_err(|_| format!("bad byte {}", text))
+}
+
+fn bytes(args: &[&str]) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![];
+    for arg in args {
+        if arg.starts_with('"') {
+            bytes.extend(unquote(arg).bytes());
+        } else {
+            bytes.push(byte(arg)?);
+        }
+    }
+    Ok(bytes)
+}
+
+// A hex count that defaults to 1
+fn count(arg: Option<&&str>) -> Result<u32, String> {
+    match arg {
+        Some(arg) => u32::from_str_radix(arg.trim_start_matches('$'), 16).map_err(|_| format!("bad count {}", arg)),
+        None => Ok(1),
+    }
+}
+
+fn id(arg: Option<&&str>) -> Result<usize, String> {
+    let arg = arg.ok_or("expected an id")?;
+    arg.parse().map_err(|_| format!("bad id {}", arg))
+}
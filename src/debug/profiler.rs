@@ -0,0 +1,307 @@
+use std::{cell::RefCell, collections::HashMap, fmt::Write, rc::Rc};
+
+use crate::cpu::trace::{TraceEvent, Tracer};
+use crate::debug::symbols::SymbolTable;
+
+const JSR: u8 = 0x20;
+const BRK: u8 = 0x00;
+// The interrupt sequence always takes 7 cycles, the trace event doesn't carry them
+const INTERRUPT_CYCLES: u64 = 7;
+
+// One subroutine's totals, `cycles` is the time spent in its own instructions and `total`
+// includes everything it called
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SubroutineProfile {
+    pub addr: u16,
+    pub calls: u64,
+    pub cycles: u64,
+    pub total: u64,
+}
+
+// A subroutine reached through one particular chain of calls. The nodes make a tree rooted at
+// whatever was running when profiling started, children always come after their parent
+struct Node {
+    addr: u16,
+    parent: Option<usize>,
+    children: HashMap<u16, usize>,
+    calls: u64,
+    cycles: u64,
+}
+
+// A subroutine or interrupt handler that's running, with the stack pointer from before it
+// was entered. Once the stack is back up to that it has returned
+struct Frame {
+    node: usize,
+    sp: u8,
+}
+
+struct Profile {
+    executions: Vec<u64>,
+    cycles: Vec<u64>,
+    nodes: Vec<Node>,
+    stack: Vec<Frame>,
+    // The stack pointer of an interrupt whose handler hasn't run its first instruction yet
+    pending_interrupt: Option<u8>,
+    total: u64,
+}
+
+impl Profile {
+    fn new() -> Self {
+        Self {
+            executions: vec![0; 0x10000],
+            cycles: vec![0; 0x10000],
+            nodes: vec![],
+            stack: vec![],
+            pending_interrupt: None,
+            total: 0,
+        }
+    }
+
+    fn current(&mut self, pc: u16) -> usize {
+        if self.nodes.is_empty() {
+            self.nodes.push(Node { addr: pc, parent: None, children: HashMap::new(), calls: 1, cycles: 0 });
+        }
+        self.stack.last().map_or(0, |frame| frame.node)
+    }
+
+    fn enter(&mut self, addr: u16, sp: u8) {
+        let parent = self.current(addr);
+        let next = self.nodes.len();
+        let node = *self.nodes[parent].children.entry(addr).or_insert(next);
+        if node == next {
+            self.nodes.push(Node { addr, parent: Some(parent), children: HashMap::new(), calls: 0, cycles: 0 });
+        }
+        self.nodes[node].calls += 1;
+        self.stack.push(Frame { node, sp });
+    }
+
+    // The handler of an interrupt starts at the first thing that runs after it
+    fn enter_interrupt(&mut self, pc: u16) {
+        if let Some(sp) = self.pending_interrupt.take() {
+            self.enter(pc, sp);
+            let node = self.current(pc);
+            self.nodes[node].cycles += INTERRUPT_CYCLES;
+        }
+    }
+
+    fn record(&mut self, event: &TraceEvent) {
+        match event {
+            TraceEvent::Instruction(ins) => {
+                self.enter_interrupt(ins.before.pc);
+                let node = self.current(ins.before.pc);
+                let cycles = ins.cycles as u64;
+                self.nodes[node].cycles += cycles;
+                self.executions[ins.before.pc as usize] += 1;
+                self.cycles[ins.before.pc as usize] += cycles;
+                self.total += cycles;
+
+                // RTS, RTI or anything else that takes the stack back above where a frame
+                // started has left it, which also copes with code that drops return addresses
+                while self.stack.last().is_some_and(|frame| frame.sp <= ins.after.sp) {
+                    self.stack.pop();
+                }
+                if ins.opcode == JSR || ins.opcode == BRK {
+                    self.enter(ins.after.pc, ins.before.sp);
+                }
+            }
+            TraceEvent::Interrupt { before, .. } => {
+                self.enter_interrupt(before.pc);
+                self.current(before.pc);
+                self.pending_interrupt = Some(before.sp);
+                self.total += INTERRUPT_CYCLES;
+            }
+            TraceEvent::UnknownOpcode { .. } => {}
+        }
+    }
+
+    // Each node's cycles including its children's
+    fn totals(&self) -> Vec<u64> {
+        let mut totals: Vec<u64> = self.nodes.iter().map(|node| node.cycles).collect();
+        for (i, node) in self.nodes.iter().enumerate().rev() {
+            if let Some(parent) = node.parent {
+                totals[parent] += totals[i];
+            }
+        }
+        totals
+    }
+
+    // Whether the subroutine at `node` is already running further up its chain of calls, its
+    // time is counted there so recursion isn't counted twice
+    fn recursive(&self, node: usize) -> bool {
+        let mut parent = self.nodes[node].parent;
+        while let Some(i) = parent {
+            if self.nodes[i].addr == self.nodes[node].addr {
+                return true;
+            }
+            parent = self.nodes[i].parent;
+        }
+        false
+    }
+}
+
+// Counts how often each address runs and how many cycles it takes, and follows JSR, RTS,
+// interrupts and RTI to give each subroutine its cycles. Set it as the CPU's tracer, clones
+// share the same counts so keep one to read the results
+#[derive(Clone)]
+pub struct Profiler {
+    profile: Rc<RefCell<Profile>>,
+}
+
+impl Profiler {
+    #[allow(clippy::should_implement_trait)]
+    pub fn default() -> Self {
+        Self { profile: Rc::new(RefCell::new(Profile::new())) }
+    }
+
+    // Forgets everything, the next instruction starts a new tree of calls
+    pub fn clear(&self) {
+        *self.profile.borrow_mut() = Profile::new();
+    }
+
+    pub fn total_cycles(&self) -> u64 {
+        self.profile.borrow().total
+    }
+
+    pub fn executions(&self, addr: u16) -> u64 {
+        self.profile.borrow().executions[addr as usize]
+    }
+
+    pub fn cycles(&self, addr: u16) -> u64 {
+        self.profile.borrow().cycles[addr as usize]
+    }
+
+    // Every address that ran as (address, executions, cycles), most cycles first
+    pub fn hot_spots(&self) -> Vec<(u16, u64, u64)> {
+        let profile = self.profile.borrow();
+        let mut spots: Vec<(u16, u64, u64)> = (0..=0xFFFF)
+            .filter(|&addr| profile.executions[addr] > 0)
+            .map(|addr| (addr as u16, profile.executions[addr], profile.cycles[addr]))
+            .collect();
+        spots.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(&b.0)));
+        spots
+    }
+
+    // Every subroutine that ran, most cycles in itself first. The code that was running when
+    // profiling started counts as one, entered once
+    pub fn subroutines(&self) -> Vec<SubroutineProfile> {
+        let profile = self.profile.borrow();
+        let totals = profile.totals();
+        let mut subroutines: HashMap<u16, SubroutineProfile> = HashMap::new();
+        for (i, node) in profile.nodes.iter().enumerate() {
+            let entry = subroutines.entry(node.addr).or_insert(SubroutineProfile { addr: node.addr, calls: 0, cycles: 0, total: 0 });
+            entry.calls += node.calls;
+            entry.cycles += node.cycles;
+            if !profile.recursive(i) {
+                entry.total += totals[i];
+            }
+        }
+        let mut subroutines: Vec<SubroutineProfile> = subroutines.into_values().collect();
+        subroutines.sort_by(|a, b| b.cycles.cmp(&a.cycles).then(a.addr.cmp(&b.addr)));
+        subroutines
+    }
+
+    // The `count` addresses that took the most cycles
+    pub fn report(&self, count: usize, symbols: Option<&SymbolTable>) -> String {
+        let total = self.total_cycles().max(1);
+        let mut text = "address  executions      cycles       %  label\n".to_string();
+        for (addr, executions, cycles) in self.hot_spots().into_iter().take(count) {
+            let label = symbols.and_then(|symbols| symbols.label(addr)).unwrap_or_default();
+            let line = format!("${:04X}  {:>10}  {:>10}  {:>6.2}  {}", addr, executions, cycles, percent(cycles, total), label);
+            let _ = writeln!(text, "{}", line.trim_end());
+        }
+        text
+    }
+
+    // A gprof style flat profile of the subroutines
+    pub fn flat(&self, symbols: Option<&SymbolTable>) -> String {
+        let total = self.total_cycles().max(1);
+        let mut text = "  self%        self       total     calls  name\n".to_string();
+        for subroutine in self.subroutines() {
+            let _ = writeln!(
+                text,
+                "{:>7.2}  {:>10}  {:>10}  {:>8}  {}",
+                percent(subroutine.cycles, total),
+                subroutine.cycles,
+                subroutine.total,
+                subroutine.calls,
+                name(subroutine.addr, symbols)
+            );
+        }
+        text
+    }
+
+    // Each subroutine, most cycles including its callees first, followed by what called it
+    // (`<-`) and what it called (`->`) with the calls and the cycles spent in them
+    pub fn call_graph(&self, symbols: Option<&SymbolTable>) -> String {
+        let profile = self.profile.borrow();
+        let totals = profile.totals();
+        let mut edges: HashMap<(u16, u16), (u64, u64)> = HashMap::new();
+        for (i, node) in profile.nodes.iter().enumerate() {
+            if let Some(parent) = node.parent {
+                let edge = edges.entry((profile.nodes[parent].addr, node.addr)).or_default();
+                edge.0 += node.calls;
+                if !profile.recursive(i) {
+                    edge.1 += totals[i];
+                }
+            }
+        }
+        let mut edges: Vec<((u16, u16), (u64, u64))> = edges.into_iter().collect();
+        edges.sort_by(|a, b| b.1.1.cmp(&a.1.1).then(a.0.cmp(&b.0)));
+        drop(profile);
+
+        let mut subroutines = self.subroutines();
+        subroutines.sort_by(|a, b| b.total.cmp(&a.total).then(a.addr.cmp(&b.addr)));
+        let mut text = String::new();
+        for subroutine in subroutines {
+            let _ = writeln!(
+                text,
+                "{}  {} cycles, {} in itself, {} calls",
+                name(subroutine.addr, symbols),
+                subroutine.total,
+                subroutine.cycles,
+                subroutine.calls
+            );
+            for ((caller, _), (calls, cycles)) in edges.iter().filter(|((_, callee), _)| *callee == subroutine.addr) {
+                let _ = writeln!(text, "    <- {}  {} calls, {} cycles", name(*caller, symbols), calls, cycles);
+            }
+            for ((_, callee), (calls, cycles)) in edges.iter().filter(|((caller, _), _)| *caller == subroutine.addr) {
+                let _ = writeln!(text, "    -> {}  {} calls, {} cycles", name(*callee, symbols), calls, cycles);
+            }
+        }
+        text
+    }
+
+    // One line per chain of calls with the cycles spent at the end of it, like
+    // `main;draw;plot 1200`, which flamegraph.pl, inferno and speedscope read
+    pub fn collapsed(&self, symbols: Option<&SymbolTable>) -> String {
+        let profile = self.profile.borrow();
+        let mut lines = vec![];
+        for (i, node) in profile.nodes.iter().enumerate().filter(|(_, node)| node.cycles > 0) {
+            let mut names = vec![name(node.addr, symbols)];
+            let mut parent = profile.nodes[i].parent;
+            while let Some(p) = parent {
+                names.push(name(profile.nodes[p].addr, symbols));
+                parent = profile.nodes[p].parent;
+            }
+            names.reverse();
+            lines.push(format!("{} {}\n", names.join(";"), node.cycles));
+        }
+        lines.sort();
+        lines.concat()
+    }
+}
+
+impl Tracer for Profiler {
+    fn trace(&mut self, event: &TraceEvent) {
+        self.profile.borrow_mut().record(event);
+    }
+}
+
+fn percent(cycles: u64, total: u64) -> f64 {
+    cycles as f64 * 100.0 / total as f64
+}
+
+// The label for `addr`, or the address itself
+fn name(addr: u16, symbols: Option<&SymbolTable>) -> String {
+    symbols.and_then(|symbols| symbols.label(addr)).unwrap_or_else(|| format!("${:04X}", addr))
+}
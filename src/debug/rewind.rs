@@ -0,0 +1,125 @@
+use std::collections::VecDeque;
+
+use crate::{bus::Bus, cpu::cpu::CPU, snapshot::Snapshot};
+
+// Snapshots of the machine taken every `interval` cycles as it runs, dropping the oldest once
+// there are `capacity` of them. Going back restores the newest snapshot before the point wanted
+// and runs forward from it to get there exactly, so the machine has to do the same thing every
+// time it runs from a snapshot. Input from outside, like the BBC's keyboard, has to be logged
+// by whatever reads it and replayed when it's run again
+pub struct Rewind {
+    interval: u64,
+    capacity: usize,
+    snapshots: VecDeque<Snapshot>,
+}
+
+impl Rewind {
+    // A snapshot every 100,000 cycles and the last 100 of them, going back 10 million cycles
+    #[allow(clippy::should_implement_trait)]
+    pub fn default() -> Self {
+        Self::new(100_000, 100)
+    }
+
+    pub fn new(interval: u64, capacity: usize) -> Self {
+        Self { interval, capacity, snapshots: VecDeque::new() }
+    }
+
+    // Takes a snapshot if one is due, called before each instruction
+    pub fn record(&mut self, cpu: &CPU, bus: &Bus) {
+        let cycles = cpu.read_cycles();
+        self.forget_after(cycles);
+        if self.capacity == 0 || self.snapshots.back().is_some_and(|snapshot| cycles < snapshot.cycles() + self.interval) {
+            return;
+        }
+        if self.snapshots.len() == self.capacity {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(Snapshot::take(cpu, bus));
+    }
+
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+    }
+
+    // The cycle count of the oldest snapshot, as far back as it can go
+    pub fn oldest(&self) -> Option<u64> {
+        self.snapshots.front().map(|snapshot| snapshot.cycles())
+    }
+
+    // Goes back to the start of the instruction before the one the CPU is about to run
+    pub fn step_back(&mut self, cpu: &mut CPU, bus: &mut Bus) -> Result<(), String> {
+        let now = cpu.read_cycles();
+        let snapshot = self.snapshots.iter().rev().find(|snapshot| snapshot.cycles() < now).ok_or("can't go back any further")?.clone();
+        // Count the instructions up to now, then run one fewer
+        let count = replay(cpu, bus, &snapshot, |cpu, _| cpu.read_cycles() < now)?;
+        replay(cpu, bus, &snapshot, |_, ran| ran + 1 < count)?;
+        self.forget_after(cpu.read_cycles());
+        Ok(())
+    }
+
+    // Goes back `cycles`, to the first instruction that starts at or after that point, or to
+    // the oldest snapshot if it doesn't go back that far
+    pub fn rewind(&mut self, cpu: &mut CPU, bus: &mut Bus, cycles: u64) -> Result<(), String> {
+        let target = cpu.read_cycles().saturating_sub(cycles);
+        let snapshot = self
+            .snapshots
+            .iter()
+            .rev()
+            .find(|snapshot| snapshot.cycles() <= target)
+            .or(self.snapshots.front())
+            .ok_or("there are no snapshots")?
+            .clone();
+        replay(cpu, bus, &snapshot, |cpu, _| cpu.read_cycles() < target)?;
+        self.forget_after(cpu.read_cycles());
+        Ok(())
+    }
+
+    // Goes back to the last point before now where `stop` was true before an instruction,
+    // or to the oldest snapshot if there wasn't one. True if it found one
+    pub fn back_to(&mut self, cpu: &mut CPU, bus: &mut Bus, mut stop: impl FnMut(&CPU) -> bool) -> Result<bool, String> {
+        let now = cpu.read_cycles();
+        let oldest = self.snapshots.front().filter(|snapshot| snapshot.cycles() < now).ok_or("can't go back any further")?.clone();
+        let mut last = None;
+        replay(cpu, bus, &oldest, |cpu, ran| {
+            if cpu.read_cycles() >= now {
+                return false;
+            }
+            if stop(cpu) {
+                last = Some(ran);
+            }
+            true
+        })?;
+        let count = last.unwrap_or(0);
+        replay(cpu, bus, &oldest, |_, ran| ran < count)?;
+        self.forget_after(cpu.read_cycles());
+        Ok(last.is_some())
+    }
+
+    // Snapshots from after `cycles` belong to a future that has been rewound away
+    fn forget_after(&mut self, cycles: u64) {
+        while self.snapshots.back().is_some_and(|snapshot| snapshot.cycles() > cycles) {
+            self.snapshots.pop_back();
+        }
+    }
+}
+
+// Restores `snapshot` and runs instructions while `more` says to, given the CPU and how many
+// have run so far. Gives back how many ran
+fn replay(cpu: &mut CPU, bus: &mut Bus, snapshot: &Snapshot, mut more: impl FnMut(&CPU, u64) -> bool) -> Result<u64, String> {
+    snapshot.restore(cpu, bus)?;
+    // A tracer has already seen these instructions
+    let tracer = cpu.config.tracer.take();
+    let mut ran = 0;
+    while more(cpu, ran) {
+        cpu.step(bus, 1);
+        ran += 1;
+    }
+    cpu.config.tracer = tracer;
+    bus.take_watch_hit();
+    Ok(ran)
+}
@@ -0,0 +1,372 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+};
+
+// How far past a name an address can be and still be called `name+offset`, much further and
+// it's unlikely to be part of the same thing
+const MAX_OFFSET: u16 = 0x100;
+
+// A line of a source file, from debug info
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceLine {
+    pub file: String,
+    // 1 based
+    pub line: usize,
+}
+
+// Names for addresses, used to show labels in place of raw addresses. An address can only
+// have one name, giving it another replaces the old one. Tables loaded from debug info can
+// also know the source line each address was assembled from
+#[derive(Clone)]
+pub struct SymbolTable {
+    names: BTreeMap<u16, String>,
+    addresses: HashMap<String, u16>,
+    lines: BTreeMap<u16, SourceLine>,
+}
+
+impl SymbolTable {
+    #[allow(clippy::should_implement_trait)]
+    pub fn default() -> Self {
+        Self {
+            names: BTreeMap::new(),
+            addresses: HashMap::new(),
+            lines: BTreeMap::new(),
+        }
+    }
+
+    // The entry points, vectors and hardware registers of the BBC Micro's OS 1.2
+    pub fn bbc_os() -> Self {
+        let mut table = Self::default();
+        for (name, addr) in BBC_OS {
+            table.insert(name, addr);
+        }
+        table
+    }
+
+    // Reads a symbol file in any of the formats `parse` knows. File names in debug info are
+    // taken as relative to the file they came from
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("couldn't read {}: {}", path, e))?;
+        let mut table = Self::parse(&text).map_err(|e| format!("{}: {}", path, e))?;
+        if let Some(dir) = Path::new(path).parent() {
+            for source in table.lines.values_mut() {
+                if Path::new(&source.file).is_relative() {
+                    source.file = dir.join(&source.file).to_string_lossy().to_string();
+                }
+            }
+        }
+        Ok(table)
+    }
+
+    // Parses ca65/ld65 debug info (`ld65 --dbgfile`), VICE label files (`al C:0400 .name`,
+    // which `ld65 -Ln` writes too) or plain `name = $0400` lines, whichever `text` looks like
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+        match lines.next() {
+            Some(first) if first.starts_with("version") && first.contains("major=") => parse_dbg(text),
+            _ if text.lines().any(|line| line.trim_start().starts_with("al ")) => parse_vice(text),
+            _ => parse_plain(text),
+        }
+    }
+
+    pub fn insert(&mut self, name: &str, addr: u16) {
+        if let Some(old) = self.names.insert(addr, name.to_string()) {
+            self.addresses.remove(&old);
+        }
+        if let Some(old) = self.addresses.insert(name.to_string(), addr)
+            && old != addr
+        {
+            self.names.remove(&old);
+        }
+    }
+
+    pub fn insert_line(&mut self, addr: u16, file: &str, line: usize) {
+        self.lines.insert(addr, SourceLine { file: file.to_string(), line });
+    }
+
+    // Adds everything in `other`, its names win where both have one
+    pub fn merge(&mut self, other: &SymbolTable) {
+        for (addr, name) in other.iter() {
+            self.insert(name, addr);
+        }
+        self.lines.extend(other.lines.iter().map(|(addr, line)| (*addr, line.clone())));
+    }
+
+    pub fn name_at(&self, addr: u16) -> Option<&str> {
+        self.names.get(&addr).map(|name| name.as_str())
+    }
+
+    pub fn address_of(&self, name: &str) -> Option<u16> {
+        self.addresses.get(name).copied()
+    }
+
+    // The closest symbol at or below `addr`
+    pub fn nearest(&self, addr: u16) -> Option<(u16, &str)> {
+        self.names.range(..=addr).next_back().map(|(addr, name)| (*addr, name.as_str()))
+    }
+
+    // `addr` as `name` or `name+offset`, like `main_loop+3`
+    pub fn label(&self, addr: u16) -> Option<String> {
+        let (base, name) = self.nearest(addr)?;
+        match addr - base {
+            0 => Some(name.to_string()),
+            offset if offset < MAX_OFFSET => Some(format!("{}+{}", name, offset)),
+            _ => None,
+        }
+    }
+
+    // The source line the instruction at `addr` came from
+    pub fn line_at(&self, addr: u16) -> Option<&SourceLine> {
+        self.lines.get(&addr)
+    }
+
+    // Every address with a source line, in address order
+    pub fn lines(&self) -> impl Iterator<Item = (u16, &SourceLine)> {
+        self.lines.iter().map(|(addr, line)| (*addr, line))
+    }
+
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    // Every symbol in address order
+    pub fn iter(&self) -> impl Iterator<Item = (u16, &str)> {
+        self.names.iter().map(|(addr, name)| (*addr, name.as_str()))
+    }
+}
+
+// `$0400`, `&0400` (BBC BASIC's hex), `0x0400` or decimal
+fn parse_address(text: &str) -> Option<u16> {
+    let text = text.trim();
+    match text.strip_prefix('$').or_else(|| text.strip_prefix('&')).or_else(|| text.strip_prefix("0x")) {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+fn parse_plain(text: &str) -> Result<SymbolTable, String> {
+    let mut table = SymbolTable::default();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split([';', '#']).next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let error = || format!("line {}: expected name = address", number + 1);
+        let (name, addr) = line.split_once('=').ok_or_else(error)?;
+        let addr = parse_address(addr).ok_or_else(error)?;
+        table.insert(name.trim(), addr);
+    }
+    Ok(table)
+}
+
+fn parse_vice(text: &str) -> Result<SymbolTable, String> {
+    let mut table = SymbolTable::default();
+    for (number, line) in text.lines().enumerate() {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        // Label files are monitor commands, the others don't define anything
+        let ["al", addr, name] = words.as_slice() else {
+            if words.first() == Some(&"al") {
+                return Err(format!("line {}: expected al address .name", number + 1));
+            }
+            continue;
+        };
+        // Labels for the disk drive's memory rather than the computer's are skipped
+        let addr = match addr.split_once(':') {
+            Some(("C", addr)) => addr,
+            Some(_) => continue,
+            None => addr,
+        };
+        let addr = u16::from_str_radix(addr, 16).map_err(|_| format!("line {}: bad address {}", number + 1, addr))?;
+        table.insert(name.trim_start_matches('.'), addr);
+    }
+    Ok(table)
+}
+
+// The `key=value` fields of a line of ca65 debug info, values can be quoted
+fn dbg_fields(text: &str) -> HashMap<&str, &str> {
+    let mut fields = HashMap::new();
+    let mut rest = text;
+    while let Some((key, after)) = rest.split_once('=') {
+        let (value, next) = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"').unwrap_or(quoted.len());
+                (&quoted[..end], quoted[end..].trim_start_matches('"'))
+            }
+            None => after.split_once(',').map_or((after, ""), |(value, next)| (value, next)),
+        };
+        fields.insert(key.trim().trim_start_matches(','), value);
+        rest = next.trim_start_matches(',');
+    }
+    fields
+}
+
+fn dbg_number(fields: &HashMap<&str, &str>, key: &str) -> Option<u32> {
+    let value = fields.get(key)?;
+    match value.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+// ca65's debug info has records for files, segments, spans (runs of bytes in a segment) and
+// lines, each line pointing at the spans it assembled to. Labels are `sym` records of type
+// `lab`, cheap local labels (which have a parent) are left out
+fn parse_dbg(text: &str) -> Result<SymbolTable, String> {
+    let mut files = HashMap::new();
+    let mut segments = HashMap::new();
+    let mut spans = HashMap::new();
+    let mut lines = vec![];
+    let mut table = SymbolTable::default();
+
+    for (number, line) in text.lines().enumerate() {
+        let Some((kind, rest)) = line.trim().split_once(char::is_whitespace) else {
+            continue;
+        };
+        let fields = dbg_fields(rest.trim());
+        let error = |what: &str| format!("line {}: {} record without {}", number + 1, kind, what);
+        let id = || dbg_number(&fields, "id").ok_or_else(|| error("an id"));
+        match kind {
+            "file" => {
+                files.insert(id()?, fields.get("name").ok_or_else(|| error("a name"))?.to_string());
+            }
+            "seg" => {
+                segments.insert(id()?, dbg_number(&fields, "start").ok_or_else(|| error("a start"))?);
+            }
+            "span" => {
+                let seg = dbg_number(&fields, "seg").ok_or_else(|| error("a segment"))?;
+                let start = dbg_number(&fields, "start").ok_or_else(|| error("a start"))?;
+                let size = dbg_number(&fields, "size").unwrap_or(0);
+                spans.insert(id()?, (seg, start, size));
+            }
+            // Lines that came from a macro point at the macro's source, which isn't as useful
+            // as the line that used it
+            "line" if fields.get("type") != Some(&"2") => {
+                let Some(span) = fields.get("span") else {
+                    continue;
+                };
+                let file = dbg_number(&fields, "file").ok_or_else(|| error("a file"))?;
+                let line = dbg_number(&fields, "line").ok_or_else(|| error("a line"))?;
+                for span in span.split('+') {
+                    let span = span.parse::<u32>().map_err(|_| error("a valid span"))?;
+                    lines.push((file, line as usize, span));
+                }
+            }
+            "sym" if fields.get("type") == Some(&"lab") && !fields.contains_key("parent") => {
+                let name = fields.get("name").ok_or_else(|| error("a name"))?;
+                let val = dbg_number(&fields, "val").ok_or_else(|| error("a value"))?;
+                table.insert(name, val as u16);
+            }
+            _ => {}
+        }
+    }
+
+    // Where more than one line covers an address, like an `.include` and the lines in the
+    // included file, the one with the smallest span is the most useful
+    let mut best: HashMap<u16, u32> = HashMap::new();
+    for (file, line, span) in lines {
+        let (Some(name), Some((seg, start, size))) = (files.get(&file), spans.get(&span)) else {
+            continue;
+        };
+        let Some(seg_start) = segments.get(seg) else {
+            continue;
+        };
+        let addr = (seg_start + start) as u16;
+        if best.get(&addr).is_none_or(|best| size < best) {
+            best.insert(addr, *size);
+            table.insert_line(addr, name, line);
+        }
+    }
+    Ok(table)
+}
+
+const BBC_OS: [(&str, u16); 82] = [
+    // Vectors in page 2
+    ("USERV", 0x0200),
+    ("BRKV", 0x0202),
+    ("IRQ1V", 0x0204),
+    ("IRQ2V", 0x0206),
+    ("CLIV", 0x0208),
+    ("BYTEV", 0x020A),
+    ("WORDV", 0x020C),
+    ("WRCHV", 0x020E),
+    ("RDCHV", 0x0210),
+    ("FILEV", 0x0212),
+    ("ARGSV", 0x0214),
+    ("BGETV", 0x0216),
+    ("BPUTV", 0x0218),
+    ("GBPBV", 0x021A),
+    ("FINDV", 0x021C),
+    ("FSCV", 0x021E),
+    ("EVNTV", 0x0220),
+    ("UPTV", 0x0222),
+    ("NETV", 0x0224),
+    ("VDUV", 0x0226),
+    ("KEYV", 0x0228),
+    ("INSV", 0x022A),
+    ("REMV", 0x022C),
+    ("CNPV", 0x022E),
+    ("IND1V", 0x0230),
+    ("IND2V", 0x0232),
+    ("IND3V", 0x0234),
+    // Where the hardware vectors point
+    ("NMI", 0x0D00),
+    ("RESET", 0xD9CD),
+    ("IRQ", 0xDC1C),
+    // SHEILA, the memory mapped hardware
+    ("CRTC_ADDR", 0xFE00),
+    ("CRTC_DATA", 0xFE01),
+    ("ACIA_CTRL", 0xFE08),
+    ("ACIA_DATA", 0xFE09),
+    ("SERPROC", 0xFE10),
+    ("VIDULA_CTRL", 0xFE20),
+    ("VIDULA_PAL", 0xFE21),
+    ("ROMSEL", 0xFE30),
+    ("SYSVIA_ORB", 0xFE40),
+    ("SYSVIA_ORA", 0xFE41),
+    ("SYSVIA_DDRB", 0xFE42),
+    ("SYSVIA_DDRA", 0xFE43),
+    ("SYSVIA_T1CL", 0xFE44),
+    ("SYSVIA_T1CH", 0xFE45),
+    ("SYSVIA_T1LL", 0xFE46),
+    ("SYSVIA_T1LH", 0xFE47),
+    ("SYSVIA_T2CL", 0xFE48),
+    ("SYSVIA_T2CH", 0xFE49),
+    ("SYSVIA_SR", 0xFE4A),
+    ("SYSVIA_ACR", 0xFE4B),
+    ("SYSVIA_PCR", 0xFE4C),
+    ("SYSVIA_IFR", 0xFE4D),
+    ("SYSVIA_IER", 0xFE4E),
+    ("SYSVIA_ORA_NH", 0xFE4F),
+    ("USRVIA_ORB", 0xFE60),
+    ("USRVIA_ORA", 0xFE61),
+    ("FDC", 0xFE80),
+    ("ECONET", 0xFEA0),
+    ("ADC", 0xFEC0),
+    ("TUBE", 0xFEE0),
+    // The OS calls
+    ("OSWRSC", 0xFFB3),
+    ("OSRDRM", 0xFFB9),
+    ("VDUCHR", 0xFFBC),
+    ("OSEVEN", 0xFFBF),
+    ("GSINIT", 0xFFC2),
+    ("GSREAD", 0xFFC5),
+    ("NVRDCH", 0xFFC8),
+    ("NVWRCH", 0xFFCB),
+    ("OSFIND", 0xFFCE),
+    ("OSGBPB", 0xFFD1),
+    ("OSBPUT", 0xFFD4),
+    ("OSBGET", 0xFFD7),
+    ("OSARGS", 0xFFDA),
+    ("OSFILE", 0xFFDD),
+    ("OSRDCH", 0xFFE0),
+    ("OSASCI", 0xFFE3),
+    ("OSNEWL", 0xFFE7),
+    ("OSWRCR", 0xFFEC),
+    ("OSWRCH", 0xFFEE),
+    ("OSWORD", 0xFFF1),
+    ("OSBYTE", 0xFFF4),
+    ("OSCLI", 0xFFF7),
+];
@@ -1,69 +1,115 @@
-use std::{cell::RefCell, rc::Rc, thread, time::{Duration, SystemTime}};
+use std::{cell::RefCell, io, rc::Rc, thread, time::{Duration, SystemTime}};
 
-use crate::{bus::{Bus, TickReturn}, cpu::cpu::CPU, devices::{bbcmicro::{paged_rom::{PagedRom, ROMSelectRegister}, system_via::SystemVIA, video_system::VideoSystem, video_ula::VideoULA}, mem::Mem, rom::Rom}, platform::{framebuffer::Fb, keyboard::Keyboard, logging::{NoLog, Stdout}}};
+use crate::{bus::{Bus, Mapping}, cpu::cpu::CPU, debug::{monitor::Monitor, rewind::Rewind, symbols::SymbolTable}, devices::{bbcmicro::{paged_rom::{PagedRom, ROMSelectRegister}, system_via::SystemVIA, video_system::VideoSystem, video_ula::VideoULA}, mem::Mem, rom::Rom}, platform::{framebuffer::Fb, keyboard::Keyboard}};
+
+// The 6502 in a BBC Micro runs at 2MHz
+const CYCLES_PER_SECOND: u64 = 2_000_000;
+
+// F11 goes back this far, snapshots are taken ten times a second and kept for half a minute
+const REWIND_SECONDS: u64 = 5;
+const SNAPSHOTS_PER_SECOND: u64 = 10;
+const SNAPSHOTS: usize = 300;
+
+// Where each device goes. SHEILA, the I/O in page $FE, is laid over the top of the OS ROM
+// and only decodes the low address lines each chip needs, so their registers repeat through
+// their blocks
+pub struct MemoryMap {
+    pub ram: Mapping,
+    pub paged_rom: Mapping,
+    pub crtc: Mapping,
+    pub video_ula: Mapping,
+    pub system_via: Mapping,
+    pub rom_select: Mapping,
+    pub os_rom: Mapping,
+}
+
+impl MemoryMap {
+    #[allow(clippy::should_implement_trait)]
+    pub fn default() -> Self {
+        let sheila = |range, mask| Mapping { priority: 1, ..Mapping::mirrored(range, mask) };
+        Self {
+            ram: Mapping::new(0x0000..=0x7FFF),
+            paged_rom: Mapping::new(0x8000..=0xBFFF),
+            crtc: sheila(0xFE00..=0xFE07, 0x01),
+            video_ula: sheila(0xFE20..=0xFE2F, 0x01),
+            system_via: sheila(0xFE40..=0xFE5F, 0x0F),
+            rom_select: sheila(0xFE30..=0xFE3F, 0x00),
+            os_rom: Mapping::new(0xC000..=0xFFFF),
+        }
+    }
+}
 
 pub struct BBCMicro {
     cpu: CPU,
     bus: Bus,
+    keyboard: Rc<RefCell<Keyboard>>,
+    monitor: Monitor,
 }
 
 impl BBCMicro {
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self{
         let mut cpu = CPU::default();
-        cpu.config.logger = Box::new(NoLog{});
         cpu.config.speed = 10.0;
+        // Run the devices in lockstep with the CPU so they see its accesses as they happen
+        cpu.config.cycle_accurate = true;
         let mut bus = Bus::default();
+        let map = MemoryMap::default();
 
         let ram = Rc::new(RefCell::new(Mem::default(32 * 1024)));
-        bus.register(0..=0x7FFF, Box::new(ram.clone()));
+        bus.map(map.ram, Box::new(ram.clone())).unwrap();
 
         let paged_rom = Rc::new(RefCell::new(PagedRom::default()));
         let basic = Rom::load("roms/bbc_micro/BASIC2.rom").unwrap_or(Rom::default(vec![0; 0xBFFF - 0x8000 + 1]));
         paged_rom.borrow_mut().add_rom(basic);
-        bus.register(0x8000..=0xBFFF, Box::new(paged_rom.clone()));
+        bus.map(map.paged_rom, Box::new(paged_rom.clone())).unwrap();
 
         let keyboard = Rc::new(RefCell::new(Keyboard::default()));
         let fb = Box::new(Fb::default(keyboard.clone()));
         let video_system= Rc::new(RefCell::new(VideoSystem::default(fb, Rc::clone(&ram))));
-        bus.register(0xFE00..=0xFE07, Box::new(video_system.clone()));
+        bus.map(map.crtc, Box::new(video_system.clone())).unwrap();
         
-        let video_ula = VideoULA{video_system: video_system};
-        bus.register(0xFE20..=0xFE2F, Box::new(video_ula));
+        let video_ula = VideoULA{video_system};
+        bus.map(map.video_ula, Box::new(video_ula)).unwrap();
 
         let system_via = SystemVIA::default(Rc::clone(&keyboard));
-        bus.register(0xFE40..=0xFE4F, Box::new(system_via));
+        bus.map(map.system_via, Box::new(system_via)).unwrap();
 
         let page_rom_select = ROMSelectRegister::default(paged_rom);
-        bus.register(0xFE30..=0xFE30, Box::new(page_rom_select));
+        bus.map(map.rom_select, Box::new(page_rom_select)).unwrap();
 
         let os_rom = Rom::load("roms/bbc_micro/OS-1.2.rom").unwrap_or(Rom::default(vec![0; 0xFFFF - 0xC000 + 1]));
-        bus.register(0xC000..=0xFFFF, Box::new(os_rom));
+        bus.map(map.os_rom, Box::new(os_rom)).unwrap();
 
         cpu.reset(&mut bus);
 
+        // OS calls show up by name in the monitor
+        let mut monitor = Monitor::default();
+        monitor.symbols = SymbolTable::bbc_os();
+        monitor.debugger.rewind = Some(Rewind::new(CYCLES_PER_SECOND / SNAPSHOTS_PER_SECOND, SNAPSHOTS));
+
         Self {
             cpu,
-            bus
+            bus,
+            keyboard,
+            monitor,
         }
     }
 
     pub fn tick(&mut self) -> bool {
-        let ticks = self.cpu.step(&mut self.bus, 1);
-
         let now = SystemTime::now();
-        for _ in 0..ticks{
-            match self.bus.tick() {
-                TickReturn::SHUTDOWN => {
-                    return false;
-                }
-                TickReturn::IRQ => {
-                    if !(self.cpu.read_status() & 0b0000_0100 != 0){
-                        let mut ticks_lc = 0 as u32;
-                        self.cpu.brk(&mut self.bus, &mut ticks_lc);
-                    }
-                }
-                TickReturn::NONE =>{}
-            }
+        if let Some(rewind) = &mut self.monitor.debugger.rewind {
+            rewind.record(&self.cpu, &self.bus);
+        }
+        let ticks = self.cpu.step(&mut self.bus, 1);
+        if self.bus.shutdown_requested() {
+            return false;
+        }
+        if self.keyboard.borrow_mut().take_monitor_request() {
+            self.enter_monitor();
+        }
+        if self.keyboard.borrow_mut().take_rewind_request() {
+            self.rewind();
         }
 
         let elapsed = now.elapsed().unwrap_or(Duration::from_micros(0));
@@ -76,4 +122,24 @@ impl BBCMicro {
         thread::sleep(sleep_time);
         true
     }
-}
\ No newline at end of file
+
+    // Goes back a few seconds, carrying on from there with the keys pressed live rather than
+    // the ones that were replayed to get there
+    pub fn rewind(&mut self) {
+        if let Some(rewind) = &mut self.monitor.debugger.rewind
+            && let Err(message) = rewind.rewind(&mut self.cpu, &mut self.bus, REWIND_SECONDS * CYCLES_PER_SECOND)
+        {
+            println!("Couldn't rewind: {}", message);
+        }
+        self.keyboard.borrow_mut().discard_future();
+    }
+
+    // Pauses the machine and runs the monitor on the terminal until it's left with `x`
+    pub fn enter_monitor(&mut self) {
+        println!("Entering the monitor, x to go back to the machine");
+        // The terminal going away just drops back to the machine
+        let _ = self.monitor.repl(&mut self.cpu, &mut self.bus, &mut io::stdin().lock(), &mut io::stdout());
+        // Going back or loading a state in the monitor carries on with live keys too
+        self.keyboard.borrow_mut().discard_future();
+    }
+}
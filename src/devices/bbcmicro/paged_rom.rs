@@ -1,6 +1,6 @@
 use std::{cell::RefCell, rc::Rc};
 
-use crate::{bus::{Device, TickReturn}, devices::rom::Rom};
+use crate::{bus::{Device, TickReturn}, devices::rom::Rom, snapshot::{StateReader, StateWriter}};
 
 pub struct ROMSelectRegister {
     paged_rom: Rc<RefCell<PagedRom>>,
@@ -8,7 +8,7 @@ pub struct ROMSelectRegister {
 
 impl ROMSelectRegister {
     pub fn default(paged_rom: Rc<RefCell<PagedRom>>) -> Self{
-        Self { paged_rom: paged_rom }
+        Self { paged_rom }
     }
 }
 
@@ -31,10 +31,11 @@ pub struct PagedRom {
 }
 
 impl PagedRom {
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> Self {
         Self {
             roms: vec![],
-            rom: u8::max_value()
+            rom: u8::MAX
         }
     }
 
@@ -66,4 +67,19 @@ impl Device for Rc<RefCell<PagedRom>> {
 
     #[allow(unused_variables)]
     fn tick(&mut self) -> TickReturn {TickReturn::NONE}
+
+    // Only which ROM is paged in, the ROMs themselves never change
+    fn save_state(&self, state: &mut StateWriter) {
+        state.u8(self.borrow().rom);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), String> {
+        let rom = state.u8()?;
+        let mut this = self.borrow_mut();
+        if rom != u8::MAX && rom as usize >= this.roms.len() {
+            return Err(format!("there's no paged ROM {}", rom));
+        }
+        this.rom = rom;
+        Ok(())
+    }
 }
@@ -3,6 +3,7 @@ use std::{cell::RefCell, rc::Rc};
 use crate::{
     bus::{Device, TickReturn},
     platform::keyboard::Keyboard,
+    snapshot::{StateReader, StateWriter},
 };
 
 const ROW_COUNT: usize = 8;
@@ -50,7 +51,10 @@ impl SystemVIA {
 
 impl Device for SystemVIA {
     fn read(&mut self, addr: u16) -> u8 {
-        println!("Read to system via at addr: {:X}", addr);
+        self.peek(addr)
+    }
+
+    fn peek(&mut self, addr: u16) -> u8 {
         match addr {
             // Port B
             0 => {
@@ -94,7 +98,6 @@ impl Device for SystemVIA {
     }
 
     fn write(&mut self, addr: u16, value: u8) {
-        println!("Write to system via at addr: {:X} with value {:X}", addr, value);
         match addr {
             // Port B
             0 => self.port_b = value,
@@ -126,6 +129,32 @@ impl Device for SystemVIA {
         }
     }
 
+    // Sets the registers to what they should read back, without anything a write from the CPU
+    // sets off, so the interrupt flags can be set as well as cleared
+    fn poke(&mut self, addr: u16, value: u8) {
+        match addr {
+            // Port B
+            0 => self.port_b = value,
+
+            // Port A
+            1 => self.port_a = value,
+
+            // DDRB
+            2 => self.port_b_direction = value,
+
+            // DDRA
+            3 => self.port_a_direction = value,
+
+            // IFR, bit 7 is worked out from the others
+            0xD => self.interrupt_flag = value & !IRQ_BIT,
+
+            // IER
+            0xE => self.interrupt_enable = value & !IRQ_BIT,
+
+            _ => {}
+        }
+    }
+
     fn tick(&mut self) -> TickReturn {
         for row in 0..ROW_COUNT {
             let current = self.keyboard.borrow().get_row(row as u8).unwrap_or(0xFF);
@@ -143,4 +172,24 @@ impl Device for SystemVIA {
             TickReturn::NONE
         }
     }
+
+    // The keyboard is only reached through the VIA so its state goes along with it
+    fn save_state(&self, state: &mut StateWriter) {
+        for value in [self.port_b_direction, self.port_a_direction, self.port_b, self.port_a, self.interrupt_enable, self.interrupt_flag] {
+            state.u8(value);
+        }
+        state.bytes(&self.last_matrix);
+        self.keyboard.borrow().save_state(state);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), String> {
+        self.port_b_direction = state.u8()?;
+        self.port_a_direction = state.u8()?;
+        self.port_b = state.u8()?;
+        self.port_a = state.u8()?;
+        self.interrupt_enable = state.u8()?;
+        self.interrupt_flag = state.u8()?;
+        state.fill(&mut self.last_matrix)?;
+        self.keyboard.borrow_mut().load_state(state)
+    }
 }
\ No newline at end of file
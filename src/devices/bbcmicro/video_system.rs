@@ -1,6 +1,6 @@
 use std::{cell::RefCell, rc::Rc};
 
-use crate::{bus::{Device, TickReturn}, devices::mem::Mem, platform::framebuffer::Fb};
+use crate::{bus::{Device, TickReturn}, devices::mem::Mem, platform::framebuffer::Fb, snapshot::{StateReader, StateWriter}};
 
 pub const PALETTE: [u32; 16] = [
     0x000000, // 0 black
@@ -51,6 +51,22 @@ impl VideoSystem {
         }
     }
 
+    // The CRTC registers, the mode and how far through the frame it is
+    fn save(&self, state: &mut StateWriter) {
+        state.u8(self.crtc_selected);
+        state.bytes(&self.crtc);
+        state.u8(self.mode);
+        state.u16(self.ticks_per_frame);
+    }
+
+    fn load(&mut self, state: &mut StateReader) -> Result<(), String> {
+        self.crtc_selected = state.u8()?;
+        state.fill(&mut self.crtc)?;
+        self.mode = state.u8()?;
+        self.ticks_per_frame = state.u16()?;
+        Ok(())
+    }
+
     fn render_frame(&mut self) -> bool {
         match self.mode {
             7 => self.render_mode7(),
@@ -71,7 +87,7 @@ impl VideoSystem {
                 let addr = base + (row * 40 + col) as u16;
                 let byte = mem.read(addr);
 
-                let c = if byte >= 32 && byte < 127 {
+                let c = if (32..127).contains(&byte) {
                     byte as char
                 } else {
                     ' '
@@ -150,6 +166,14 @@ impl Device for Rc<RefCell<VideoSystem>> {
         }
         TickReturn::NONE
     }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        self.borrow().save(state);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), String> {
+        self.borrow_mut().load(state)
+    }
 }
 
 impl Device for VideoSystem {
@@ -163,10 +187,8 @@ impl Device for VideoSystem {
                 self.crtc_selected = value & 0x1F;
             }
 
-            0x01 => {
-                if (self.crtc_selected as usize) < self.crtc.len() {
-                    self.crtc[self.crtc_selected as usize] = value;
-                }
+            0x01 if (self.crtc_selected as usize) < self.crtc.len() => {
+                self.crtc[self.crtc_selected as usize] = value;
             }
 
             0x20 => {
@@ -190,4 +212,12 @@ impl Device for VideoSystem {
         }
         TickReturn::NONE
     }
-}
\ No newline at end of file
+
+    fn save_state(&self, state: &mut StateWriter) {
+        self.save(state);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), String> {
+        self.load(state)
+    }
+}
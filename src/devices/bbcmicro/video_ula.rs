@@ -7,22 +7,12 @@ pub struct VideoULA{
 }
 
 impl Device for VideoULA {
-    fn read(&mut self, addr: u16) -> u8 {
-        println!("VideoULA {:02X}", addr);
-        0
-    }
+    #[allow(unused_variables)]
+    fn read(&mut self, addr: u16) -> u8 {0}
 
     fn write(&mut self, addr: u16, value: u8) {
-        match addr{
-            0 => {
-                match value{
-                    0x4B => {
-                        self.video_system.borrow_mut().mode = 7;
-                    }
-                    _ => {}
-                }
-            }
-            _ => {}
+        if addr == 0 && value == 0x4B {
+            self.video_system.borrow_mut().mode = 7;
         }
     }
 
@@ -1,6 +1,9 @@
 use std::{cell::RefCell, rc::Rc};
 
-use crate::bus::{Device, TickReturn};
+use crate::{
+    bus::{Device, TickReturn},
+    snapshot::{StateReader, StateWriter},
+};
 
 pub struct Mem {
     data: Vec<u8>,
@@ -9,10 +12,11 @@ pub struct Mem {
 impl Mem {
     pub fn default(len: usize) -> Self {
         Self {
-            data: vec![0 as u8; len],
+            data: vec![0_u8; len],
         }
     }
 
+    #[allow(clippy::len_without_is_empty)]
     pub fn len(&self) -> usize {
         self.data.len()
     }
@@ -29,6 +33,14 @@ impl Device for Mem {
 
     #[allow(unused_variables)]
     fn tick(&mut self) -> TickReturn {TickReturn::NONE}
+
+    fn save_state(&self, state: &mut StateWriter) {
+        state.bytes(&self.data);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), String> {
+        state.fill(&mut self.data)
+    }
 }
 
 impl Device for Rc<RefCell<Mem>> {
@@ -42,4 +54,12 @@ impl Device for Rc<RefCell<Mem>> {
 
     #[allow(unused_variables)]
     fn tick(&mut self) -> TickReturn {TickReturn::NONE}
+
+    fn save_state(&self, state: &mut StateWriter) {
+        self.borrow().save_state(state);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), String> {
+        self.borrow_mut().load_state(state)
+    }
 }
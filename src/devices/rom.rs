@@ -15,7 +15,7 @@ impl Rom {
 
     pub fn load(path: &str) -> Option<Self> {
         let contents = fs::read(path).unwrap_or(vec![]);
-        if contents.len() == 0 {
+        if contents.is_empty() {
             return None
         }
 
@@ -1,5 +1,7 @@
 pub mod bus;
 pub mod cpu;
+pub mod debug;
 pub mod devices;
 pub mod platform;
+pub mod snapshot;
 pub mod test;
@@ -1,7 +1,79 @@
-use emulate6502::devices::bbcmicro::bbc_micro::BBCMicro;
+use std::{env, io};
+
+use emulate6502::{
+    bus::Bus,
+    cpu::cpu::CPU,
+    debug::{dap::DapServer, gdb::GdbStub, monitor::Monitor},
+    devices::{bbcmicro::bbc_micro::BBCMicro, mem::Mem},
+};
 
 fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(String::as_str) == Some("--monitor") {
+        headless_monitor(&args[2..]);
+        return;
+    }
+    if args.get(1).map(String::as_str) == Some("--gdb") {
+        headless_gdb(&args[2..]);
+        return;
+    }
+    if args.get(1).map(String::as_str) == Some("--dap") {
+        headless_dap(&args[2..]);
+        return;
+    }
+
     let mut system = BBCMicro::new();
 
     while system.tick() {}
-}
\ No newline at end of file
+}
+
+// `--monitor [file [hex address]]` runs the monitor on a bare CPU with 64K of RAM and no
+// window, with the file loaded at the address (or $0000) and the PC pointing at it
+fn headless_monitor(args: &[String]) {
+    let (mut cpu, mut bus) = headless(args);
+    let _ = Monitor::default().repl(&mut cpu, &mut bus, &mut io::stdin().lock(), &mut io::stdout());
+}
+
+// `--gdb [port [file [hex address]]]` is the same but waits for gdb to connect on the port,
+// 2345 by default
+fn headless_gdb(args: &[String]) {
+    let port = args.first().and_then(|port| port.parse::<u16>().ok()).unwrap_or(2345);
+    let (mut cpu, mut bus) = headless(args.get(1..).unwrap_or_default());
+    println!("Waiting for gdb on 127.0.0.1:{}", port);
+    if let Err(e) = GdbStub::default().listen(&mut cpu, &mut bus, &format!("127.0.0.1:{}", port)) {
+        eprintln!("gdb: {}", e);
+    }
+}
+
+// `--dap [port]` serves the debug adapter protocol on stdin and stdout, or on the port if
+// there is one, for a headless machine that the launch request loads a program into
+fn headless_dap(args: &[String]) {
+    let (mut cpu, mut bus) = headless(&[]);
+    let mut server = DapServer::default();
+    let result = match args.first().and_then(|port| port.parse::<u16>().ok()) {
+        Some(port) => {
+            eprintln!("Waiting for a debug adapter client on 127.0.0.1:{}", port);
+            server.listen(&mut cpu, &mut bus, &format!("127.0.0.1:{}", port))
+        }
+        None => server.serve(&mut cpu, &mut bus, io::stdin(), io::stdout()),
+    };
+    if let Err(e) = result {
+        eprintln!("dap: {}", e);
+    }
+}
+
+fn headless(args: &[String]) -> (CPU, Bus) {
+    let mut cpu = CPU::default();
+    let mut bus = Bus::default();
+    bus.register(0..=0xFFFF, Box::new(Mem::default(1024 * 64)));
+
+    let addr = args.get(1).and_then(|addr| u16::from_str_radix(addr.trim_start_matches('$'), 16).ok()).unwrap_or(0);
+    if let Some(file) = args.first()
+        && !cpu.load_rom(&mut bus, file, addr)
+    {
+        eprintln!("Couldn't load {}", file);
+    }
+    cpu.reset(&mut bus);
+    cpu.pc = addr;
+    (cpu, bus)
+}
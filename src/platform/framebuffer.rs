@@ -17,7 +17,7 @@ pub struct Fb{
 impl Fb{
     pub fn default(keyboard: Rc<RefCell<Keyboard>>) -> Self{
         let mut window = Window::new(
-            "Test - ESC to exit",
+            "Test - ESC to exit, F12 for the monitor",
             WIDTH,
             HEIGHT,
             WindowOptions {
@@ -1,4 +1,10 @@
-use minifb::{Key, Window};
+use std::collections::BTreeMap;
+
+use minifb::{Key, KeyRepeat, Window};
+
+use crate::snapshot::{StateReader, StateWriter};
+
+const NO_KEYS: [u8; 8] = [0b11111111; 8];
 
 #[derive(Copy, Clone)]
 pub struct PlatformKey {
@@ -70,27 +76,89 @@ pub fn map_key(key: Key) -> Option<PlatformKey> {
 
 pub struct Keyboard{
     rows: [u8; 8],
+    // F12 was pressed, the machine drops into the monitor when it sees this
+    monitor_requested: bool,
+    // F11 was pressed, the machine goes back a few seconds
+    rewind_requested: bool,
+
+    // Counts the updates, one a frame. The rows are logged at every update they changed on,
+    // up to `recorded`, so after the machine is rewound it replays the same keys
+    frame: u64,
+    recorded: u64,
+    inputs: BTreeMap<u64, [u8; 8]>,
 }
 
 impl Keyboard {
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> Self {
         Self {
-            rows: [0b11111111; 8]
+            rows: NO_KEYS,
+            monitor_requested: false,
+            rewind_requested: false,
+            frame: 0,
+            recorded: 0,
+            inputs: BTreeMap::new(),
         }
     }
 
+    pub fn take_monitor_request(&mut self) -> bool {
+        std::mem::take(&mut self.monitor_requested)
+    }
+
+    pub fn take_rewind_request(&mut self) -> bool {
+        std::mem::take(&mut self.rewind_requested)
+    }
+
     pub fn update_keys(&mut self, window: &Window) {
-        self.rows = [0b11111111; 8]; // Reset pressed keys
+        if window.is_key_pressed(Key::F12, KeyRepeat::No) {
+            self.monitor_requested = true;
+        }
+        if window.is_key_pressed(Key::F11, KeyRepeat::No) {
+            self.rewind_requested = true;
+        }
+
+        let mut rows = NO_KEYS;
         let cur_keys = window.get_keys();
         for key in cur_keys{
-            let platform_key = map_key(key);
-            if platform_key.is_some(){
-                let platform_key = platform_key.unwrap();
+            if let Some(platform_key) = map_key(key) {
                 let row = platform_key.row;
                 let bit = platform_key.bit;
-                self.rows[row] &= !(1 << bit);
+                rows[row] &= !(1 << bit);
             }
         }
+        self.update(rows);
+    }
+
+    // A frame's worth of keys, `rows` are the ones held down now. Frames that have already
+    // been recorded take the logged keys instead
+    pub fn update(&mut self, rows: [u8; 8]) {
+        self.frame += 1;
+        if self.frame <= self.recorded {
+            self.rows = self.inputs.range(..=self.frame).next_back().map_or(NO_KEYS, |(_, rows)| *rows);
+            return;
+        }
+        if rows != self.rows {
+            self.inputs.insert(self.frame, rows);
+        }
+        self.rows = rows;
+        self.recorded = self.frame;
+    }
+
+    // Forgets the keys logged after the current frame, so what happens from here is live
+    pub fn discard_future(&mut self) {
+        self.inputs.split_off(&(self.frame + 1));
+        self.recorded = self.frame;
+    }
+
+    // Where it is in the log and the keys held, the log itself isn't part of the state
+    pub fn save_state(&self, state: &mut StateWriter) {
+        state.u64(self.frame);
+        state.bytes(&self.rows);
+    }
+
+    pub fn load_state(&mut self, state: &mut StateReader) -> Result<(), String> {
+        self.frame = state.u64()?;
+        state.fill(&mut self.rows)
     }
 
     pub fn get_row(&self, row: u8) -> Option<u8> {
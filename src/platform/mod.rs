@@ -1,4 +1,3 @@
-pub mod logging;
 pub mod framebuffer;
 pub mod keyboard;
 pub mod text;
@@ -42,7 +42,7 @@ impl Text {
         //if text != " " {println!("{}", text);}
         for c in text.chars() {
             let mut index = (c as usize).wrapping_sub(' ' as usize);
-            if index > MICROKNIGHT_LAYOUT.len() as usize {
+            if index > MICROKNIGHT_LAYOUT.len() {
                 index = 0;
             }
 
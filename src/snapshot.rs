@@ -0,0 +1,178 @@
+use std::fs;
+
+use crate::{bus::Bus, cpu::cpu::CPU};
+
+// Save state files start with MAGIC and then VERSION, which goes up whenever anything saves
+// its state differently. The layout is in the ReadMe
+const MAGIC: &[u8; 8] = b"6502SNAP";
+pub const VERSION: u16 = 3;
+
+// Machine state as bytes. The CPU and each device write what they need to carry on from where
+// they are with `save_state`, and read it back in the same order with `load_state`
+pub struct StateWriter {
+    bytes: Vec<u8>,
+}
+
+impl StateWriter {
+    #[allow(clippy::should_implement_trait)]
+    pub fn default() -> Self {
+        Self { bytes: vec![] }
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.bytes.push(value as u8);
+    }
+
+    pub fn u16(&mut self, value: u16) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn f64(&mut self, value: f64) {
+        self.u64(value.to_bits());
+    }
+
+    // A block of bytes with its length in front
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+pub struct StateReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let bytes = self.bytes.get(self.pos..self.pos + len).ok_or("the state ends early")?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn bool(&mut self) -> Result<bool, String> {
+        Ok(self.u8()? != 0)
+    }
+
+    pub fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    pub fn f64(&mut self) -> Result<f64, String> {
+        Ok(f64::from_bits(self.u64()?))
+    }
+
+    pub fn bytes(&mut self) -> Result<&'a [u8], String> {
+        let len = u32::from_le_bytes(self.take(4)?.try_into().unwrap());
+        self.take(len as usize)
+    }
+
+    // Reads a block written by `StateWriter::bytes` into `into`, which has to be the same size
+    pub fn fill(&mut self, into: &mut [u8]) -> Result<(), String> {
+        let bytes = self.bytes()?;
+        if bytes.len() != into.len() {
+            return Err(format!("expected {} bytes of state, found {}", into.len(), bytes.len()));
+        }
+        into.copy_from_slice(bytes);
+        Ok(())
+    }
+
+    // Errors if there's anything left, which means it was written by something else
+    pub fn finish(&self) -> Result<(), String> {
+        if self.pos != self.bytes.len() {
+            return Err(format!("{} bytes of state left over", self.bytes.len() - self.pos));
+        }
+        Ok(())
+    }
+}
+
+// The state of a CPU and every device on its bus at one moment. It can only be restored to
+// the machine it was taken from, or one built the same way. As a file it's MAGIC, VERSION and
+// the cycle count, followed by the CPU's state and then the bus's
+#[derive(Clone)]
+pub struct Snapshot {
+    cycles: u64,
+    state: Vec<u8>,
+}
+
+impl Snapshot {
+    pub fn take(cpu: &CPU, bus: &Bus) -> Self {
+        let mut state = StateWriter::default();
+        cpu.save_state(&mut state);
+        bus.save_state(&mut state);
+        Self { cycles: cpu.read_cycles(), state: state.into_bytes() }
+    }
+
+    // Puts the machine back how it was. On an error the machine may have been partly restored
+    pub fn restore(&self, cpu: &mut CPU, bus: &mut Bus) -> Result<(), String> {
+        let mut state = StateReader::new(&self.state);
+        cpu.load_state(&mut state)?;
+        bus.load_state(&mut state)?;
+        state.finish()
+    }
+
+    // The CPU's cycle count when it was taken
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.state.len()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.cycles.to_le_bytes());
+        bytes.extend_from_slice(&self.state);
+        bytes
+    }
+
+    // Checks the header, the rest is only checked when it's restored
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let Some(state) = bytes.strip_prefix(MAGIC) else {
+            return Err("not a save state".to_string());
+        };
+        let mut header = StateReader::new(state);
+        let version = header.u16().map_err(|_| "the header is cut short")?;
+        if version != VERSION {
+            return Err(format!("a version {} save state, only version {} can be loaded", version, VERSION));
+        }
+        let cycles = header.u64().map_err(|_| "the header is cut short")?;
+        Ok(Self { cycles, state: state[10..].to_vec() })
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.to_bytes()).map_err(|e| format!("couldn't write {}: {}", path, e))
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| format!("couldn't read {}: {}", path, e))?;
+        Self::from_bytes(&bytes).map_err(|e| format!("{}: {}", path, e))
+    }
+}
@@ -0,0 +1,212 @@
+#[cfg(test)]
+mod assembler_tests {
+    use std::fs;
+
+    use crate::bus::Bus;
+    use crate::cpu::assembler::Assembler;
+    use crate::cpu::config::CpuVariant;
+    use crate::cpu::cpu::CPU;
+    use crate::devices::mem::Mem;
+
+    fn bytes(source: &str) -> Vec<u8> {
+        Assembler::new(CpuVariant::Nmos6502).assemble(source).unwrap().to_binary()
+    }
+
+    fn error(source: &str) -> String {
+        Assembler::new(CpuVariant::Nmos6502).assemble(source).err().unwrap().to_string()
+    }
+
+    // Assembles `source`, loads it and runs from its origin
+    fn run(source: &str, steps: u32) -> (CPU, Bus) {
+        let assembly = Assembler::new(CpuVariant::Nmos6502).assemble(source).unwrap();
+        let mut cpu = CPU::default();
+        let mut bus = Bus::default();
+        bus.register(0..=0xFFFF, Box::new(Mem::default(1024 * 64)));
+        assembly.load(&mut bus);
+        let [lo, hi] = assembly.origin().to_le_bytes();
+        bus.write(0xFFFC, lo);
+        bus.write(0xFFFD, hi);
+        cpu.reset(&mut bus);
+        cpu.step(&mut bus, steps);
+        (cpu, bus)
+    }
+
+    #[test]
+    fn addressing_modes() {
+        let source = "
+            LDA #$10
+            LDA $10
+            LDA $10,X
+            LDX $10,Y
+            LDA $1234
+            LDA $1234,X
+            LDA $1234,Y
+            JMP ($1234)
+            LDA ($10,X)
+            LDA ($10),Y
+            ASL A
+            ASL
+            RTS
+        ";
+        assert_eq!(
+            bytes(source),
+            [
+                0xA9, 0x10, 0xA5, 0x10, 0xB5, 0x10, 0xB6, 0x10, 0xAD, 0x34, 0x12, 0xBD, 0x34, 0x12, 0xB9, 0x34, 0x12,
+                0x6C, 0x34, 0x12, 0xA1, 0x10, 0xB1, 0x10, 0x0A, 0x0A, 0x60
+            ]
+        );
+    }
+
+    #[test]
+    fn labels_and_branches() {
+        // Labels in the first column don't need a colon
+        let source = "\
+        *= $C000
+start:  LDX #$05
+loop    DEX
+        BNE loop
+        BEQ done
+        NOP
+done:   JMP start
+";
+        let assembly = Assembler::new(CpuVariant::Nmos6502).assemble(source).unwrap();
+        assert_eq!(assembly.origin(), 0xC000);
+        assert_eq!(
+            assembly.to_binary(),
+            [0xA2, 0x05, 0xCA, 0xD0, 0xFD, 0xF0, 0x01, 0xEA, 0x4C, 0x00, 0xC0]
+        );
+        assert_eq!(assembly.symbols.address_of("loop"), Some(0xC002));
+        assert_eq!(assembly.symbols.name_at(0xC008), Some("done"));
+    }
+
+    #[test]
+    fn forward_references_are_absolute() {
+        // Neither is known when the LDAs are sized, so they stay absolute even though they end
+        // up in zero page
+        let source = "
+            LDA later
+            LDA early
+        early = $20
+        later = early + 1
+        ";
+        assert_eq!(bytes(source), [0xAD, 0x21, 0x00, 0xAD, 0x20, 0x00]);
+
+        let source = "
+        early = $20
+            LDA later
+            LDA early
+        later = early + 1
+        ";
+        assert_eq!(bytes(source), [0xAD, 0x21, 0x00, 0xA5, 0x20]);
+    }
+
+    #[test]
+    fn expressions() {
+        let source = "
+        table = $1234
+            LDA #<table
+            LDX #>table
+            LDY #(2 + 3) * 4
+            LDA #%1010 | $F0
+            LDA #'A'
+            LDA #-1
+            LDA table+1,X
+            JMP *
+        ";
+        assert_eq!(
+            bytes(source),
+            [0xA9, 0x34, 0xA2, 0x12, 0xA0, 0x14, 0xA9, 0xFA, 0xA9, 0x41, 0xA9, 0xFF, 0xBD, 0x35, 0x12, 0x4C, 0x0F, 0x00]
+        );
+    }
+
+    #[test]
+    fn data_directives() {
+        let source = "
+            *= $0300
+        text:   .byte \"Hi;\", 0, <text
+            .word text, $BEEF
+            .org $0310
+            .db 1
+        ";
+        let assembly = Assembler::new(CpuVariant::Nmos6502).assemble(source).unwrap();
+        assert_eq!(assembly.segments.len(), 2);
+        assert_eq!(assembly.segments[1].origin, 0x0310);
+        let binary = assembly.to_binary();
+        assert_eq!(&binary[..9], [b'H', b'i', b';', 0x00, 0x00, 0x00, 0x03, 0xEF, 0xBE]);
+        assert_eq!(binary.len(), 0x11);
+        assert_eq!(binary[0x10], 1);
+    }
+
+    #[test]
+    fn cmos_instructions() {
+        let source = "
+            *= $0200
+        loop:   BRA next
+            LDA ($10)
+            JMP ($1234,X)
+            STZ $10
+        next:   BBR0 $10,loop
+            RMB7 $10
+        ";
+        let assembly = Assembler::new(CpuVariant::Wdc65C02).assemble(source).unwrap();
+        assert_eq!(
+            assembly.to_binary(),
+            [0x80, 0x07, 0xB2, 0x10, 0x7C, 0x34, 0x12, 0x64, 0x10, 0x0F, 0x10, 0xF4, 0x77, 0x10]
+        );
+        assert!(Assembler::new(CpuVariant::Nmos6502).assemble("STZ $10").is_err());
+    }
+
+    #[test]
+    fn undocumented_instructions() {
+        assert_eq!(bytes("LAX $10\nNOP\nSBC #1"), [0xA7, 0x10, 0xEA, 0xE9, 0x01]);
+        let mut assembler = Assembler::new(CpuVariant::Nmos6502);
+        assembler.undocumented_opcodes = false;
+        assert!(assembler.assemble("LAX $10").is_err());
+    }
+
+    #[test]
+    fn errors_have_line_numbers() {
+        assert_eq!(error("NOP\n  FOO #1"), "line 2: unknown instruction FOO");
+        assert_eq!(error("LDA #$100"), "line 1: 256 doesn't fit in a byte");
+        assert_eq!(error("JMP nowhere"), "line 1: unknown label nowhere");
+        assert_eq!(error("a: NOP\na: NOP"), "line 2: a is defined twice");
+        assert_eq!(error("STX $1234,Y"), "line 1: $1234 isn't in the zero page");
+        assert_eq!(error(".align 2"), "line 1: unknown directive .align");
+        assert_eq!(error("*= $0200\nBNE $0300"), "line 2: branch to $0300 is out of range");
+    }
+
+    #[test]
+    fn assembled_program_runs() {
+        let source = "
+            *= $0400
+            LDX #$00
+        copy:   LDA message,X
+            STA $0200,X
+            INX
+            CPX #message_end - message
+            BNE copy
+            BRK
+        message: .byte \"6502\"
+        message_end:
+        ";
+        let (cpu, mut bus) = run(source, 1 + 5 * 4);
+        assert_eq!(cpu.read_x(), 4);
+        assert_eq!([bus.read(0x0200), bus.read(0x0203)], [b'6', b'2']);
+    }
+
+    #[test]
+    fn self_made_rom() {
+        let source = fs::read_to_string("roms/self-made.asm").unwrap();
+        let assembly = Assembler::new(CpuVariant::Nmos6502).assemble(&source).unwrap();
+        let success = assembly.symbols.address_of("successloop").unwrap();
+
+        let mut cpu = CPU::default();
+        let mut bus = Bus::default();
+        bus.register(0..=0xFFFF, Box::new(Mem::default(1024 * 64)));
+        assembly.load(&mut bus);
+        cpu.reset(&mut bus);
+
+        assert_eq!(cpu.run(&mut bus, success, Some(1000)), 0);
+        assert_eq!(bus.read(0x6666), 0xFF);
+    }
+}
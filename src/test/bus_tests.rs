@@ -0,0 +1,238 @@
+#[cfg(test)]
+mod bus_tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use crate::bus::{Bus, Device, Mapping, TickReturn, UnmappedAccesses, UnmappedReads};
+    use crate::devices::bbcmicro::bbc_micro::MemoryMap;
+    use crate::devices::bbcmicro::system_via::SystemVIA;
+    use crate::devices::mem::Mem;
+    use crate::devices::rom::Rom;
+    use crate::platform::keyboard::Keyboard;
+
+    // The offsets a probe was asked for, with the value for writes
+    type Seen = Rc<RefCell<Vec<(u16, Option<u8>)>>>;
+
+    // Reads give back `tag` and remember the offset asked for, writes are kept
+    struct Probe {
+        tag: u8,
+        seen: Seen,
+    }
+
+    impl Device for Probe {
+        fn read(&mut self, addr: u16) -> u8 {
+            self.seen.borrow_mut().push((addr, None));
+            self.tag
+        }
+
+        fn write(&mut self, addr: u16, value: u8) {
+            self.seen.borrow_mut().push((addr, Some(value)));
+        }
+
+        fn tick(&mut self) -> TickReturn {
+            TickReturn::NONE
+        }
+    }
+
+    fn probe(tag: u8) -> (Box<Probe>, Seen) {
+        let seen = Rc::new(RefCell::new(vec![]));
+        (Box::new(Probe { tag, seen: seen.clone() }), seen)
+    }
+
+    #[test]
+    fn addresses_go_to_the_right_device() {
+        let mut bus = Bus::default();
+        let (crtc, crtc_seen) = probe(1);
+        let (via, via_seen) = probe(2);
+        bus.register(0xFE00..=0xFE07, crtc);
+        bus.register(0xFE40..=0xFE4F, via);
+        bus.register(0x0000..=0x7FFF, Box::new(Mem::default(0x8000)));
+
+        // Page $FE is shared, each device is handed the offset from its start
+        assert_eq!(bus.read(0xFE01), 1);
+        assert_eq!(bus.read(0xFE4F), 2);
+        bus.write(0xFE43, 0x99);
+        assert_eq!(*crtc_seen.borrow(), [(1, None)]);
+        assert_eq!(*via_seen.borrow(), [(0x0F, None), (3, Some(0x99))]);
+
+        // Nothing answers the gaps, so they read the last value on the data bus
+        assert_eq!(bus.read(0xFE08), 0x99);
+        assert_eq!(bus.read(0x8000), 0x99);
+        bus.write(0x1234, 0x56);
+        assert_eq!(bus.read(0x1234), 0x56);
+        assert_eq!(bus.peek(0x1234), 0x56);
+    }
+
+    #[test]
+    fn overlaps_need_a_priority() {
+        let mut bus = Bus::default();
+        let (low, _) = probe(1);
+        let (high, high_seen) = probe(2);
+        bus.register(0x1000..=0x10FF, low);
+        assert_eq!(bus.read(0x1180), 0);
+
+        let (clash, _) = probe(3);
+        assert_eq!(bus.map(Mapping::new(0x1080..=0x11FF), clash).err().unwrap(), "$1080-$11FF overlaps the device at $1000-$10FF, both at priority 0");
+        let (empty, _) = probe(3);
+        let (start, end) = (0x1100, 0x10FF);
+        assert_eq!(bus.map(Mapping::new(start..=end), empty).err().unwrap(), "$1100-$10FF is empty");
+        assert_eq!(bus.read(0x1180), 0);
+
+        // Laid over the first, the overlap goes to it
+        bus.map(Mapping { priority: 1, ..Mapping::new(0x1080..=0x11FF) }, high).unwrap();
+        assert_eq!(bus.read(0x107F), 1);
+        assert_eq!(bus.read(0x10FF), 2);
+        assert_eq!(bus.read(0x1180), 2);
+        assert_eq!(*high_seen.borrow(), [(0x7F, None), (0x100, None)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "$0000-$0000 overlaps the device at $0000-$FFFF, both at priority 0")]
+    fn registering_over_a_device_panics() {
+        let mut bus = Bus::default();
+        bus.register(0..=0xFFFF, Box::new(Mem::default(0x10000)));
+        bus.register(0..=0, Box::new(Mem::default(1)));
+    }
+
+    #[test]
+    fn mirrors() {
+        // 2K of RAM repeated four times like the NES's
+        let mut bus = Bus::default();
+        bus.map(Mapping::mirrored(0x0000..=0x1FFF, 0x07FF), Box::new(Mem::default(0x800))).unwrap();
+        bus.write(0x0012, 0x34);
+        assert_eq!(bus.read(0x0812), 0x34);
+        assert_eq!(bus.read(0x1812), 0x34);
+        bus.write(0x1FFF, 0x56);
+        assert_eq!(bus.peek(0x07FF), 0x56);
+        assert_eq!(bus.read(0x2000), 0x56);
+
+        // A VIA's 16 registers through 32 bytes, on a page it shares
+        let (via, via_seen) = probe(1);
+        bus.map(Mapping::mirrored(0xFE40..=0xFE5F, 0x0F), via).unwrap();
+        bus.write(0xFE4E, 0x82);
+        bus.write(0xFE5E, 0x02);
+        assert_eq!(bus.read(0xFE5F), 1);
+        assert_eq!(*via_seen.borrow(), [(0x0E, Some(0x82)), (0x0E, Some(0x02)), (0x0F, None)]);
+    }
+
+    #[test]
+    fn handles_reach_the_device() {
+        let mut bus = Bus::default();
+        // So where nothing answers can't look like the device
+        bus.set_unmapped_reads(UnmappedReads::Value(0));
+        let (device, _) = probe(1);
+        let handle = bus.register(0xFE40..=0xFE4F, device);
+        assert_eq!(bus.device(handle).unwrap().tag, 1);
+        bus.device_mut(handle).unwrap().tag = 5;
+        assert_eq!(bus.read(0xFE40), 5);
+        assert_eq!(bus.mapping(handle), Some(&Mapping::new(0xFE40..=0xFE4F)));
+
+        // Moved, it keeps its state
+        bus.remap(handle, Mapping::mirrored(0xFE60..=0xFE7F, 0x0F)).unwrap();
+        assert_eq!(bus.read(0xFE40), 0);
+        assert_eq!(bus.read(0xFE7F), 5);
+        let other = bus.register(0x0000..=0x00FF, Box::new(Mem::default(0x100)));
+        assert_eq!(bus.remap(other, Mapping::new(0xFE00..=0xFEFF)).err().unwrap(), "$FE00-$FEFF overlaps the device at $FE60-$FE7F, both at priority 0");
+        assert_eq!(bus.mapping(other), Some(&Mapping::new(0x0000..=0x00FF)));
+
+        // Taken off the bus it comes back as what it was
+        let device = bus.unregister(handle).unwrap();
+        assert_eq!(device.seen.borrow().len(), 2);
+        assert_eq!(bus.read(0xFE7F), 0);
+        assert!(bus.unregister(handle).is_none());
+        assert!(bus.device(handle).is_none());
+        assert_eq!(bus.remap(handle, Mapping::new(0..=0)).err().unwrap(), "the device isn't on the bus");
+        bus.register(0xFE00..=0xFEFF, Box::new(Mem::default(0x100)));
+    }
+
+    #[test]
+    fn swapping_a_rom() {
+        let mut bus = Bus::default();
+        let basic = bus.register(0x8000..=0xBFFF, Box::new(Rom::default(vec![0xAA; 0x4000])));
+        let (mut old, game) = bus.replace(basic, Box::new(Rom::default(vec![0xBB; 0x4000]))).unwrap();
+        assert_eq!(old.read(0), 0xAA);
+        assert_eq!(bus.read(0x8000), 0xBB);
+        assert_eq!(bus.mapping(game), Some(&Mapping::new(0x8000..=0xBFFF)));
+
+        // The old handle doesn't reach what replaced it
+        assert!(bus.device(basic).is_none());
+        assert!(bus.replace(basic, Box::new(Mem::default(1))).is_none());
+        let (probe, _) = probe(1);
+        let probe = bus.replace(game, probe).unwrap().1;
+        assert_eq!(bus.read(0x8000), 1);
+        assert!(bus.device(game).is_none());
+        assert_eq!(bus.device(probe).unwrap().tag, 1);
+    }
+
+    #[test]
+    fn peeking_and_poking_io() {
+        let mut bus = Bus::default();
+        bus.register(0xFE40..=0xFE4F, Box::new(SystemVIA::default(Rc::new(RefCell::new(Keyboard::default())))));
+
+        // A poke can set an interrupt flag, which a write would clear
+        bus.poke(0xFE4D, 0x02);
+        assert_eq!(bus.peek(0xFE4D), 0x02);
+        bus.poke(0xFE4E, 0x02);
+        assert_eq!(bus.peek(0xFE4D), 0x82);
+        assert_eq!(bus.peek(0xFE4D), 0x82);
+        bus.write(0xFE4D, 0x02);
+        assert_eq!(bus.peek(0xFE4D), 0x00);
+        bus.poke(0xFE42, 0xFF);
+        assert_eq!(bus.read(0xFE42), 0xFF);
+        bus.write(0xFE43, 0x7F);
+        assert_eq!(bus.peek(0xFE43), 0x7F);
+
+        // Devices without a poke of their own are written to
+        let (device, seen) = probe(1);
+        bus.register(0x0000..=0x00FF, device);
+        bus.poke(0x0012, 0x34);
+        assert_eq!(*seen.borrow(), [(0x12, Some(0x34))]);
+    }
+
+    #[test]
+    fn open_bus() {
+        let mut bus = Bus::default();
+        bus.register(0x0000..=0x7FFF, Box::new(Mem::default(0x8000)));
+        bus.write(0x0010, 0x4C);
+        assert_eq!(bus.read(0x0010), 0x4C);
+        bus.write(0x0011, 0x20);
+        assert_eq!(bus.read(0xC000), 0x20);
+        assert_eq!(bus.peek(0xC001), 0x20);
+        // Writes where nothing answers are still on the bus, peeks and pokes aren't
+        bus.write(0xC000, 0x33);
+        bus.poke(0x0012, 0x44);
+        bus.peek(0x0012);
+        assert_eq!(bus.read(0xD000), 0x33);
+
+        bus.set_unmapped_reads(UnmappedReads::Value(0xFF));
+        assert_eq!(bus.read(0xD000), 0xFF);
+        assert_eq!(bus.unmapped_accesses(0xC0), UnmappedAccesses { reads: 1, writes: 1 });
+        assert_eq!(bus.unmapped_accesses(0xD0), UnmappedAccesses { reads: 2, writes: 0 });
+        assert_eq!(bus.unmapped_accesses(0x00), UnmappedAccesses::default());
+        bus.clear_unmapped_accesses();
+        assert_eq!(bus.unmapped_accesses(0xD0), UnmappedAccesses::default());
+    }
+
+    #[test]
+    fn bbc_memory_map() {
+        // The BBC Micro's layout with memory in place of its devices, which need a window
+        let map = MemoryMap::default();
+        let mut bus = Bus::default();
+        let mappings = [map.ram, map.paged_rom, map.crtc, map.video_ula, map.system_via, map.rom_select, map.os_rom];
+        for (i, mapping) in mappings.into_iter().enumerate() {
+            let len = (mapping.range.end() - mapping.range.start()) as usize + 1;
+            let mut mem = Mem::default(len);
+            mem.write(0, i as u8);
+            bus.map(mapping, Box::new(mem)).unwrap();
+        }
+
+        // SHEILA answers over the OS ROM, which has the rest of the page
+        assert_eq!(bus.read(0xFE00), 2);
+        assert_eq!(bus.read(0xFE22), 3);
+        assert_eq!(bus.read(0xFE50), 4);
+        assert_eq!(bus.read(0xFE3F), 5);
+        assert_eq!(bus.read(0xC000), 6);
+        bus.write(0xFE10, 0x77);
+        assert_eq!(bus.peek(0xFE10), 0x77);
+        assert_eq!(bus.peek(0x8000), 1);
+    }
+}
@@ -0,0 +1,313 @@
+#[cfg(test)]
+mod cmos_tests {
+    use crate::bus::Bus;
+    use crate::cpu::config::CpuVariant;
+    use crate::cpu::cpu::CPU;
+    use crate::devices::mem::Mem;
+
+    fn init() -> (CPU, Bus) {
+        let mut cpu = CPU::default();
+        cpu.config.variant = CpuVariant::Wdc65C02;
+        let mut bus = Bus::default();
+        let mem = Box::new(Mem::default(1024 * 64));
+        bus.register(0..=0xFFFF, mem);
+
+        bus.write(0xFFFC, 0x00);
+        bus.write(0xFFFD, 0x00);
+        cpu.reset(&mut bus);
+        (cpu, bus)
+    }
+
+    #[test]
+    fn every_opcode_is_defined() {
+        for ins in 0..=0xFFu8 {
+            // BRK and the halting opcodes are covered on their own
+            if matches!(ins, 0x00 | 0xCB | 0xDB) {
+                continue;
+            }
+            let (mut cpu, mut bus) = init();
+            bus.write(0x0000, ins);
+            cpu.step(&mut bus, 1);
+            assert!(!cpu.is_jammed(), "opcode {:02X} jammed", ins);
+        }
+    }
+
+    #[test]
+    fn bra_always_branches() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0x80); // BRA
+        bus.write(0x0001, 0x10);
+        let ticks = cpu.step(&mut bus, 1);
+        assert_eq!(cpu.pc, 0x0012);
+        assert_eq!(ticks, 3);
+    }
+
+    #[test]
+    fn phx_plx_phy_ply() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0xA2); // LDX immediate
+        bus.write(0x0001, 0x80);
+        bus.write(0x0002, 0xDA); // PHX
+        bus.write(0x0003, 0xA0); // LDY immediate
+        bus.write(0x0004, 0x00);
+        bus.write(0x0005, 0x5A); // PHY
+        bus.write(0x0006, 0xFA); // PLX
+        bus.write(0x0007, 0x7A); // PLY
+        cpu.step(&mut bus, 4);
+        assert_eq!(bus.read(0x01FD), 0x80);
+        assert_eq!(bus.read(0x01FC), 0x00);
+
+        let ticks = cpu.step(&mut bus, 1);
+        assert_eq!(cpu.read_x(), 0x00);
+        assert_eq!(cpu.read_status() & 0b00000010, 0b00000010); // Z set
+        assert_eq!(ticks, 4);
+
+        cpu.step(&mut bus, 1);
+        assert_eq!(cpu.read_y(), 0x80);
+        assert_eq!(cpu.read_status() & 0b10000000, 0b10000000); // N set
+        assert_eq!(cpu.read_sp(), 0xFD);
+    }
+
+    #[test]
+    fn stz_absolute_x() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0xA2); // LDX immediate
+        bus.write(0x0001, 0x02);
+        bus.write(0x0002, 0x9E); // STZ absolute, X
+        bus.write(0x0003, 0x00);
+        bus.write(0x0004, 0x20);
+        bus.write(0x2002, 0xFF);
+        cpu.step(&mut bus, 1);
+        let ticks = cpu.step(&mut bus, 1);
+        assert_eq!(bus.read(0x2002), 0x00);
+        assert_eq!(ticks, 5);
+    }
+
+    #[test]
+    fn tsb_and_trb() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0xA9); // LDA immediate
+        bus.write(0x0001, 0x0F);
+        bus.write(0x0002, 0x04); // TSB zero page
+        bus.write(0x0003, 0x10);
+        bus.write(0x0004, 0x1C); // TRB absolute
+        bus.write(0x0005, 0x00);
+        bus.write(0x0006, 0x20);
+        bus.write(0x0010, 0xF0);
+        bus.write(0x2000, 0xFF);
+        cpu.step(&mut bus, 1);
+
+        let ticks = cpu.step(&mut bus, 1);
+        assert_eq!(bus.read(0x0010), 0xFF);
+        assert_eq!(cpu.read_status() & 0b00000010, 0b00000010); // Z set, no common bits
+        assert_eq!(ticks, 5);
+
+        let ticks = cpu.step(&mut bus, 1);
+        assert_eq!(bus.read(0x2000), 0xF0);
+        assert_eq!(cpu.read_status() & 0b00000010, 0); // Z clear
+        assert_eq!(ticks, 6);
+    }
+
+    #[test]
+    fn lda_and_sta_zp_indirect() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0xB2); // LDA (zp)
+        bus.write(0x0001, 0x10);
+        bus.write(0x0002, 0x92); // STA (zp)
+        bus.write(0x0003, 0x12);
+        bus.write(0x0010, 0x00);
+        bus.write(0x0011, 0x30);
+        bus.write(0x0012, 0x00);
+        bus.write(0x0013, 0x40);
+        bus.write(0x3000, 0x99);
+        let ticks = cpu.step(&mut bus, 1);
+        assert_eq!(cpu.read_acc(), 0x99);
+        assert_eq!(ticks, 5);
+        cpu.step(&mut bus, 1);
+        assert_eq!(bus.read(0x4000), 0x99);
+    }
+
+    #[test]
+    fn inc_and_dec_accumulator() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0x1A); // INC A
+        bus.write(0x0001, 0x3A); // DEC A
+        bus.write(0x0002, 0x3A); // DEC A
+        let ticks = cpu.step(&mut bus, 1);
+        assert_eq!(cpu.read_acc(), 0x01);
+        assert_eq!(ticks, 2);
+        cpu.step(&mut bus, 2);
+        assert_eq!(cpu.read_acc(), 0xFF);
+        assert_eq!(cpu.read_status() & 0b10000000, 0b10000000); // N set
+    }
+
+    #[test]
+    fn bit_immediate_only_sets_z() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0xA9); // LDA immediate
+        bus.write(0x0001, 0x01);
+        bus.write(0x0002, 0x89); // BIT immediate
+        bus.write(0x0003, 0xC0);
+        cpu.step(&mut bus, 1);
+        let ticks = cpu.step(&mut bus, 1);
+        assert_eq!(cpu.read_status() & 0b11000010, 0b00000010); // Z set, N and V untouched
+        assert_eq!(ticks, 2);
+    }
+
+    #[test]
+    fn jmp_absolute_x_indirect() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0xA2); // LDX immediate
+        bus.write(0x0001, 0x04);
+        bus.write(0x0002, 0x7C); // JMP (absolute, X)
+        bus.write(0x0003, 0x00);
+        bus.write(0x0004, 0x30);
+        bus.write(0x3004, 0x34);
+        bus.write(0x3005, 0x12);
+        cpu.step(&mut bus, 1);
+        let ticks = cpu.step(&mut bus, 1);
+        assert_eq!(cpu.pc, 0x1234);
+        assert_eq!(ticks, 6);
+    }
+
+    #[test]
+    fn jmp_indirect_has_no_page_bug() {
+        let (mut cpu, mut bus) = init();
+        cpu.config.emulate_indirect_jmp_bug = true;
+        bus.write(0x0000, 0x6C); // JMP indirect
+        bus.write(0x0001, 0xFF);
+        bus.write(0x0002, 0x30);
+        bus.write(0x30FF, 0x34);
+        bus.write(0x3100, 0x12);
+        bus.write(0x3000, 0x56);
+        let ticks = cpu.step(&mut bus, 1);
+        assert_eq!(cpu.pc, 0x1234);
+        assert_eq!(ticks, 6);
+    }
+
+    #[test]
+    fn rmb_and_smb() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0x37); // RMB3
+        bus.write(0x0001, 0x10);
+        bus.write(0x0002, 0xC7); // SMB4
+        bus.write(0x0003, 0x10);
+        bus.write(0x0010, 0xFF);
+        let ticks = cpu.step(&mut bus, 1);
+        assert_eq!(bus.read(0x0010), 0xF7);
+        assert_eq!(ticks, 5);
+        bus.write(0x0010, 0x00);
+        cpu.step(&mut bus, 1);
+        assert_eq!(bus.read(0x0010), 0x10);
+    }
+
+    #[test]
+    fn bbr_and_bbs() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0x8F); // BBS0, taken
+        bus.write(0x0001, 0x10);
+        bus.write(0x0002, 0x10);
+        bus.write(0x0010, 0x01);
+        let ticks = cpu.step(&mut bus, 1);
+        assert_eq!(cpu.pc, 0x0013);
+        assert_eq!(ticks, 6);
+
+        bus.write(0x0013, 0x0F); // BBR0, not taken
+        bus.write(0x0014, 0x10);
+        bus.write(0x0015, 0x10);
+        let ticks = cpu.step(&mut bus, 1);
+        assert_eq!(cpu.pc, 0x0016);
+        assert_eq!(ticks, 5);
+    }
+
+    #[test]
+    fn wai_sleeps_until_irq() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0xFFFE, 0x00);
+        bus.write(0xFFFF, 0x30);
+        bus.write(0x0000, 0x58); // CLI
+        bus.write(0x0001, 0xCB); // WAI
+        cpu.step(&mut bus, 2);
+        cpu.step(&mut bus, 10);
+        assert_eq!(cpu.pc, 0x0002);
+
+        cpu.set_irq(true);
+        cpu.step(&mut bus, 1);
+        assert_eq!(cpu.pc, 0x3000);
+    }
+
+    #[test]
+    fn wai_with_irq_masked_resumes() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0xCB); // WAI, I is set by reset
+        bus.write(0x0001, 0xEA); // NOP
+        cpu.step(&mut bus, 5);
+        assert_eq!(cpu.pc, 0x0001);
+
+        cpu.set_irq(true);
+        cpu.step(&mut bus, 1);
+        assert_eq!(cpu.pc, 0x0002);
+    }
+
+    #[test]
+    fn stp_halts_until_reset() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0xDB); // STP
+        cpu.step(&mut bus, 5);
+        assert!(cpu.is_jammed());
+        assert_eq!(cpu.pc, 0x0001);
+
+        cpu.reset(&mut bus);
+        assert!(!cpu.is_jammed());
+    }
+
+    #[test]
+    fn interrupts_clear_decimal() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0xFFFE, 0x00);
+        bus.write(0xFFFF, 0x30);
+        bus.write(0x0000, 0xF8); // SED
+        bus.write(0x0001, 0x00); // BRK
+        cpu.step(&mut bus, 2);
+        assert_eq!(cpu.pc, 0x3000);
+        assert_eq!(cpu.read_status() & 0b00001000, 0); // D cleared
+        assert_eq!(bus.read(0x01FB) & 0b00001000, 0b00001000); // pushed with D set
+    }
+
+    #[test]
+    fn nmos_interrupts_keep_decimal() {
+        let (mut cpu, mut bus) = init();
+        cpu.config.variant = CpuVariant::Nmos6502;
+        bus.write(0x0000, 0xF8); // SED
+        bus.write(0x0001, 0x00); // BRK
+        cpu.step(&mut bus, 2);
+        assert_eq!(cpu.read_status() & 0b00001000, 0b00001000);
+    }
+
+    #[test]
+    fn decimal_adc_takes_an_extra_cycle() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0x69); // ADC immediate
+        bus.write(0x0001, 0x01);
+        bus.write(0x0002, 0xF8); // SED
+        bus.write(0x0003, 0x69); // ADC immediate
+        bus.write(0x0004, 0x01);
+        let binary = cpu.step(&mut bus, 1);
+        cpu.step(&mut bus, 1);
+        let decimal = cpu.step(&mut bus, 1);
+        assert_eq!(decimal, binary + 1);
+    }
+
+    #[test]
+    fn decimal_adc_sets_flags_from_result() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0xF8); // SED
+        bus.write(0x0001, 0xA9); // LDA immediate
+        bus.write(0x0002, 0x99);
+        bus.write(0x0003, 0x69); // ADC immediate
+        bus.write(0x0004, 0x01);
+        cpu.step(&mut bus, 3);
+        assert_eq!(cpu.read_acc(), 0x00);
+        assert_eq!(cpu.read_status() & 0b10000011, 0b00000011); // Z and C set, N clear
+    }
+}
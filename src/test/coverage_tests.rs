@@ -0,0 +1,184 @@
+#[cfg(test)]
+mod coverage_tests {
+    use std::{env, fs};
+
+    use crate::bus::Bus;
+    use crate::cpu::assembler::Assembler;
+    use crate::cpu::config::CpuVariant;
+    use crate::cpu::cpu::CPU;
+    use crate::debug::coverage::{CODE, Coverage, DATA, OPCODE, WRITTEN};
+    use crate::debug::monitor::Monitor;
+    use crate::debug::symbols::SymbolTable;
+    use crate::devices::mem::Mem;
+
+    const PROGRAM: &str = "        *= $0400
+start:  LDA table
+        STA $10
+        INC $11
+        NOP
+done:   JMP done
+table:  .byte $42
+";
+
+    // Runs the five instructions of PROGRAM with coverage being recorded
+    fn init() -> (CPU, Bus, SymbolTable) {
+        let assembly = Assembler::new(CpuVariant::Nmos6502).assemble(PROGRAM).unwrap();
+        let mut cpu = CPU::default();
+        let mut bus = Bus::default();
+        bus.register(0..=0xFFFF, Box::new(Mem::default(1024 * 64)));
+        assembly.load(&mut bus);
+        bus.write(0xFFFC, 0x00);
+        bus.write(0xFFFD, 0x04);
+        cpu.reset(&mut bus);
+
+        bus.set_coverage(Some(Coverage::default()));
+        cpu.step(&mut bus, 5);
+        (cpu, bus, assembly.symbols)
+    }
+
+    fn temp_path(name: &str) -> String {
+        env::temp_dir().join(format!("coverage_test_{}_{}", std::process::id(), name)).to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn code_operands_and_data() {
+        let (_, bus, _) = init();
+        let coverage = bus.coverage().unwrap();
+        assert_eq!(coverage.flags(0x0400), CODE | OPCODE);
+        assert_eq!(coverage.flags(0x0401), CODE);
+        assert_eq!(coverage.flags(0x0402), CODE);
+        assert_eq!(coverage.flags(0x040B), DATA);
+        assert_eq!(coverage.flags(0x0010), WRITTEN);
+        assert_eq!(coverage.flags(0x0011), DATA | WRITTEN);
+        // NOP reads the next opcode and throws it away, which doesn't make it data
+        assert_eq!(coverage.flags(0x0408), CODE | OPCODE);
+        assert_eq!(coverage.flags(0x040C), 0);
+        // The reset vector was read before recording started
+        assert_eq!(coverage.flags(0xFFFC), 0);
+    }
+
+    #[test]
+    fn throwaway_stack_reads_arent_data() {
+        let assembly = Assembler::new(CpuVariant::Nmos6502).assemble("*= $0400\n JSR sub\n BRK\nsub: RTS\n").unwrap();
+        let mut cpu = CPU::default();
+        let mut bus = Bus::default();
+        bus.register(0..=0xFFFF, Box::new(Mem::default(1024 * 64)));
+        assembly.load(&mut bus);
+        cpu.reset(&mut bus);
+        cpu.pc = 0x0400;
+        cpu.write_sp(0xFF);
+
+        bus.set_coverage(Some(Coverage::default()));
+        cpu.step(&mut bus, 2);
+        let coverage = bus.take_coverage().unwrap();
+        // JSR reads $01FF before pushing to it, RTS reads $01FD before pulling from above it
+        assert_eq!(coverage.flags(0x01FF), WRITTEN | DATA);
+        assert_eq!(coverage.flags(0x01FE), WRITTEN | DATA);
+        assert_eq!(coverage.flags(0x01FD), 0);
+        assert!(bus.coverage().is_none());
+    }
+
+    #[test]
+    fn save_load_and_merge() {
+        let (_, bus, _) = init();
+        let coverage = bus.coverage().unwrap();
+        let path = temp_path("program.cdl");
+        coverage.save(&path, 0x0400..=0x040F).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        assert_eq!(bytes.len(), 16);
+        // Just FCEUX's code and data bits, the opcodes aren't saved
+        assert_eq!(bytes[0], CODE);
+        assert!(bytes.iter().all(|byte| byte & !(CODE | DATA) == 0));
+
+        let loaded = Coverage::load(&path, 0x0400).unwrap();
+        assert_eq!(loaded.flags(0x0400), CODE);
+        assert_eq!(loaded.flags(0x040B), DATA);
+        assert_eq!(loaded.flags(0x0011), 0);
+
+        // Another run that went somewhere else
+        let mut merged = Coverage::default();
+        merged.mark(0x040C, CODE | OPCODE);
+        merged.mark(0x040B, WRITTEN);
+        merged.merge(&loaded);
+        assert_eq!(merged.flags(0x040B), DATA | WRITTEN);
+        assert_eq!(merged.flags(0x040C), CODE | OPCODE);
+        assert_eq!(merged.flags(0x0400), CODE);
+        merged.clear();
+        assert_eq!(merged, Coverage::default());
+
+        assert_eq!(Coverage::load(&path, 0xFFF8).unwrap_err(), format!("{} doesn't fit after $FFF8", path));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn report() {
+        let (_, bus, symbols) = init();
+        let coverage = bus.coverage().unwrap();
+        assert_eq!(
+            coverage.report(0x0400..=0x040C, Some(&symbols)),
+            "$0400-$040C: 11 code (5 opcodes), 1 data, 0 written, 1 unused\n\
+             $0400-$0400  opcode  start\n\
+             $0401-$0402  operand\n\
+             $0403-$0403  opcode\n\
+             $0404-$0404  operand\n\
+             $0405-$0405  opcode\n\
+             $0406-$0406  operand\n\
+             $0407-$0408  opcode\n\
+             $0409-$040A  operand\n\
+             $040B-$040B  data  table\n\
+             $040C-$040C  unused"
+        );
+        assert_eq!(coverage.report(0x0010..=0x0011, None), "$0010-$0011: 0 code (0 opcodes), 1 data, 2 written, 0 unused\n$0010-$0010  written\n$0011-$0011  data+written");
+        assert_eq!(coverage.report(0xFFFF..=0xFFFF, None), "$FFFF-$FFFF: 0 code (0 opcodes), 0 data, 0 written, 1 unused\n$FFFF-$FFFF  unused");
+    }
+
+    #[test]
+    fn two_byte_instructions() {
+        let assembly = Assembler::new(CpuVariant::Nmos6502).assemble("*= $0400\n LDA #$01\n LDX $10\n").unwrap();
+        let mut cpu = CPU::default();
+        let mut bus = Bus::default();
+        bus.register(0..=0xFFFF, Box::new(Mem::default(1024 * 64)));
+        assembly.load(&mut bus);
+        cpu.reset(&mut bus);
+        cpu.pc = 0x0400;
+
+        bus.set_coverage(Some(Coverage::default()));
+        cpu.step(&mut bus, 2);
+        assert_eq!(
+            bus.coverage().unwrap().report(0x0400..=0x0403, None),
+            "$0400-$0403: 4 code (2 opcodes), 0 data, 0 written, 0 unused\n\
+             $0400-$0400  opcode\n\
+             $0401-$0401  operand\n\
+             $0402-$0402  opcode\n\
+             $0403-$0403  operand"
+        );
+    }
+
+    #[test]
+    fn monitor_commands() {
+        let (mut cpu, mut bus, _) = init();
+        bus.set_coverage(None);
+        cpu.pc = 0x0400;
+        let mut monitor = Monitor::default();
+        let path = temp_path("monitor.cdl");
+
+        let mut run = |line: &str| monitor.execute(&mut cpu, &mut bus, line).unwrap();
+        assert_eq!(run("cdl"), "Coverage isn't being recorded");
+        assert!(run("cdl report").starts_with("error: coverage isn't being recorded"));
+        assert_eq!(run("cdl on"), "Recording coverage");
+        run("z 2");
+        assert!(run("cdl").starts_with("$0000-$FFFF: 5 code (2 opcodes), 1 data, 1 written,"));
+        assert_eq!(run(&format!("cdl save {} 400 40f", path)), format!("Saved the coverage of $0400-$040F to {}", path));
+        assert_eq!(run("cdl off"), "Stopped recording coverage");
+
+        // Merging starts recording again, with what was saved
+        assert_eq!(run(&format!("cdl merge {} 400", path)), format!("Merged {} at $0400", path));
+        // The saved log doesn't say which bytes were opcodes
+        assert!(run("cdl report 400 40c").contains("\n$0400-$0404  operand\n$0405-$040A  unused\n$040B-$040B  data\n"));
+        assert!(run("cdl merge missing.cdl").starts_with("error: couldn't read missing.cdl"));
+        assert_eq!(run("cdl save backwards.cdl C000 8000"), "error: $C000-$8000 is empty");
+        assert_eq!(run("cdl report C000 8000"), "error: $C000-$8000 is empty");
+        assert!(run("cdl sideways").starts_with("error: expected on, off, save, merge or report"));
+        fs::remove_file(path).unwrap();
+    }
+}
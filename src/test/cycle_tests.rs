@@ -0,0 +1,248 @@
+#[cfg(test)]
+mod cycle_tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use crate::bus::{Bus, Device, Mapping, TickReturn};
+    use crate::cpu::config::CpuVariant;
+    use crate::cpu::cpu::CPU;
+
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    enum Access {
+        Read(u16),
+        Write(u16, u8),
+        Tick,
+    }
+
+    // 64K of memory that records every access and tick in the order they happen, and holds
+    // IRQ asserted once `irq_after` ticks have passed
+    struct Recorder {
+        data: Vec<u8>,
+        log: Rc<RefCell<Vec<Access>>>,
+        ticks: u32,
+        irq_after: Option<u32>,
+    }
+
+    impl Device for Recorder {
+        fn read(&mut self, addr: u16) -> u8 {
+            self.log.borrow_mut().push(Access::Read(addr));
+            self.data[addr as usize]
+        }
+
+        fn write(&mut self, addr: u16, value: u8) {
+            self.log.borrow_mut().push(Access::Write(addr, value));
+            self.data[addr as usize] = value;
+        }
+
+        fn tick(&mut self) -> TickReturn {
+            self.log.borrow_mut().push(Access::Tick);
+            self.ticks += 1;
+            match self.irq_after {
+                Some(after) if self.ticks >= after => TickReturn::IRQ,
+                _ => TickReturn::NONE,
+            }
+        }
+    }
+
+    // Loads `program` at 0x0200 and returns the CPU, the bus and the access log
+    fn init(program: &[u8], cycle_accurate: bool) -> (CPU, Bus, Rc<RefCell<Vec<Access>>>) {
+        let mut data = vec![0_u8; 0x10000];
+        data[0x0200..0x0200 + program.len()].copy_from_slice(program);
+        data[0xFFFC] = 0x00;
+        data[0xFFFD] = 0x02;
+        data[0xFFFE] = 0x00;
+        data[0xFFFF] = 0x03;
+
+        let log = Rc::new(RefCell::new(vec![]));
+        let mut cpu = CPU::default();
+        cpu.config.cycle_accurate = cycle_accurate;
+        let mut bus = Bus::default();
+        let recorder = Recorder { data, log: Rc::clone(&log), ticks: 0, irq_after: None };
+        bus.register(0..=0xFFFF, Box::new(recorder));
+
+        cpu.reset(&mut bus);
+        log.borrow_mut().clear();
+        (cpu, bus, log)
+    }
+
+    fn accesses(log: &Rc<RefCell<Vec<Access>>>) -> Vec<Access> {
+        log.borrow().iter().copied().filter(|a| *a != Access::Tick).collect()
+    }
+
+    #[test]
+    fn one_access_per_cycle() {
+        // LDA #$01, STA $10, INC $10, LDX #$FF, LDA $1001,X, JSR $0300
+        let program = [0xA9, 0x01, 0x85, 0x10, 0xE6, 0x10, 0xA2, 0xFF, 0xBD, 0x01, 0x10, 0x20, 0x00, 0x03];
+        let (mut cpu, mut bus, log) = init(&program, false);
+        let ticks = cpu.step(&mut bus, 6);
+        assert_eq!(ticks, 2 + 3 + 5 + 2 + 5 + 6);
+        assert_eq!(accesses(&log).len() as u32, ticks);
+    }
+
+    #[test]
+    fn lockstep_ticks_between_accesses() {
+        let (mut cpu, mut bus, log) = init(&[0xAD, 0x34, 0x12], true); // LDA $1234
+        cpu.step(&mut bus, 1);
+        assert_eq!(
+            *log.borrow(),
+            vec![
+                Access::Read(0x0200),
+                Access::Tick,
+                Access::Read(0x0201),
+                Access::Tick,
+                Access::Read(0x0202),
+                Access::Tick,
+                Access::Read(0x1234),
+                Access::Tick,
+            ]
+        );
+    }
+
+    #[test]
+    fn burst_mode_leaves_ticking_to_the_caller() {
+        let (mut cpu, mut bus, log) = init(&[0xAD, 0x34, 0x12], false); // LDA $1234
+        cpu.step(&mut bus, 1);
+        assert!(!log.borrow().contains(&Access::Tick));
+    }
+
+    #[test]
+    fn implied_reads_next_opcode() {
+        let (mut cpu, mut bus, log) = init(&[0xE8], false); // INX
+        cpu.step(&mut bus, 1);
+        assert_eq!(accesses(&log), vec![Access::Read(0x0200), Access::Read(0x0201)]);
+    }
+
+    #[test]
+    fn read_modify_write_writes_twice() {
+        let (mut cpu, mut bus, log) = init(&[0xEE, 0x34, 0x12], false); // INC $1234
+        cpu.step(&mut bus, 1);
+        assert_eq!(
+            accesses(&log),
+            vec![
+                Access::Read(0x0200),
+                Access::Read(0x0201),
+                Access::Read(0x0202),
+                Access::Read(0x1234),
+                Access::Write(0x1234, 0x00),
+                Access::Write(0x1234, 0x01),
+            ]
+        );
+    }
+
+    #[test]
+    fn cmos_read_modify_write_reads_twice() {
+        let (mut cpu, mut bus, log) = init(&[0xEE, 0x34, 0x12], false); // INC $1234
+        cpu.config.variant = CpuVariant::Wdc65C02;
+        cpu.step(&mut bus, 1);
+        assert_eq!(
+            accesses(&log)[3..],
+            [Access::Read(0x1234), Access::Read(0x1234), Access::Write(0x1234, 0x01)]
+        );
+    }
+
+    #[test]
+    fn indexed_read_page_cross_reads_the_wrong_page() {
+        // LDX #$10, LDA $12F8,X
+        let (mut cpu, mut bus, log) = init(&[0xA2, 0x10, 0xBD, 0xF8, 0x12], false);
+        cpu.step(&mut bus, 1);
+        log.borrow_mut().clear();
+        let ticks = cpu.step(&mut bus, 1);
+        assert_eq!(ticks, 5);
+        assert_eq!(accesses(&log)[3..], [Access::Read(0x1208), Access::Read(0x1308)]);
+    }
+
+    #[test]
+    fn indexed_read_without_page_cross_has_no_dummy_read() {
+        // LDX #$01, LDA $1200,X
+        let (mut cpu, mut bus, log) = init(&[0xA2, 0x01, 0xBD, 0x00, 0x12], false);
+        cpu.step(&mut bus, 1);
+        log.borrow_mut().clear();
+        let ticks = cpu.step(&mut bus, 1);
+        assert_eq!(ticks, 4);
+        assert_eq!(accesses(&log)[3..], [Access::Read(0x1201)]);
+    }
+
+    #[test]
+    fn indexed_write_always_dummy_reads() {
+        // LDX #$01, STA $1200,X
+        let (mut cpu, mut bus, log) = init(&[0xA2, 0x01, 0x9D, 0x00, 0x12], false);
+        cpu.step(&mut bus, 1);
+        log.borrow_mut().clear();
+        let ticks = cpu.step(&mut bus, 1);
+        assert_eq!(ticks, 5);
+        assert_eq!(accesses(&log)[3..], [Access::Read(0x1201), Access::Write(0x1201, 0x00)]);
+    }
+
+    #[test]
+    fn zero_page_indexed_reads_the_base() {
+        // LDX #$01, LDA $10,X
+        let (mut cpu, mut bus, log) = init(&[0xA2, 0x01, 0xB5, 0x10], false);
+        cpu.step(&mut bus, 1);
+        log.borrow_mut().clear();
+        let ticks = cpu.step(&mut bus, 1);
+        assert_eq!(ticks, 4);
+        assert_eq!(accesses(&log)[2..], [Access::Read(0x0010), Access::Read(0x0011)]);
+    }
+
+    #[test]
+    fn branch_cycles() {
+        // BNE +0 taken, BEQ not taken, BNE -128 crossing into the previous page
+        let (mut cpu, mut bus, _log) = init(&[0xD0, 0x00, 0xF0, 0x00, 0xD0, 0x80], false);
+        assert_eq!(cpu.step(&mut bus, 1), 3);
+        assert_eq!(cpu.step(&mut bus, 1), 2);
+        assert_eq!(cpu.step(&mut bus, 1), 4);
+        assert_eq!(cpu.pc, 0x0186);
+    }
+
+    #[test]
+    fn stack_instruction_cycles() {
+        // PHA, PLA, JSR $0300 with an RTS there
+        let (mut cpu, mut bus, _log) = init(&[0x48, 0x68, 0x20, 0x00, 0x03], false);
+        bus.write(0x0300, 0x60);
+        assert_eq!(cpu.step(&mut bus, 1), 3);
+        assert_eq!(cpu.step(&mut bus, 1), 4);
+        assert_eq!(cpu.step(&mut bus, 1), 6);
+        assert_eq!(cpu.step(&mut bus, 1), 6);
+        assert_eq!(cpu.pc, 0x0205);
+    }
+
+    #[test]
+    fn zero_page_y_takes_four_cycles() {
+        let (mut cpu, mut bus, _log) = init(&[0xB6, 0x10], false); // LDX $10,Y
+        assert_eq!(cpu.step(&mut bus, 1), 4);
+    }
+
+    #[test]
+    fn jmp_indirect_takes_five_cycles() {
+        let (mut cpu, mut bus, _log) = init(&[0x6C, 0x00, 0x10], false); // JMP ($1000)
+        assert_eq!(cpu.step(&mut bus, 1), 5);
+    }
+
+    #[test]
+    fn lockstep_irq_needs_no_caller_ticks() {
+        let (mut cpu, mut bus, _log) = init(&[0x58, 0xEA], true); // CLI, NOP
+        let source = Recorder { data: vec![0; 1], log: Rc::new(RefCell::new(vec![])), ticks: 0, irq_after: Some(2) };
+        // Only there to be ticked, over a byte nothing reads
+        bus.map(Mapping { priority: 1, ..Mapping::new(0xFFF0..=0xFFF0) }, Box::new(source)).unwrap();
+
+        cpu.step(&mut bus, 1);
+        assert!(cpu.irq_asserted());
+        cpu.step(&mut bus, 1);
+        assert_eq!(cpu.pc, 0x0300);
+    }
+
+    #[test]
+    fn lockstep_passes_on_shutdown() {
+        struct Off;
+        impl Device for Off {
+            fn read(&mut self, _addr: u16) -> u8 {0}
+            fn write(&mut self, _addr: u16, _value: u8) {}
+            fn tick(&mut self) -> TickReturn {TickReturn::SHUTDOWN}
+        }
+
+        let (mut cpu, mut bus, _log) = init(&[0xEA], true);
+        bus.map(Mapping { priority: 1, ..Mapping::new(0xFFF0..=0xFFF0) }, Box::new(Off)).unwrap();
+        assert!(!bus.shutdown_requested());
+        cpu.step(&mut bus, 1);
+        assert!(bus.shutdown_requested());
+    }
+}
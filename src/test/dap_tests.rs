@@ -0,0 +1,338 @@
+#[cfg(test)]
+mod dap_tests {
+    use std::{
+        collections::VecDeque,
+        env, fs,
+        io::{BufRead, BufReader, Read, Write},
+        net::{TcpListener, TcpStream},
+        thread,
+        time::Duration,
+    };
+
+    use crate::bus::Bus;
+    use crate::cpu::assembler::Assembler;
+    use crate::cpu::config::CpuVariant;
+    use crate::cpu::cpu::CPU;
+    use crate::debug::dap::DapServer;
+    use crate::debug::json::Json;
+    use crate::devices::mem::Mem;
+
+    const PROGRAM: &str = "        *= $0400
+start:  LDX #$03
+loop:   JSR sub
+        DEX
+        BNE loop
+done:   JMP done
+
+sub:    INC $10
+        RTS
+";
+
+    // Plays the part of an editor
+    struct Client {
+        stream: TcpStream,
+        reader: BufReader<TcpStream>,
+        seq: i64,
+        events: VecDeque<Json>,
+    }
+
+    impl Client {
+        fn connect(port: u16) -> Self {
+            let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+            stream.set_nodelay(true).unwrap();
+            let reader = BufReader::new(stream.try_clone().unwrap());
+            Self { stream, reader, seq: 1, events: VecDeque::new() }
+        }
+
+        fn read(&mut self) -> Json {
+            let mut length = 0;
+            loop {
+                let mut header = String::new();
+                self.reader.read_line(&mut header).unwrap();
+                match header.trim().strip_prefix("Content-Length: ") {
+                    Some(value) => length = value.parse().unwrap(),
+                    None if header.trim().is_empty() => break,
+                    None => panic!("unexpected header {}", header),
+                }
+            }
+            let mut body = vec![0; length];
+            self.reader.read_exact(&mut body).unwrap();
+            Json::parse(std::str::from_utf8(&body).unwrap()).unwrap()
+        }
+
+        // Sends a request and waits for its response, keeping any events that come first
+        fn request(&mut self, command: &str, arguments: &str) -> Json {
+            let text = format!(r#"{{"seq":{},"type":"request","command":"{}","arguments":{}}}"#, self.seq, command, arguments);
+            write!(self.stream, "Content-Length: {}\r\n\r\n{}", text.len(), text).unwrap();
+            self.seq += 1;
+            loop {
+                let message = self.read();
+                if message.get("type").as_str() == Some("response") {
+                    assert_eq!(message.get("request_seq").as_i64(), Some(self.seq - 1));
+                    return message;
+                }
+                self.events.push_back(message);
+            }
+        }
+
+        fn body(&mut self, command: &str, arguments: &str) -> Json {
+            let response = self.request(command, arguments);
+            assert_eq!(response.get("success"), &Json::Bool(true), "{}", response);
+            response.get("body").clone()
+        }
+
+        // The body of the next event, which has to be `name`
+        fn event(&mut self, name: &str) -> Json {
+            let event = self.events.pop_front().unwrap_or_else(|| self.read());
+            assert_eq!(event.get("event").as_str(), Some(name), "{}", event);
+            event.get("body").clone()
+        }
+
+        fn stopped(&mut self, reason: &str) -> Json {
+            let body = self.event("stopped");
+            assert_eq!(body.get("reason").as_str(), Some(reason), "{}", body);
+            body
+        }
+
+        fn frames(&mut self) -> Vec<(String, i64)> {
+            let body = self.body("stackTrace", r#"{"threadId":1}"#);
+            let frames = body.get("stackFrames").as_array();
+            frames.iter().map(|frame| (frame.get("name").as_str().unwrap().to_string(), frame.get("line").as_i64().unwrap())).collect()
+        }
+
+        fn register(&mut self, name: &str) -> String {
+            let body = self.body("variables", r#"{"variablesReference":1}"#);
+            let variable = body.get("variables").as_array().iter().find(|variable| variable.get("name").as_str() == Some(name)).cloned();
+            variable.unwrap().get("value").as_str().unwrap().to_string()
+        }
+    }
+
+    // A bare 64K machine served on this thread with `script` as the client on another
+    fn session(script: impl FnOnce(Client) + Send + 'static) -> (CPU, Bus) {
+        let mut cpu = CPU::default();
+        let mut bus = Bus::default();
+        bus.register(0..=0xFFFF, Box::new(Mem::default(1024 * 64)));
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let client = thread::spawn(move || script(Client::connect(port)));
+        let (stream, _) = listener.accept().unwrap();
+        stream.set_nodelay(true).unwrap();
+        DapServer::default().serve(&mut cpu, &mut bus, stream.try_clone().unwrap(), stream).unwrap();
+        client.join().unwrap();
+        (cpu, bus)
+    }
+
+    fn temp_file(name: &str, contents: &[u8]) -> String {
+        let path = env::temp_dir().join(format!("dap_test_{}_{}", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        path.to_str().unwrap().replace('\\', "/")
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let text = r#"{"a":[1,-2.5,true,null],"b":"line\n\"quoted\" é😀","c":{}}"#;
+        let json = Json::parse(text).unwrap();
+        assert_eq!(json.get("a").as_array()[1], Json::Number(-2.5));
+        assert_eq!(json.get("b").as_str(), Some("line\n\"quoted\" é😀"));
+        assert_eq!(json.get("missing"), &Json::Null);
+        assert_eq!(Json::parse(&json.to_string()).unwrap(), json);
+        assert!(Json::parse(r#"{"a":1,}"#).is_err());
+        assert!(Json::parse("[1] 2").is_err());
+    }
+
+    #[test]
+    fn source_breakpoints_and_stepping() {
+        let path = temp_file("source.asm", PROGRAM.as_bytes());
+        let source = path.clone();
+        let (_, mut bus) = session(move |mut client| {
+            let capabilities = client.body("initialize", r#"{"adapterID":"emulate6502"}"#);
+            assert_eq!(capabilities.get("supportsConfigurationDoneRequest"), &Json::Bool(true));
+            client.event("initialized");
+            client.request("launch", &format!(r#"{{"source":"{}","stopOnEntry":true}}"#, source));
+
+            let breakpoints = format!(r#"{{"source":{{"path":"{}"}},"breakpoints":[{{"line":7}},{{"line":50}}]}}"#, source);
+            let body = client.body("setBreakpoints", &breakpoints);
+            let breakpoints = body.get("breakpoints").as_array().to_vec();
+            // Moved down to the next instruction
+            assert_eq!(breakpoints[0].get("verified"), &Json::Bool(true));
+            assert_eq!(breakpoints[0].get("line").as_i64(), Some(8));
+            assert_eq!(breakpoints[0].get("instructionReference").as_str(), Some("0x040B"));
+            assert_eq!(breakpoints[1].get("verified"), &Json::Bool(false));
+
+            client.request("configurationDone", "{}");
+            client.stopped("entry");
+            assert_eq!(client.frames(), [("start".to_string(), 2)]);
+
+            client.request("continue", r#"{"threadId":1}"#);
+            let body = client.stopped("breakpoint");
+            assert_eq!(body.get("hitBreakpointIds"), &Json::Array(vec![breakpoints[0].get("id").clone()]));
+            assert_eq!(client.frames(), [("sub".to_string(), 8), ("loop".to_string(), 3)]);
+            assert_eq!(client.register("X"), "$03");
+
+            // Setting them again replaces the old ones
+            let breakpoints = format!(r#"{{"source":{{"path":"{}"}},"breakpoints":[{{"line":5,"condition":"x == 1"}}]}}"#, source);
+            client.body("setBreakpoints", &breakpoints);
+            client.request("continue", r#"{"threadId":1}"#);
+            client.stopped("breakpoint");
+            assert_eq!(client.frames(), [("loop".to_string(), 5)]);
+            assert_eq!(client.register("X"), "$01");
+
+            client.request("next", r#"{"threadId":1}"#);
+            client.stopped("step");
+            assert_eq!(client.frames(), [("loop".to_string(), 3)]);
+            client.request("stepIn", r#"{"threadId":1}"#);
+            client.stopped("step");
+            assert_eq!(client.frames(), [("sub".to_string(), 8), ("loop".to_string(), 3)]);
+            client.request("stepOut", r#"{"threadId":1}"#);
+            client.stopped("step");
+            assert_eq!(client.frames(), [("loop".to_string(), 4)]);
+            client.request("disconnect", "{}");
+        });
+        assert_eq!(bus.peek(0x10), 3);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn stepping_back() {
+        let path = temp_file("back.asm", PROGRAM.as_bytes());
+        let source = path.clone();
+        let (_, mut bus) = session(move |mut client| {
+            let capabilities = client.body("initialize", r#"{"adapterID":"emulate6502"}"#);
+            assert_eq!(capabilities.get("supportsStepBack"), &Json::Bool(true));
+            client.event("initialized");
+            client.request("launch", &format!(r#"{{"source":"{}","stopOnEntry":true}}"#, source));
+            client.request("configurationDone", "{}");
+            client.stopped("entry");
+
+            let response = client.request("stepBack", r#"{"threadId":1}"#);
+            assert_eq!(response.get("message").as_str(), Some("can't go back any further"));
+            client.request("stepIn", r#"{"threadId":1}"#);
+            client.stopped("step");
+            client.request("stepIn", r#"{"threadId":1}"#);
+            client.stopped("step");
+            client.request("stepBack", r#"{"threadId":1}"#);
+            client.stopped("step");
+            assert_eq!(client.frames(), [("loop".to_string(), 3)]);
+
+            // Conditions only apply going forwards
+            let breakpoints = format!(r#"{{"source":{{"path":"{}"}},"breakpoints":[{{"line":5,"condition":"x == 1"}}]}}"#, source);
+            client.body("setBreakpoints", &breakpoints);
+            client.request("continue", r#"{"threadId":1}"#);
+            client.stopped("breakpoint");
+            assert_eq!(client.register("X"), "$01");
+            client.request("reverseContinue", r#"{"threadId":1}"#);
+            client.stopped("breakpoint");
+            assert_eq!(client.register("X"), "$02");
+            client.request("disconnect", "{}");
+        });
+        // Memory went back too, it had only been incremented once when X was 2
+        assert_eq!(bus.peek(0x10), 1);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn memory_registers_and_evaluate() {
+        let assembly = Assembler::new(CpuVariant::Nmos6502).assemble(PROGRAM).unwrap();
+        let path = temp_file("program.bin", &assembly.to_binary());
+        let program = path.clone();
+        let (cpu, mut bus) = session(move |mut client| {
+            client.body("initialize", "{}");
+            client.event("initialized");
+            client.request("launch", &format!(r#"{{"program":"{}","address":"$0400","stopOnEntry":true}}"#, program));
+            client.request("configurationDone", "{}");
+            client.stopped("entry");
+            // No symbols or source for a binary
+            assert_eq!(client.frames(), [("$0400".to_string(), 0)]);
+
+            let body = client.body("writeMemory", r#"{"memoryReference":"0x0300","data":"AQID"}"#);
+            assert_eq!(body.get("bytesWritten").as_i64(), Some(3));
+            let body = client.body("readMemory", r#"{"memoryReference":"0x02FF","offset":1,"count":3}"#);
+            assert_eq!(body.get("address").as_str(), Some("0x0300"));
+            assert_eq!(body.get("data").as_str(), Some("AQID"));
+            let body = client.body("readMemory", r#"{"memoryReference":"0xFFFE","count":4}"#);
+            assert_eq!(body.get("unreadableBytes").as_i64(), Some(2));
+
+            let body = client.body("setVariable", r#"{"variablesReference":1,"name":"A","value":"$40 + 2"}"#);
+            assert_eq!(body.get("value").as_str(), Some("$42"));
+            client.body("setVariable", r#"{"variablesReference":2,"name":"C","value":"1"}"#);
+            assert_eq!(client.body("evaluate", r#"{"expression":"A"}"#).get("result").as_str(), Some("$42 (66)"));
+            assert_eq!(client.body("evaluate", r#"{"expression":"[$0301] + C"}"#).get("result").as_str(), Some("$03 (3)"));
+            assert_eq!(client.request("evaluate", r#"{"expression":"A +"}"#).get("success"), &Json::Bool(false));
+
+            let body = client.body("setBreakpoints", r#"{"source":{"path":"elsewhere.asm"},"breakpoints":[{"line":1}]}"#);
+            assert_eq!(body.get("breakpoints").as_array()[0].get("message").as_str(), Some("not part of the program"));
+            client.body("setInstructionBreakpoints", r#"{"breakpoints":[{"instructionReference":"0x0403","offset":2}]}"#);
+            client.request("continue", r#"{"threadId":1}"#);
+            client.stopped("breakpoint");
+            assert_eq!(client.register("PC"), "$0405");
+
+            assert_eq!(client.request("restartFrame", "{}").get("success"), &Json::Bool(false));
+            client.request("disconnect", "{}");
+        });
+        assert_eq!(cpu.read_status() & 1, 1);
+        assert_eq!(bus.peek(0x0302), 3);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn debug_info_breakpoints() {
+        let assembly = Assembler::new(CpuVariant::Nmos6502).assemble(PROGRAM).unwrap();
+        let program = temp_file("debug.bin", &assembly.to_binary());
+        let source = temp_file("debug.s", PROGRAM.as_bytes());
+        let file_name = source.rsplit('/').next().unwrap();
+        // What ld65 would write for the JSR on line 3 and the INC on line 8
+        let dbg = format!(
+            "version\tmajor=2,minor=0
+file\tid=0,name=\"{}\",size=0,mtime=0x00000000,mod=0
+line\tid=0,file=0,line=3,span=0
+line\tid=1,file=0,line=8,span=1
+seg\tid=0,name=\"CODE\",start=0x000400,size=0x000E,addrsize=absolute,type=ro
+span\tid=0,seg=0,start=2,size=3
+span\tid=1,seg=0,start=11,size=2
+sym\tid=0,name=\"loop\",addrsize=absolute,scope=0,def=0,val=0x402,seg=0,type=lab
+sym\tid=1,name=\"sub\",addrsize=absolute,scope=0,def=1,val=0x40B,seg=0,type=lab
+",
+            file_name
+        );
+        let symbols = temp_file("debug.dbg", dbg.as_bytes());
+        let paths = (program.clone(), source.clone(), symbols.clone());
+        session(move |mut client| {
+            let (program, source, symbols) = paths;
+            client.body("initialize", "{}");
+            client.event("initialized");
+            client.request("launch", &format!(r#"{{"program":"{}","address":"$0400","symbols":["{}"]}}"#, program, symbols));
+
+            let breakpoints = format!(r#"{{"source":{{"path":"{}"}},"breakpoints":[{{"line":8}}]}}"#, source);
+            let body = client.body("setBreakpoints", &breakpoints);
+            assert_eq!(body.get("breakpoints").as_array()[0].get("instructionReference").as_str(), Some("0x040B"));
+            client.request("configurationDone", "{}");
+            client.stopped("breakpoint");
+            assert_eq!(client.frames(), [("sub".to_string(), 8), ("loop".to_string(), 3)]);
+            client.request("disconnect", "{}");
+        });
+        for path in [program, source, symbols] {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn pause_and_terminate() {
+        let path = temp_file("pause.asm", PROGRAM.as_bytes());
+        let source = path.clone();
+        session(move |mut client| {
+            client.body("initialize", "{}");
+            client.event("initialized");
+            client.request("launch", &format!(r#"{{"source":"{}"}}"#, source));
+            client.request("configurationDone", "{}");
+            // Give it time to get to the loop at the end
+            thread::sleep(Duration::from_millis(200));
+            client.request("pause", r#"{"threadId":1}"#);
+            client.stopped("pause");
+            assert_eq!(client.frames(), [("done".to_string(), 6)]);
+            client.request("terminate", "{}");
+            client.event("terminated");
+        });
+        fs::remove_file(path).unwrap();
+    }
+}
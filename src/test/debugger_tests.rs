@@ -0,0 +1,206 @@
+#[cfg(test)]
+mod debugger_tests {
+    use crate::bus::Bus;
+    use crate::cpu::assembler::Assembler;
+    use crate::cpu::config::CpuVariant;
+    use crate::cpu::cpu::CPU;
+    use crate::debug::debugger::{Debugger, StopReason, WatchKind};
+    use crate::debug::expression::Expression;
+    use crate::debug::symbols::SymbolTable;
+    use crate::devices::mem::Mem;
+
+    const PROGRAM: &str = "
+            *= $0400
+    start:  LDX #$03
+    loop:   JSR sub
+            DEX
+            BNE loop
+            STA $0300
+    done:   JMP done
+
+    sub:    INC $10
+            JSR leaf
+            LDA $10
+            RTS
+
+    leaf:   NOP
+            RTS
+    ";
+
+    fn init() -> (CPU, Bus, SymbolTable) {
+        let assembly = Assembler::new(CpuVariant::Nmos6502).assemble(PROGRAM).unwrap();
+        let mut cpu = CPU::default();
+        let mut bus = Bus::default();
+        bus.register(0..=0xFFFF, Box::new(Mem::default(1024 * 64)));
+        assembly.load(&mut bus);
+        bus.write(0xFFFC, 0x00);
+        bus.write(0xFFFD, 0x04);
+        cpu.reset(&mut bus);
+        (cpu, bus, assembly.symbols)
+    }
+
+    fn at(symbols: &SymbolTable, name: &str) -> u16 {
+        symbols.address_of(name).unwrap()
+    }
+
+    #[test]
+    fn breakpoints_and_hit_counts() {
+        let (mut cpu, mut bus, symbols) = init();
+        let mut debugger = Debugger::default();
+        let id = debugger.add_breakpoint(at(&symbols, "sub"));
+        let done = debugger.add_breakpoint(at(&symbols, "done"));
+
+        for hit in 1..=3 {
+            let reason = debugger.run(&mut cpu, &mut bus, Some(1000));
+            assert_eq!(reason, StopReason::Breakpoint { id, pc: at(&symbols, "sub") });
+            assert_eq!(debugger.breakpoints()[0].hits, hit);
+        }
+        assert_eq!(debugger.run(&mut cpu, &mut bus, Some(1000)), StopReason::Breakpoint { id: done, pc: at(&symbols, "done") });
+        assert_eq!(bus.read(0x10), 3);
+    }
+
+    #[test]
+    fn temporary_and_disabled_breakpoints() {
+        let (mut cpu, mut bus, symbols) = init();
+        let mut debugger = Debugger::default();
+        let id = debugger.add_temporary_breakpoint(at(&symbols, "leaf"));
+        let disabled = debugger.add_breakpoint(at(&symbols, "sub"));
+        assert!(debugger.set_enabled(&mut bus, disabled, false));
+
+        assert_eq!(debugger.run(&mut cpu, &mut bus, Some(1000)), StopReason::Breakpoint { id, pc: at(&symbols, "leaf") });
+        assert_eq!(debugger.breakpoints().len(), 1);
+        assert_eq!(debugger.run(&mut cpu, &mut bus, Some(100)), StopReason::Limit);
+
+        assert!(debugger.remove(&mut bus, disabled));
+        assert!(!debugger.remove(&mut bus, disabled));
+    }
+
+    #[test]
+    fn write_and_read_watchpoints() {
+        let (mut cpu, mut bus, symbols) = init();
+        let mut debugger = Debugger::default();
+        let write = debugger.add_watchpoint(&mut bus, 0x0300..=0x03FF, WatchKind::Write);
+        let read = debugger.add_watchpoint(&mut bus, 0x0010..=0x0010, WatchKind::Read);
+
+        // INC reads $10 before writing it
+        let reason = debugger.run(&mut cpu, &mut bus, Some(1000));
+        assert_eq!(reason, StopReason::Watchpoint { id: read, kind: WatchKind::Read, addr: 0x0010, value: 0 });
+        assert_eq!(cpu.pc, at(&symbols, "sub") + 2);
+
+        debugger.remove(&mut bus, read);
+        let reason = debugger.run(&mut cpu, &mut bus, Some(1000));
+        assert_eq!(reason, StopReason::Watchpoint { id: write, kind: WatchKind::Write, addr: 0x0300, value: 3 });
+        assert_eq!(debugger.watchpoints()[0].hits, 1);
+    }
+
+    #[test]
+    fn peeks_and_pokes_do_not_hit_watchpoints() {
+        let (mut cpu, mut bus, _) = init();
+        let mut debugger = Debugger::default();
+        debugger.add_watchpoint(&mut bus, 0x0000..=0xFFFF, WatchKind::Access);
+        bus.peek(0x0400);
+        bus.poke(0x0300, 0x12);
+        assert_eq!(bus.take_watch_hit(), None);
+        assert_eq!(bus.peek(0x0300), 0x12);
+        // The last access the LDX made was reading its operand
+        assert!(matches!(debugger.step_into(&mut cpu, &mut bus), StopReason::Watchpoint { addr: 0x0401, .. }));
+    }
+
+    #[test]
+    fn execute_watchpoints() {
+        let (mut cpu, mut bus, symbols) = init();
+        let mut debugger = Debugger::default();
+        let leaf = at(&symbols, "leaf");
+        let id = debugger.add_watchpoint(&mut bus, leaf..=leaf + 1, WatchKind::Execute);
+
+        let reason = debugger.run(&mut cpu, &mut bus, Some(1000));
+        assert_eq!(reason, StopReason::Watchpoint { id, kind: WatchKind::Execute, addr: leaf, value: 0xEA });
+        let reason = debugger.run(&mut cpu, &mut bus, Some(1000));
+        assert_eq!(reason, StopReason::Watchpoint { id, kind: WatchKind::Execute, addr: leaf + 1, value: 0x60 });
+    }
+
+    #[test]
+    fn step_into_and_over() {
+        let (mut cpu, mut bus, symbols) = init();
+        let mut debugger = Debugger::default();
+        assert_eq!(debugger.step_into(&mut cpu, &mut bus), StopReason::Stepped);
+        assert_eq!(cpu.pc, at(&symbols, "loop"));
+
+        assert_eq!(debugger.step_over(&mut cpu, &mut bus, Some(1000)), StopReason::Stepped);
+        assert_eq!(cpu.pc, at(&symbols, "loop") + 3);
+        assert_eq!(bus.read(0x10), 1);
+
+        debugger.step_over(&mut cpu, &mut bus, Some(1000));
+        assert_eq!(cpu.read_x(), 2);
+    }
+
+    #[test]
+    fn step_over_stops_at_breakpoints_inside() {
+        let (mut cpu, mut bus, symbols) = init();
+        let mut debugger = Debugger::default();
+        debugger.step_into(&mut cpu, &mut bus);
+        let id = debugger.add_breakpoint(at(&symbols, "leaf"));
+        assert_eq!(debugger.step_over(&mut cpu, &mut bus, Some(1000)), StopReason::Breakpoint { id, pc: at(&symbols, "leaf") });
+    }
+
+    #[test]
+    fn step_out() {
+        let (mut cpu, mut bus, symbols) = init();
+        let mut debugger = Debugger::default();
+        debugger.add_temporary_breakpoint(at(&symbols, "sub"));
+        debugger.run(&mut cpu, &mut bus, Some(1000));
+
+        // Goes over the nested call to leaf and stops after the RTS out of sub
+        assert_eq!(debugger.step_out(&mut cpu, &mut bus, Some(1000)), StopReason::Stepped);
+        assert_eq!(cpu.pc, at(&symbols, "loop") + 3);
+        assert_eq!(cpu.read_sp(), 0xFD);
+    }
+
+    #[test]
+    fn conditional_breakpoints() {
+        let (mut cpu, mut bus, symbols) = init();
+        let mut debugger = Debugger::default();
+        let id = debugger.add_breakpoint(at(&symbols, "loop"));
+        assert!(debugger.set_condition(id, Some(Expression::parse("X == 1 && [$10] == 2").unwrap())));
+
+        assert_eq!(debugger.run(&mut cpu, &mut bus, Some(1000)), StopReason::Breakpoint { id, pc: at(&symbols, "loop") });
+        assert_eq!(cpu.read_x(), 1);
+        // Counted every time it was reached, not just when it stopped
+        assert_eq!(debugger.breakpoints()[0].hits, 3);
+    }
+
+    #[test]
+    fn hit_count_conditions() {
+        let (mut cpu, mut bus, symbols) = init();
+        let mut debugger = Debugger::default();
+        let id = debugger.add_temporary_breakpoint(at(&symbols, "leaf"));
+        debugger.set_condition(id, Some(Expression::parse("hits == 3").unwrap()));
+
+        assert_eq!(debugger.run(&mut cpu, &mut bus, Some(1000)), StopReason::Breakpoint { id, pc: at(&symbols, "leaf") });
+        assert_eq!(bus.read(0x10), 3);
+        assert!(debugger.breakpoints().is_empty());
+    }
+
+    #[test]
+    fn conditional_watchpoints() {
+        let (mut cpu, mut bus, _) = init();
+        let mut debugger = Debugger::default();
+        let id = debugger.add_watchpoint(&mut bus, 0x0010..=0x0010, WatchKind::Write);
+        debugger.set_condition(id, Some(Expression::parse("[$10] >= 2").unwrap()));
+
+        let reason = debugger.run(&mut cpu, &mut bus, Some(1000));
+        assert_eq!(reason, StopReason::Watchpoint { id, kind: WatchKind::Write, addr: 0x0010, value: 2 });
+        assert_eq!(debugger.watchpoints()[0].hits, 2);
+        assert!(!debugger.set_condition(99, None));
+    }
+
+    #[test]
+    fn stops_when_jammed() {
+        let mut cpu = CPU::default();
+        let mut bus = Bus::default();
+        bus.register(0..=0xFFFF, Box::new(Mem::default(1024 * 64)));
+        bus.write(0x0000, 0x02);
+        cpu.reset(&mut bus);
+        assert_eq!(Debugger::default().run(&mut cpu, &mut bus, None), StopReason::Jammed);
+    }
+}
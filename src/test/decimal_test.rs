@@ -0,0 +1,46 @@
+#[cfg(test)]
+mod decimal_test {
+    use crate::bus::Bus;
+    use crate::cpu::config::CpuVariant;
+    use crate::cpu::cpu::CPU;
+    use crate::devices::mem::Mem;
+
+    // Bruce Clark's exhaustive decimal mode test, see roms/6502_decimal_test.asm and
+    // roms/65C02_decimal_test.asm. Both builds share the same layout
+    const START: u16 = 0x0200;
+    const DONE_TRAP: u16 = 0x0203;
+    const ERROR: u16 = 0x0004;
+
+    #[test]
+    fn decimal_test() {
+        let mut cpu = CPU::default();
+        let mut bus = Bus::default();
+        let mem = Box::new(Mem::default(1024 * 64));
+        bus.register(0..=0xFFFF, mem);
+
+        assert!(cpu.load_rom(&mut bus, "roms/6502_decimal_test.bin", START));
+
+        cpu.reset(&mut bus);
+        cpu.pc = START;
+        cpu.config.speed = 30.0;
+        assert_eq!(cpu.run(&mut bus, DONE_TRAP, Some(50_000_000)), 0);
+        assert_eq!(bus.read(ERROR), 0);
+    }
+
+    #[test]
+    fn cmos_decimal_test() {
+        let mut cpu = CPU::default();
+        cpu.config.variant = CpuVariant::Wdc65C02;
+        let mut bus = Bus::default();
+        let mem = Box::new(Mem::default(1024 * 64));
+        bus.register(0..=0xFFFF, mem);
+
+        assert!(cpu.load_rom(&mut bus, "roms/65C02_decimal_test.bin", START));
+
+        cpu.reset(&mut bus);
+        cpu.pc = START;
+        cpu.config.speed = 30.0;
+        assert_eq!(cpu.run(&mut bus, DONE_TRAP, Some(50_000_000)), 0);
+        assert_eq!(bus.read(ERROR), 0);
+    }
+}
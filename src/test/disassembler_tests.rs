@@ -0,0 +1,128 @@
+#[cfg(test)]
+mod disassembler_tests {
+    use crate::bus::{Bus, Device, TickReturn};
+    use crate::cpu::config::CpuVariant;
+    use crate::cpu::disassembler::Disassembler;
+    use crate::debug::symbols::SymbolTable;
+    use crate::devices::mem::Mem;
+
+    fn init(program: &[u8], at: u16) -> Bus {
+        let mut bus = Bus::default();
+        bus.register(0..=0xFFFF, Box::new(Mem::default(1024 * 64)));
+        for (i, byte) in program.iter().enumerate() {
+            bus.write(at + i as u16, *byte);
+        }
+        bus
+    }
+
+    fn lines(variant: CpuVariant, program: &[u8], count: usize) -> Vec<String> {
+        let mut bus = init(program, 0xC000);
+        Disassembler::new(variant)
+            .disassemble(&mut bus, 0xC000, count)
+            .iter()
+            .map(|line| line.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn documented_instructions() {
+        let program = [0x4C, 0xF5, 0xC5, 0xA9, 0x10, 0xB1, 0x80, 0x0A, 0x60];
+        assert_eq!(
+            lines(CpuVariant::Nmos6502, &program, 5),
+            [
+                "C000  4C F5 C5  JMP $C5F5",
+                "C003  A9 10     LDA #$10",
+                "C005  B1 80     LDA ($80),Y",
+                "C007  0A        ASL A",
+                "C008  60        RTS",
+            ]
+        );
+    }
+
+    #[test]
+    fn branch_targets_are_resolved() {
+        let mut bus = init(&[0xD0, 0xFE, 0xF0, 0x80], 0xC000);
+        let disassembler = Disassembler::new(CpuVariant::Nmos6502);
+        let back = disassembler.decode(&mut bus, 0xC000);
+        assert_eq!(back.text(None), "BNE $C000");
+        assert_eq!(back.target(), Some(0xC000));
+        assert_eq!(disassembler.decode(&mut bus, 0xC002).target(), Some(0xBF84));
+    }
+
+    #[test]
+    fn undocumented_opcodes_follow_config() {
+        let program = [0xA7, 0x10, 0x02];
+        assert_eq!(
+            lines(CpuVariant::Nmos6502, &program, 2),
+            ["C000  A7 10     LAX $10", "C002  02        JAM"]
+        );
+
+        let mut bus = init(&program, 0xC000);
+        let mut disassembler = Disassembler::new(CpuVariant::Nmos6502);
+        disassembler.undocumented_opcodes = false;
+        let line = disassembler.decode(&mut bus, 0xC000);
+        assert_eq!(line.len, 1);
+        assert_eq!(line.to_string(), "C000  A7        .byte $A7");
+    }
+
+    #[test]
+    fn cmos_opcodes() {
+        let program = [0x80, 0x02, 0x72, 0x40, 0x7C, 0x00, 0x20, 0x0F, 0x12, 0xFD];
+        assert_eq!(
+            lines(CpuVariant::Wdc65C02, &program, 4),
+            [
+                "C000  80 02     BRA $C004",
+                "C002  72 40     ADC ($40)",
+                "C004  7C 00 20  JMP ($2000,X)",
+                "C007  0F 12 FD  BBR0 $12,$C007",
+            ]
+        );
+    }
+
+    #[test]
+    fn labels_replace_addresses() {
+        let mut symbols = SymbolTable::default();
+        symbols.insert("main_loop", 0xC000);
+        symbols.insert("ptr", 0x0080);
+        symbols.insert("oswrch", 0xFFEE);
+
+        let mut bus = init(&[0x20, 0xEE, 0xFF, 0xB1, 0x80, 0xA9, 0x80, 0xD0, 0xF7], 0xC000);
+        let text: Vec<String> = Disassembler::new(CpuVariant::Nmos6502)
+            .disassemble(&mut bus, 0xC000, 4)
+            .iter()
+            .map(|line| line.text(Some(&symbols)))
+            .collect();
+        assert_eq!(text, ["JSR oswrch", "LDA (ptr),Y", "LDA #$80", "BNE main_loop"]);
+    }
+
+    // Counts reads so we can tell the disassembler didn't make any
+    struct ReadCounter {
+        reads: u32,
+    }
+
+    impl Device for ReadCounter {
+        fn read(&mut self, _addr: u16) -> u8 {
+            self.reads += 1;
+            self.reads as u8
+        }
+
+        fn peek(&mut self, _addr: u16) -> u8 {
+            0xEA
+        }
+
+        fn write(&mut self, _addr: u16, _value: u8) {}
+
+        fn tick(&mut self) -> TickReturn {
+            TickReturn::NONE
+        }
+    }
+
+    #[test]
+    fn decoding_has_no_side_effects() {
+        let mut bus = Bus::default();
+        bus.register(0..=0xFFFF, Box::new(ReadCounter { reads: 0 }));
+        let lines = Disassembler::new(CpuVariant::Nmos6502).disassemble(&mut bus, 0x1000, 3);
+        assert!(lines.iter().all(|line| line.text(None) == "NOP"));
+        assert_eq!(bus.read(0x1000), 1);
+    }
+}
@@ -0,0 +1,118 @@
+#[cfg(test)]
+mod expression_tests {
+    use crate::bus::{Bus, Device, TickReturn};
+    use crate::cpu::assembler::Assembler;
+    use crate::cpu::config::CpuVariant;
+    use crate::cpu::cpu::CPU;
+    use crate::debug::expression::Expression;
+    use crate::devices::mem::Mem;
+
+    // A = $80, X = $05, Y = $00 with N set and Z clear, and a few bytes in memory
+    fn init() -> (CPU, Bus) {
+        let source = "
+            LDY #$00
+            LDX #$05
+            LDA #$80
+        ";
+        let mut cpu = CPU::default();
+        let mut bus = Bus::default();
+        bus.register(0..=0xFFFF, Box::new(Mem::default(1024 * 64)));
+        Assembler::new(CpuVariant::Nmos6502).assemble(source).unwrap().load(&mut bus);
+        bus.write(0x0070, 0x42);
+        bus.write(0xFFFC, 0x34);
+        bus.write(0xFFFD, 0x12);
+        cpu.reset(&mut bus);
+        cpu.pc = 0;
+        cpu.step(&mut bus, 3);
+        (cpu, bus)
+    }
+
+    fn eval(text: &str) -> i64 {
+        let (cpu, mut bus) = init();
+        Expression::parse(text).unwrap().eval(&cpu, &mut bus, 7)
+    }
+
+    #[test]
+    fn registers_and_flags() {
+        assert_eq!(eval("A"), 0x80);
+        assert_eq!(eval("x + y"), 5);
+        assert_eq!(eval("PC"), 6);
+        assert_eq!(eval("SP"), 0xFD);
+        assert_eq!(eval("P"), 0xA4);
+        assert_eq!(eval("N"), 1);
+        assert_eq!(eval("Z"), 0);
+        assert_eq!(eval("I && !D"), 1);
+    }
+
+    #[test]
+    fn memory() {
+        assert_eq!(eval("[$70]"), 0x42);
+        assert_eq!(eval("[$6F + X - 4]"), 0x42);
+        assert_eq!(eval("w[$FFFC]"), 0x1234);
+        assert_eq!(eval("W[$FFFC] >> 8"), 0x12);
+    }
+
+    #[test]
+    fn cycles_and_hits() {
+        assert_eq!(eval("cycles"), 6);
+        assert_eq!(eval("hits == 7"), 1);
+        assert_eq!(eval("hits % 3"), 1);
+    }
+
+    #[test]
+    fn operators() {
+        assert_eq!(eval("1 + 2 * 3"), 7);
+        assert_eq!(eval("(1 + 2) * 3"), 9);
+        assert_eq!(eval("A & $0F == 0"), 0);
+        assert_eq!(eval("(A & $0F) == 0"), 1);
+        assert_eq!(eval("X >= 5 && X < 6"), 1);
+        assert_eq!(eval("X <= 4 || Y != 0"), 0);
+        assert_eq!(eval("1 << 4 | %0011"), 0x13);
+        assert_eq!(eval("-1 < 0"), 1);
+        assert_eq!(eval("~0"), -1);
+        assert_eq!(eval("5 / 0"), 0);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(Expression::parse("Q == 1").unwrap_err(), "unknown name Q");
+        assert_eq!(Expression::parse("(A == 1").unwrap_err(), "missing )");
+        assert_eq!(Expression::parse("[$70").unwrap_err(), "missing ]");
+        assert_eq!(Expression::parse("A == ").unwrap_err(), "expected a value");
+        assert_eq!(Expression::parse("A 1").unwrap_err(), "unexpected '1'");
+        assert_eq!(Expression::parse(" A==0 ").unwrap().to_string(), "A==0");
+    }
+
+    // A register whose reads count themselves, like a status register a read acknowledges
+    struct ReadCounter {
+        reads: u8,
+    }
+
+    impl Device for ReadCounter {
+        fn read(&mut self, _addr: u16) -> u8 {
+            self.reads += 1;
+            self.reads
+        }
+
+        fn peek(&mut self, _addr: u16) -> u8 {
+            self.reads
+        }
+
+        fn write(&mut self, _addr: u16, _value: u8) {}
+
+        fn tick(&mut self) -> TickReturn {
+            TickReturn::NONE
+        }
+    }
+
+    #[test]
+    fn memory_is_peeked() {
+        let cpu = CPU::default();
+        let mut bus = Bus::default();
+        bus.register(0xFE40..=0xFE40, Box::new(ReadCounter { reads: 0 }));
+        let condition = Expression::parse("[$FE40] == 0").unwrap();
+        assert!(condition.is_true(&cpu, &mut bus, 0));
+        assert!(condition.is_true(&cpu, &mut bus, 0));
+        assert_eq!(bus.read(0xFE40), 1);
+    }
+}
@@ -0,0 +1,204 @@
+#[cfg(test)]
+mod gdb_tests {
+    use std::{
+        io::{Read, Write},
+        net::{TcpListener, TcpStream},
+        thread,
+    };
+
+    use crate::bus::Bus;
+    use crate::cpu::assembler::Assembler;
+    use crate::cpu::config::CpuVariant;
+    use crate::cpu::cpu::CPU;
+    use crate::debug::gdb::GdbStub;
+    use crate::devices::mem::Mem;
+
+    const PROGRAM: &str = "
+            *= $0400
+    start:  LDX #$03
+    loop:   JSR sub
+            DEX
+            BNE loop
+    done:   JMP done
+
+    sub:    INC $10
+            RTS
+    ";
+
+    // Plays the part of gdb, one command at a time
+    struct Client {
+        stream: TcpStream,
+        acks: bool,
+    }
+
+    impl Client {
+        fn connect(port: u16) -> Self {
+            Self { stream: TcpStream::connect(("127.0.0.1", port)).unwrap(), acks: true }
+        }
+
+        fn byte(&mut self) -> u8 {
+            let mut byte = [0];
+            self.stream.read_exact(&mut byte).unwrap();
+            byte[0]
+        }
+
+        fn send_raw(&mut self, data: &[u8]) {
+            self.stream.write_all(data).unwrap();
+        }
+
+        // Sends a packet without waiting for the reply
+        fn send(&mut self, command: &str) {
+            let sum = command.bytes().fold(0u8, |sum, byte| sum.wrapping_add(byte));
+            self.send_raw(format!("${}#{:02x}", command, sum).as_bytes());
+            if self.acks {
+                assert_eq!(self.byte(), b'+');
+            }
+        }
+
+        fn command(&mut self, command: &str) -> String {
+            self.send(command);
+            self.reply()
+        }
+
+        fn reply(&mut self) -> String {
+            assert_eq!(self.byte(), b'$');
+            let mut data = vec![];
+            loop {
+                match self.byte() {
+                    b'#' => break,
+                    byte => data.push(byte),
+                }
+            }
+            let sum = [self.byte(), self.byte()];
+            let sum = u8::from_str_radix(std::str::from_utf8(&sum).unwrap(), 16).unwrap();
+            assert_eq!(sum, data.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)));
+            if self.acks {
+                self.send_raw(b"+");
+            }
+            String::from_utf8(data).unwrap()
+        }
+    }
+
+    // Serves the stub on this thread, it can't be sent anywhere else, with `script` driving
+    // it as the client from another. Gives back the CPU and bus afterwards
+    fn session(script: impl FnOnce(Client, u16) + Send + 'static) -> (CPU, Bus) {
+        let assembly = Assembler::new(CpuVariant::Nmos6502).assemble(PROGRAM).unwrap();
+        let sub = assembly.symbols.address_of("sub").unwrap();
+        let mut cpu = CPU::default();
+        let mut bus = Bus::default();
+        bus.register(0..=0xFFFF, Box::new(Mem::default(1024 * 64)));
+        assembly.load(&mut bus);
+        bus.write(0xFFFC, 0x00);
+        bus.write(0xFFFD, 0x04);
+        cpu.reset(&mut bus);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let client = thread::spawn(move || script(Client::connect(port), sub));
+        let (stream, _) = listener.accept().unwrap();
+        GdbStub::default().serve(&mut cpu, &mut bus, stream).unwrap();
+        client.join().unwrap();
+        (cpu, bus)
+    }
+
+    #[test]
+    fn target_description() {
+        session(|mut client, _| {
+            assert!(client.command("qSupported:swbreak+;hwbreak+").contains("qXfer:features:read+"));
+            // Read in small pieces like gdb does with a big one
+            let mut xml = String::new();
+            loop {
+                let reply = client.command(&format!("qXfer:features:read:target.xml:{:x},40", xml.len()));
+                xml.push_str(&reply[1..]);
+                if reply.starts_with('l') {
+                    break;
+                }
+                assert!(reply.starts_with('m'));
+            }
+            assert!(xml.starts_with("<?xml"));
+            assert!(xml.contains(r#"<reg name="pc" bitsize="16" type="code_ptr"/>"#));
+            assert!(xml.trim_end().ends_with("</target>"));
+            assert_eq!(client.command("qXfer:features:read:target.xml:ffff,40"), "l");
+            assert_eq!(client.command("vMustReplyEmpty"), "");
+            client.send("k");
+        });
+    }
+
+    #[test]
+    fn registers_and_memory() {
+        let (cpu, mut bus) = session(|mut client, _| {
+            assert_eq!(client.command("?"), "S05");
+            let registers = client.command("g");
+            assert_eq!(registers.len(), 14);
+            assert!(registers.ends_with("0004"));
+            assert_eq!(client.command("p5"), "0004");
+
+            assert_eq!(client.command("P0=42"), "OK");
+            assert_eq!(client.command("P5=0c04"), "OK");
+            assert_eq!(client.command("p0"), "42");
+            assert_eq!(client.command("G01020304ff0004"), "OK");
+            assert_eq!(client.command("g"), "01020304ff0004");
+            assert_eq!(client.command("P9=00"), "E01");
+
+            assert_eq!(client.command("m0400,5"), "a203200b04");
+            assert_eq!(client.command("M0300,3:aabbcc"), "OK");
+            assert_eq!(client.command("m0300,3"), "aabbcc");
+            assert_eq!(client.command("M0300,3:aa"), "E01");
+
+            // A corrupt packet is asked for again
+            client.send_raw(b"$g#00");
+            assert_eq!(client.byte(), b'-');
+
+            assert_eq!(client.command("QStartNoAckMode"), "OK");
+            client.acks = false;
+            assert_eq!(client.command("m0301,1"), "bb");
+            assert_eq!(client.command("D"), "OK");
+        });
+        assert_eq!(cpu.registers().a, 0x01);
+        assert_eq!(cpu.read_sp(), 0xFF);
+        assert_eq!(cpu.pc, 0x0400);
+        assert_eq!(bus.peek(0x0302), 0xCC);
+    }
+
+    #[test]
+    fn breakpoints_watchpoints_and_stepping() {
+        let (cpu, mut bus) = session(|mut client, sub| {
+            assert_eq!(client.command(&format!("Z0,{:x},1", sub)), "OK");
+            assert_eq!(client.command("c"), "T05swbreak:;");
+            assert_eq!(client.command("p5"), format!("{:02x}{:02x}", sub as u8, sub >> 8));
+
+            // Stops after the instruction that wrote
+            assert_eq!(client.command(&format!("z0,{:x},1", sub)), "OK");
+            assert_eq!(client.command("Z2,10,1"), "OK");
+            assert_eq!(client.command("c"), "T05watch:0010;");
+            assert_eq!(client.command("m10,1"), "01");
+            assert_eq!(client.command("z2,10,1"), "OK");
+
+            assert_eq!(client.command("Z3,10,1"), "OK");
+            assert_eq!(client.command("c"), "T05rwatch:0010;");
+            assert_eq!(client.command("z3,10,1"), "OK");
+
+            assert_eq!(client.command("Z1,405,1"), "OK");
+            assert_eq!(client.command("c"), "T05hwbreak:;");
+            assert_eq!(client.command("?"), "T05hwbreak:;");
+            assert_eq!(client.command("s"), "S05");
+            assert_eq!(client.command("p5"), "0604");
+            assert_eq!(client.command("z1,405,1"), "OK");
+            client.send("k");
+        });
+        assert_eq!(cpu.pc, 0x0406);
+        assert_eq!(bus.peek(0x10), 2);
+    }
+
+    #[test]
+    fn interrupt() {
+        session(|mut client, _| {
+            client.send("c");
+            // Only gets a reply once the stub sees the Ctrl-C
+            client.send_raw(&[0x03]);
+            assert_eq!(client.reply(), "S02");
+            assert_eq!(client.command("p5"), "0804");
+            client.send("k");
+        });
+    }
+}
@@ -0,0 +1,151 @@
+#[cfg(test)]
+mod golden_tests {
+    use crate::bus::Bus;
+    use crate::cpu::cpu::CPU;
+    use crate::cpu::golden::LogLine;
+    use crate::cpu::trace::{FileTracer, RingBuffer, TraceEvent};
+    use crate::devices::mem::Mem;
+
+    fn init(start: u16, program: &[u8]) -> (CPU, Bus) {
+        let mut cpu = CPU::default();
+        let mut bus = Bus::default();
+        bus.register(0..=0xFFFF, Box::new(Mem::default(1024 * 64)));
+        for (i, byte) in program.iter().enumerate() {
+            bus.write(start + i as u16, *byte);
+        }
+        bus.write(0xFFFC, (start & 0xFF) as u8);
+        bus.write(0xFFFD, (start >> 8) as u8);
+        cpu.reset(&mut bus);
+        (cpu, bus)
+    }
+
+    fn trace_line(cpu: &mut CPU, bus: &mut Bus) -> String {
+        let buffer = RingBuffer::new(1);
+        cpu.config.tracer = Some(Box::new(buffer.clone()));
+        cpu.step(bus, 1);
+        cpu.config.tracer = None;
+        buffer.events()[0].to_string()
+    }
+
+    // LDX #$03, then DEX, STX $10,Y and PHA in a loop until X is zero, which touches every
+    // compared field
+    const PROGRAM: [u8; 10] = [0xA2, 0x03, 0xCA, 0x96, 0x10, 0x48, 0xD0, 0xFA, 0xEA, 0xEA];
+
+    fn write_golden(name: &str, lines: usize) -> String {
+        let path = std::env::temp_dir().join(name).to_str().unwrap().to_string();
+        let (mut cpu, mut bus) = init(0x0200, &PROGRAM);
+        cpu.config.tracer = Some(Box::new(FileTracer::create(&path).unwrap()));
+        cpu.step(&mut bus, lines as u32);
+        cpu.config.tracer = None;
+        path
+    }
+
+    #[test]
+    fn nestest_layout() {
+        let (mut cpu, mut bus) = init(0xC000, &[0x4C, 0xF5, 0xC5]); // JMP $C5F5
+        assert_eq!(
+            trace_line(&mut cpu, &mut bus),
+            "C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD CYC:0"
+        );
+    }
+
+    #[test]
+    fn undocumented_opcodes_are_marked() {
+        let (mut cpu, mut bus) = init(0xDCDB, &[0x04, 0xA9]); // NOP $A9
+        assert_eq!(
+            trace_line(&mut cpu, &mut bus),
+            "DCDB  04 A9    *NOP $A9                         A:00 X:00 Y:00 P:24 SP:FD CYC:0"
+        );
+    }
+
+    #[test]
+    fn branch_shows_target() {
+        let (mut cpu, mut bus) = init(0xC72A, &[0xD0, 0x04]); // BNE +4
+        assert!(trace_line(&mut cpu, &mut bus).starts_with("C72A  D0 04     BNE $C730 "));
+    }
+
+    #[test]
+    fn parse_nestest_line() {
+        let line = "C72E  A2 00     LDX #$00                        A:00 X:00 Y:00 P:26 SP:FB PPU:  0, 30 CYC:10";
+        let parsed = LogLine::parse(line).unwrap();
+        assert_eq!(parsed.pc, 0xC72E);
+        assert_eq!(parsed.status, 0x26);
+        assert_eq!(parsed.sp, 0xFB);
+        assert_eq!(parsed.cycles, Some(10));
+        assert!(LogLine::parse("not a trace line").is_none());
+    }
+
+    #[test]
+    fn matching_log_has_no_divergence() {
+        let path = write_golden("emulate6502_golden_match.log", 12);
+        let (mut cpu, mut bus) = init(0x0200, &PROGRAM);
+        assert_eq!(cpu.compare_with_log(&mut bus, &path).unwrap(), None);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn cycle_count_is_relative_to_the_first_line() {
+        let path = write_golden("emulate6502_golden_offset.log", 6);
+        // Shift every cycle count by 7 like a log that counts the reset sequence
+        let log: Vec<String> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                let (head, cycles) = line.split_once("CYC:").unwrap();
+                format!("{}CYC:{}", head, cycles.parse::<u64>().unwrap() + 7)
+            })
+            .collect();
+        std::fs::write(&path, log.join("\n")).unwrap();
+
+        let (mut cpu, mut bus) = init(0x0200, &PROGRAM);
+        assert_eq!(cpu.compare_with_log(&mut bus, &path).unwrap(), None);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn reports_first_divergence() {
+        let path = write_golden("emulate6502_golden_diverge.log", 8);
+        let mut log: Vec<String> = std::fs::read_to_string(&path).unwrap().lines().map(str::to_string).collect();
+        log[4] = log[4].replace(" X:02", " X:07");
+        std::fs::write(&path, log.join("\n")).unwrap();
+
+        let (mut cpu, mut bus) = init(0x0200, &PROGRAM);
+        let divergence = cpu.compare_with_log(&mut bus, &path).unwrap().unwrap();
+        assert_eq!(divergence.line, 5);
+        assert_eq!(divergence.fields, vec!["X"]);
+        assert_eq!(divergence.expected, log[4]);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn stopping_before_the_log_ends_diverges() {
+        let path = write_golden("emulate6502_golden_stopped.log", 8);
+        let log: Vec<String> = std::fs::read_to_string(&path).unwrap().lines().map(str::to_string).collect();
+
+        // A JAM where the PHA was, which the log carries on past
+        let mut program = PROGRAM;
+        program[5] = 0x02;
+        let (mut cpu, mut bus) = init(0x0200, &program);
+        let divergence = cpu.compare_with_log(&mut bus, &path).unwrap().unwrap();
+        assert_eq!(divergence.line, 5);
+        assert_eq!(divergence.expected, log[4]);
+        assert_eq!(divergence.actual, "CPU stopped (jammed)");
+        assert!(divergence.fields.is_empty());
+        assert!(divergence.to_string().starts_with("Diverged at line 5\n"));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn comparison_restores_the_tracer() {
+        let path = write_golden("emulate6502_golden_restore.log", 2);
+        let (mut cpu, mut bus) = init(0x0200, &PROGRAM);
+        let buffer = RingBuffer::new(4);
+        cpu.config.tracer = Some(Box::new(buffer.clone()));
+        cpu.compare_with_log(&mut bus, &path).unwrap();
+        assert!(buffer.events().is_empty());
+
+        cpu.step(&mut bus, 1);
+        assert!(matches!(buffer.events()[..], [TraceEvent::Instruction(_)]));
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,178 @@
+#[cfg(test)]
+mod interrupt_tests {
+    use crate::bus::{Bus, Device, TickReturn};
+    use crate::cpu::cpu::CPU;
+    use crate::devices::mem::Mem;
+
+    // Holds a line asserted for as long as `level` says so
+    struct Line {
+        nmi: bool,
+        level: std::rc::Rc<std::cell::Cell<bool>>,
+    }
+
+    impl Device for Line {
+        fn read(&mut self, _addr: u16) -> u8 {0}
+
+        fn write(&mut self, _addr: u16, _value: u8) {}
+
+        fn tick(&mut self) -> TickReturn {
+            match (self.level.get(), self.nmi) {
+                (false, _) => TickReturn::NONE,
+                (true, false) => TickReturn::IRQ,
+                (true, true) => TickReturn::NMI,
+            }
+        }
+    }
+
+    fn init() -> (CPU, Bus) {
+        let mut cpu = CPU::default();
+        let mut bus = Bus::default();
+        let mem = Box::new(Mem::default(1024 * 64));
+        bus.register(0..=0xFFFF, mem);
+
+        bus.write(0xFFFC, 0x00);
+        bus.write(0xFFFD, 0x00);
+        // IRQ handler at 0x0300, NMI handler at 0x0400
+        bus.write(0xFFFE, 0x00);
+        bus.write(0xFFFF, 0x03);
+        bus.write(0xFFFA, 0x00);
+        bus.write(0xFFFB, 0x04);
+        cpu.reset(&mut bus);
+        (cpu, bus)
+    }
+
+    #[test]
+    fn irq_masked_after_reset() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0xEA); // NOP
+        cpu.set_irq(true);
+        cpu.step(&mut bus, 1);
+        assert_eq!(cpu.pc, 0x0001); // I is set by reset so the IRQ is ignored
+    }
+
+    #[test]
+    fn irq_vectors_through_fffe() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0x58); // CLI
+        bus.write(0x0001, 0xEA); // NOP
+        cpu.step(&mut bus, 1);
+        cpu.set_irq(true);
+        let ticks = cpu.step(&mut bus, 1);
+        assert_eq!(ticks, 7);
+        assert_eq!(cpu.pc, 0x0300);
+        assert_eq!(cpu.read_status() & 0b00000100, 0b00000100); // I set
+    }
+
+    #[test]
+    fn irq_pushes_pc_and_status_without_b() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0x58); // CLI
+        bus.write(0x0001, 0xEA); // NOP
+        cpu.step(&mut bus, 1);
+        cpu.set_irq(true);
+        cpu.step(&mut bus, 1);
+        assert_eq!(bus.read(0x01FD), 0x00); // hi byte of 0x0001
+        assert_eq!(bus.read(0x01FC), 0x01); // lo byte of 0x0001, no byte skipped
+        let pushed_status = bus.read(0x01FB);
+        assert_eq!(pushed_status & 0b00110000, 0b00100000); // B clear, bit 5 set
+        assert_eq!(cpu.read_sp(), 0xFA);
+    }
+
+    #[test]
+    fn irq_is_level_triggered() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0x58); // CLI
+        bus.write(0x0300, 0x40); // RTI straight away
+        cpu.step(&mut bus, 1);
+        cpu.set_irq(true);
+        cpu.step(&mut bus, 2); // IRQ, RTI
+        assert_eq!(cpu.pc, 0x0001);
+        cpu.step(&mut bus, 1); // Line is still held so the IRQ is taken again
+        assert_eq!(cpu.pc, 0x0300);
+    }
+
+    #[test]
+    fn irq_released_is_not_taken() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0x58); // CLI
+        bus.write(0x0001, 0xEA); // NOP
+        cpu.set_irq(true);
+        cpu.set_irq(false);
+        cpu.step(&mut bus, 2);
+        assert_eq!(cpu.pc, 0x0002);
+    }
+
+    #[test]
+    fn nmi_vectors_through_fffa() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0xEA); // NOP
+        cpu.set_nmi(true);
+        let ticks = cpu.step(&mut bus, 1);
+        assert_eq!(ticks, 7);
+        assert_eq!(cpu.pc, 0x0400); // Not masked by I
+        assert_eq!(bus.read(0x01FB) & 0b00110000, 0b00100000);
+    }
+
+    #[test]
+    fn nmi_is_edge_triggered() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0400, 0x40); // RTI
+        bus.write(0x0000, 0xEA); // NOP
+        bus.write(0x0001, 0xEA); // NOP
+        cpu.set_nmi(true);
+        cpu.step(&mut bus, 2); // NMI, RTI
+        assert_eq!(cpu.pc, 0x0000);
+        cpu.set_nmi(true); // Still held, no new edge
+        cpu.step(&mut bus, 1);
+        assert_eq!(cpu.pc, 0x0001);
+        cpu.set_nmi(false);
+        cpu.set_nmi(true);
+        cpu.step(&mut bus, 1);
+        assert_eq!(cpu.pc, 0x0400);
+    }
+
+    #[test]
+    fn nmi_has_priority_over_irq() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0x58); // CLI
+        cpu.step(&mut bus, 1);
+        cpu.set_irq(true);
+        cpu.set_nmi(true);
+        cpu.step(&mut bus, 1);
+        assert_eq!(cpu.pc, 0x0400);
+    }
+
+    #[test]
+    fn brk_leaves_b_out_of_the_status_register() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0x00); // BRK
+        cpu.step(&mut bus, 1);
+        assert_eq!(cpu.pc, 0x0300);
+        assert_eq!(cpu.read_status() & 0b00010000, 0);
+    }
+
+    #[test]
+    fn bus_reports_device_lines() {
+        let mut bus = Bus::default();
+        let irq = std::rc::Rc::new(std::cell::Cell::new(false));
+        let nmi = std::rc::Rc::new(std::cell::Cell::new(false));
+        bus.register(0..=0, Box::new(Line { nmi: false, level: irq.clone() }));
+        bus.register(1..=1, Box::new(Line { nmi: true, level: nmi.clone() }));
+
+        bus.tick();
+        assert!(!bus.irq() && !bus.nmi());
+
+        irq.set(true);
+        assert!(matches!(bus.tick(), TickReturn::IRQ));
+        assert!(bus.irq() && !bus.nmi());
+
+        nmi.set(true);
+        assert!(matches!(bus.tick(), TickReturn::NMI));
+        assert!(bus.irq() && bus.nmi());
+
+        irq.set(false);
+        nmi.set(false);
+        bus.tick();
+        assert!(!bus.irq() && !bus.nmi());
+    }
+}
@@ -1,6 +1,7 @@
 #[cfg(test)]
 mod klaus_test {
     use crate::bus::Bus;
+    use crate::cpu::config::CpuVariant;
     use crate::cpu::cpu::CPU;
     use crate::devices::mem::Mem;
 
@@ -18,4 +19,27 @@ mod klaus_test {
         cpu.config.speed = 30.0; // run it faster because it take long
         assert_eq!(cpu.run(&mut bus, 0x37CE, Some(100000)), 0);
     }
-}
\ No newline at end of file
+
+    // Klaus Dormann's 65C02_extended_opcodes_test, built with the default options
+    // (rkwl_wdc_op = 1). The binary is not checked in, assemble it with as65 and copy it to
+    // roms/65C02_extended_opcodes_test.bin, then check SUCCESS against the `success` label
+    // in the listing before running with `cargo test -- --ignored`
+    const CMOS_SUCCESS: u16 = 0x24F1;
+
+    #[test]
+    #[ignore]
+    fn cmos_extended_opcodes_test() {
+        let mut cpu = CPU::default();
+        cpu.config.variant = CpuVariant::Wdc65C02;
+        let mut bus = Bus::default();
+        let mem = Box::new(Mem::default(1024 * 64));
+        bus.register(0..=0xFFFF, mem);
+
+        assert!(cpu.load_rom(&mut bus, "roms/65C02_extended_opcodes_test.bin", 0x0000));
+
+        cpu.reset(&mut bus);
+        cpu.pc = 0x0400;
+        cpu.config.speed = 30.0;
+        assert_eq!(cpu.run(&mut bus, CMOS_SUCCESS, Some(100_000_000)), 0);
+    }
+}
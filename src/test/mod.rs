@@ -1,2 +1,26 @@
+// Each file holds a module of the same name, so its tests are grouped under it
+#![allow(clippy::module_inception)]
+
 pub mod instruction_tests;
-pub mod klaus_test;
\ No newline at end of file
+pub mod klaus_test;
+pub mod decimal_test;
+pub mod interrupt_tests;
+pub mod undocumented_tests;
+pub mod cmos_tests;
+pub mod cycle_tests;
+pub mod opcode_table_tests;
+pub mod trace_tests;
+pub mod golden_tests;
+pub mod disassembler_tests;
+pub mod assembler_tests;
+pub mod debugger_tests;
+pub mod expression_tests;
+pub mod monitor_tests;
+pub mod gdb_tests;
+pub mod dap_tests;
+pub mod symbols_tests;
+pub mod profiler_tests;
+pub mod coverage_tests;
+pub mod rewind_tests;
+pub mod snapshot_tests;
+pub mod bus_tests;
@@ -0,0 +1,165 @@
+#[cfg(test)]
+mod monitor_tests {
+    use std::{env, fs, io::Cursor};
+
+    use crate::bus::Bus;
+    use crate::cpu::cpu::CPU;
+    use crate::debug::coverage::Coverage;
+    use crate::debug::monitor::Monitor;
+    use crate::devices::mem::Mem;
+
+    fn init() -> (Monitor, CPU, Bus) {
+        let mut cpu = CPU::default();
+        let mut bus = Bus::default();
+        bus.register(0..=0xFFFF, Box::new(Mem::default(1024 * 64)));
+        bus.write(0xFFFC, 0x00);
+        bus.write(0xFFFD, 0x04);
+        cpu.reset(&mut bus);
+        (Monitor::default(), cpu, bus)
+    }
+
+    fn run(monitor: &mut Monitor, cpu: &mut CPU, bus: &mut Bus, line: &str) -> String {
+        monitor.execute(cpu, bus, line).unwrap()
+    }
+
+    #[test]
+    fn memory_dump_and_edit() {
+        let (mut monitor, mut cpu, mut bus) = init();
+        assert_eq!(run(&mut monitor, &mut cpu, &mut bus, "> 2000 48 69 00 \"!\""), "");
+        assert_eq!(
+            run(&mut monitor, &mut cpu, &mut bus, "m $2000 $2013"),
+            "2000  48 69 00 21 00 00 00 00 00 00 00 00 00 00 00 00  Hi.!............\n\
+             2010  00 00 00 00                                      ...."
+        );
+        // Carries on from where the last dump stopped
+        assert!(run(&mut monitor, &mut cpu, &mut bus, "m").starts_with("2014  00"));
+        assert!(run(&mut monitor, &mut cpu, &mut bus, "").starts_with("2094  00"));
+    }
+
+    #[test]
+    fn assemble_and_disassemble() {
+        let (mut monitor, mut cpu, mut bus) = init();
+        assert_eq!(run(&mut monitor, &mut cpu, &mut bus, "a 0400 ldx #$03"), "0400  A2 03     LDX #$03");
+        run(&mut monitor, &mut cpu, &mut bus, "a 402 dex");
+        run(&mut monitor, &mut cpu, &mut bus, "a 403 bne $0402");
+        assert_eq!(
+            run(&mut monitor, &mut cpu, &mut bus, "d 400 403"),
+            "0400  A2 03     LDX #$03\n0402  CA        DEX\n0403  D0 FD     BNE $0402"
+        );
+        assert_eq!(run(&mut monitor, &mut cpu, &mut bus, "d").lines().count(), 16);
+        assert_eq!(run(&mut monitor, &mut cpu, &mut bus, "a 400 ldx #$100"), "error: 256 doesn't fit in a byte");
+    }
+
+    #[test]
+    fn registers() {
+        let (mut monitor, mut cpu, mut bus) = init();
+        assert_eq!(
+            run(&mut monitor, &mut cpu, &mut bus, "r a=42 x=$10 y=1 sp=f0 p=e3 pc=c000"),
+            "PC:C000 A:42 X:10 Y:01 SP:F0 P:E3 NV-BDIZC:11100011 CYC:0"
+        );
+        assert_eq!((cpu.read_acc(), cpu.read_x(), cpu.read_y()), (0x42, 0x10, 0x01));
+        assert_eq!((cpu.read_sp(), cpu.read_status(), cpu.pc), (0xF0, 0xE3, 0xC000));
+        assert_eq!(run(&mut monitor, &mut cpu, &mut bus, "r q=1"), "error: unknown register q");
+    }
+
+    #[test]
+    fn fill_compare_and_hunt() {
+        let (mut monitor, mut cpu, mut bus) = init();
+        run(&mut monitor, &mut cpu, &mut bus, "f 1000 100f aa 55");
+        run(&mut monitor, &mut cpu, &mut bus, "f 2000 200f aa 55");
+        assert_eq!([bus.read(0x1000), bus.read(0x1001), bus.read(0x100F)], [0xAA, 0x55, 0x55]);
+        assert_eq!(run(&mut monitor, &mut cpu, &mut bus, "c 1000 100f 2000"), "No differences");
+
+        run(&mut monitor, &mut cpu, &mut bus, "> 2004 00");
+        assert_eq!(run(&mut monitor, &mut cpu, &mut bus, "c 1000 100f 2000"), "$1004: AA  $2004: 00");
+
+        run(&mut monitor, &mut cpu, &mut bus, "> 3000 \"BBC\" 42 43");
+        assert_eq!(run(&mut monitor, &mut cpu, &mut bus, "h 0 ffff \"BC\""), "$3001 $3003");
+        assert_eq!(run(&mut monitor, &mut cpu, &mut bus, "h 0 3003 42 43"), "$3001");
+        assert_eq!(run(&mut monitor, &mut cpu, &mut bus, "h 0 ffff de ad"), "Not found");
+    }
+
+    #[test]
+    fn save_and_load() {
+        let (mut monitor, mut cpu, mut bus) = init();
+        let path = env::temp_dir().join(format!("monitor_test_{}.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        run(&mut monitor, &mut cpu, &mut bus, "> 1000 01 02 03");
+        assert_eq!(run(&mut monitor, &mut cpu, &mut bus, &format!("s \"{}\" 1000 1002", path)), format!("Saved 3 bytes to {}", path));
+        assert_eq!(fs::read(path).unwrap(), [1, 2, 3]);
+
+        assert_eq!(run(&mut monitor, &mut cpu, &mut bus, &format!("l {} 5000", path)), "Loaded 3 bytes at $5000");
+        assert_eq!(bus.read(0x5002), 3);
+        assert!(run(&mut monitor, &mut cpu, &mut bus, &format!("l {} FFFF", path)).starts_with("error"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn breakpoints_and_stepping() {
+        let (mut monitor, mut cpu, mut bus) = init();
+        for line in ["a 400 ldx #3", "a 402 jsr $0500", "a 405 dex", "a 406 bne $0402", "a 408 jmp $0408", "a 500 inc $10", "a 502 rts"] {
+            run(&mut monitor, &mut cpu, &mut bus, line);
+        }
+
+        assert_eq!(run(&mut monitor, &mut cpu, &mut bus, "b 405 if x == 2"), "Breakpoint 1 at $0405");
+        assert_eq!(run(&mut monitor, &mut cpu, &mut bus, "w w 10"), "Watchpoint 2 at $0010-$0010");
+        assert_eq!(run(&mut monitor, &mut cpu, &mut bus, "b"), "1: b $0405 hits 0 if x == 2\n2: w w $0010-$0010 hits 0");
+
+        assert!(run(&mut monitor, &mut cpu, &mut bus, "g").starts_with("Watchpoint 2: write $01 at $0010\n0502"));
+        run(&mut monitor, &mut cpu, &mut bus, "disable 2");
+        assert!(run(&mut monitor, &mut cpu, &mut bus, "g").starts_with("Breakpoint 1 at $0405\n0405"));
+        assert_eq!(cpu.read_x(), 2);
+
+        run(&mut monitor, &mut cpu, &mut bus, "del 1");
+        assert_eq!(run(&mut monitor, &mut cpu, &mut bus, "del 1"), "error: there's no breakpoint 1");
+        assert!(run(&mut monitor, &mut cpu, &mut bus, "z 2").starts_with("0402"));
+        assert!(run(&mut monitor, &mut cpu, &mut bus, "z").starts_with("0500"));
+        assert!(run(&mut monitor, &mut cpu, &mut bus, "ret").starts_with("0405"));
+        assert!(run(&mut monitor, &mut cpu, &mut bus, "un 408").starts_with("Breakpoint 3 at $0408"));
+        assert_eq!(bus.read(0x10), 3);
+    }
+
+    #[test]
+    fn repl() {
+        let (mut monitor, mut cpu, mut bus) = init();
+        let mut input = Cursor::new("r a=7\nnonsense\nx\nr a=8\n");
+        let mut output = vec![];
+        monitor.repl(&mut cpu, &mut bus, &mut input, &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("(0400) PC:0400 A:07"));
+        assert!(output.contains("error: unknown command nonsense"));
+        assert_eq!(cpu.read_acc(), 7);
+    }
+
+    #[test]
+    fn unmapped_accesses() {
+        let (mut monitor, mut cpu, _) = init();
+        let mut bus = Bus::default();
+        bus.register(0..=0x7FFF, Box::new(Mem::default(1024 * 32)));
+        let mut run = |line: &str| monitor.execute(&mut cpu, &mut bus, line).unwrap();
+        assert_eq!(run("um"), "Nothing unmapped has been accessed");
+        // Peeks and pokes aren't counted
+        run("> 8000 01 02");
+        run("m 8000 8000");
+        assert_eq!(run("um"), "Nothing unmapped has been accessed");
+
+        run("> 0400 ad 00 80 8d 01 80");
+        run("z 2");
+        assert_eq!(run("um"), "$8000-$80FF  1 reads, 1 writes");
+        assert_eq!(run("um clear"), "");
+        assert_eq!(run("um"), "Nothing unmapped has been accessed");
+        assert_eq!(run("um all"), "error: expected nothing or clear");
+    }
+
+    #[test]
+    fn assembling_doesnt_disturb_the_bus() {
+        let (mut monitor, mut cpu, mut bus) = init();
+        bus.set_coverage(Some(Coverage::default()));
+        run(&mut monitor, &mut cpu, &mut bus, "w w 0500");
+        assert!(run(&mut monitor, &mut cpu, &mut bus, "a 0500 LDA #$01").starts_with("0500  A9 01"));
+        assert_eq!(bus.take_watch_hit(), None);
+        assert_eq!(bus.coverage().unwrap().flags(0x0500), 0);
+    }
+}
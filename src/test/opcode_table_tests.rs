@@ -0,0 +1,99 @@
+#[cfg(test)]
+mod opcode_table_tests {
+    use crate::bus::Bus;
+    use crate::cpu::addresing::AddrMode;
+    use crate::cpu::config::CpuVariant;
+    use crate::cpu::cpu::CPU;
+    use crate::cpu::opcodes::opcode_table;
+    use crate::devices::mem::Mem;
+
+    const START: u16 = 0x0204;
+
+    // Runs `ins` with both index registers set to `index` after LDX and LDY, and returns the
+    // cycles it took and where the PC ended up. Operands are $80 $12, which points abs,X
+    // and abs,Y at $1280, (zp),Y at $12F0 and a relative branch 128 bytes back
+    fn run(variant: CpuVariant, ins: u8, index: u8) -> (u32, u16) {
+        let mut cpu = CPU::default();
+        cpu.config.variant = variant;
+        let mut bus = Bus::default();
+        let mem = Box::new(Mem::default(1024 * 64));
+        bus.register(0..=0xFFFF, mem);
+
+        let program = [0xA2, index, 0xA0, index, ins, 0x80, 0x12];
+        for (i, byte) in program.iter().enumerate() {
+            bus.write(0x0200 + i as u16, *byte);
+        }
+        bus.write(0x0080, 0xF0);
+        bus.write(0x0081, 0x12);
+        bus.write(0xFFFC, 0x00);
+        bus.write(0xFFFD, 0x02);
+        cpu.reset(&mut bus);
+
+        cpu.step(&mut bus, 2);
+        let cycles = cpu.step(&mut bus, 1);
+        (cycles, cpu.pc)
+    }
+
+    fn check_table(variant: CpuVariant) {
+        for (ins, opcode) in opcode_table(variant).iter().enumerate() {
+            let ins = ins as u8;
+            let next = START + opcode.bytes as u16;
+            let indexed = matches!(opcode.mode, AddrMode::AbsoluteX | AddrMode::AbsoluteY | AddrMode::IndirectY);
+
+            for (index, crosses) in [(0x00, false), (0xFF, indexed)] {
+                let (cycles, pc) = run(variant, ins, index);
+                let mut expected = opcode.cycles as u32;
+
+                match opcode.mode {
+                    AddrMode::Relative | AddrMode::ZeroPageRelative => {
+                        if pc != next {
+                            let crossed = (pc & 0xFF00) != (next & 0xFF00);
+                            expected += 1 + (crossed && opcode.page_penalty) as u32;
+                        }
+                    }
+                    _ => {
+                        expected += (crosses && opcode.page_penalty) as u32;
+                        let jumps = matches!(opcode.mnemonic, "JMP" | "JSR" | "RTS" | "RTI" | "BRK" | "JAM");
+                        if !jumps {
+                            assert_eq!(pc, next, "{:?} {:02X} {} length", variant, ins, opcode.mnemonic);
+                        }
+                    }
+                }
+
+                assert_eq!(cycles, expected, "{:?} {:02X} {} with index {:02X}", variant, ins, opcode.mnemonic, index);
+            }
+        }
+    }
+
+    #[test]
+    fn nmos_cycles_match_table() {
+        check_table(CpuVariant::Nmos6502);
+    }
+
+    #[test]
+    fn cmos_cycles_match_table() {
+        check_table(CpuVariant::Wdc65C02);
+    }
+
+    #[test]
+    fn documented_opcode_count() {
+        let documented = opcode_table(CpuVariant::Nmos6502).iter().filter(|op| !op.undocumented).count();
+        assert_eq!(documented, 151);
+        assert!(opcode_table(CpuVariant::Wdc65C02).iter().all(|op| !op.undocumented));
+    }
+
+    #[test]
+    fn undocumented_opcodes_can_be_turned_off() {
+        let mut cpu = CPU::default();
+        cpu.config.undocumented_opcodes = false;
+        let mut bus = Bus::default();
+        bus.register(0..=0xFFFF, Box::new(Mem::default(1024 * 64)));
+        bus.write(0x0000, 0xA7); // LAX $10
+        bus.write(0x0001, 0x10);
+        bus.write(0x0010, 0x42);
+        cpu.reset(&mut bus);
+
+        assert_eq!(cpu.step(&mut bus, 1), 1);
+        assert_eq!(cpu.read_acc(), 0);
+    }
+}
@@ -0,0 +1,145 @@
+#[cfg(test)]
+mod profiler_tests {
+    use crate::bus::Bus;
+    use crate::cpu::assembler::Assembler;
+    use crate::cpu::config::CpuVariant;
+    use crate::cpu::cpu::CPU;
+    use crate::debug::profiler::{Profiler, SubroutineProfile};
+    use crate::debug::symbols::SymbolTable;
+    use crate::devices::mem::Mem;
+
+    const NESTED: &str = "        *= $0400
+start:  LDX #$03
+loop:   JSR outer
+        DEX
+        BNE loop
+done:   JMP done
+outer:  JSR inner
+        RTS
+inner:  NOP
+        RTS
+";
+
+    // Runs `steps` instructions of `source` with a profiler as the tracer
+    fn profile(source: &str, steps: u32) -> (CPU, Bus, Profiler, SymbolTable) {
+        let assembly = Assembler::new(CpuVariant::Nmos6502).assemble(source).unwrap();
+        let mut cpu = CPU::default();
+        let mut bus = Bus::default();
+        bus.register(0..=0xFFFF, Box::new(Mem::default(1024 * 64)));
+        assembly.load(&mut bus);
+        bus.write(0xFFFC, 0x00);
+        bus.write(0xFFFD, 0x04);
+        cpu.reset(&mut bus);
+
+        let profiler = Profiler::default();
+        cpu.config.tracer = Some(Box::new(profiler.clone()));
+        cpu.step(&mut bus, steps);
+        (cpu, bus, profiler, assembly.symbols)
+    }
+
+    fn subroutine(profiler: &Profiler, addr: u16) -> SubroutineProfile {
+        profiler.subroutines().into_iter().find(|subroutine| subroutine.addr == addr).unwrap()
+    }
+
+    #[test]
+    fn counts_per_address() {
+        let (cpu, _, profiler, _) = profile(NESTED, 22);
+        assert_eq!(cpu.pc, 0x0408);
+        assert_eq!(profiler.total_cycles(), 94);
+        assert_eq!(profiler.executions(0x0400), 1);
+        assert_eq!(profiler.executions(0x0406), 3);
+        assert_eq!(profiler.cycles(0x0406), 8);
+        assert_eq!(profiler.executions(0x0408), 0);
+
+        let spots = profiler.hot_spots();
+        assert_eq!(spots[0], (0x0402, 3, 18));
+        assert_eq!(spots.len(), 8);
+    }
+
+    #[test]
+    fn subroutines_get_their_callees_cycles() {
+        let (_, _, profiler, symbols) = profile(NESTED, 22);
+        let outer = symbols.address_of("outer").unwrap();
+        let inner = symbols.address_of("inner").unwrap();
+        assert_eq!(subroutine(&profiler, 0x0400), SubroutineProfile { addr: 0x0400, calls: 1, cycles: 34, total: 94 });
+        assert_eq!(subroutine(&profiler, outer), SubroutineProfile { addr: outer, calls: 3, cycles: 36, total: 60 });
+        assert_eq!(subroutine(&profiler, inner), SubroutineProfile { addr: inner, calls: 3, cycles: 24, total: 24 });
+        assert_eq!(profiler.subroutines()[0].addr, outer);
+
+        assert_eq!(profiler.collapsed(Some(&symbols)), "start 34\nstart;outer 36\nstart;outer;inner 24\n");
+        assert_eq!(profiler.collapsed(None), "$0400 34\n$0400;$040B 36\n$0400;$040B;$040F 24\n");
+
+        let flat = profiler.flat(Some(&symbols));
+        assert_eq!(flat.lines().nth(1), Some("  38.30          36          60         3  outer"));
+        let graph = profiler.call_graph(Some(&symbols));
+        assert!(graph.starts_with("start  94 cycles, 34 in itself, 1 calls\n    -> outer  3 calls, 60 cycles\n"));
+        assert!(graph.contains("outer  60 cycles, 36 in itself, 3 calls\n    <- start  3 calls, 60 cycles\n    -> inner  3 calls, 24 cycles\n"));
+    }
+
+    #[test]
+    fn recursion_isnt_counted_twice() {
+        let source = "        *= $0400
+start:  LDX #$02
+        JSR count
+done:   JMP done
+count:  DEX
+        BEQ out
+        JSR count
+out:    RTS
+";
+        let (cpu, _, profiler, symbols) = profile(source, 10);
+        assert_eq!(cpu.pc, 0x0405);
+        let count = symbols.address_of("count").unwrap();
+        assert_eq!(subroutine(&profiler, count), SubroutineProfile { addr: count, calls: 2, cycles: 27, total: 27 });
+        assert_eq!(profiler.collapsed(Some(&symbols)), "start 11\nstart;count 16\nstart;count;count 11\n");
+        assert!(profiler.call_graph(Some(&symbols)).contains("    -> count  1 calls, 0 cycles\n"));
+    }
+
+    #[test]
+    fn interrupts_and_dropped_return_addresses() {
+        // The handler throws away its return address and the status and jumps back itself
+        let source = "        *= $0400
+start:  CLI
+loop:   JMP loop
+        *= $0500
+irq:    INC $10
+        PLA
+        PLA
+        PLA
+        CLI
+        JMP loop
+";
+        let assembly = Assembler::new(CpuVariant::Nmos6502).assemble(source).unwrap();
+        let (mut cpu, mut bus, profiler, _) = profile(source, 2);
+        bus.write(0xFFFE, 0x00);
+        bus.write(0xFFFF, 0x05);
+        cpu.set_irq(true);
+        cpu.step(&mut bus, 1);
+        cpu.set_irq(false);
+        cpu.step(&mut bus, 7);
+        assert_eq!(cpu.pc, 0x0401);
+
+        let irq = assembly.symbols.address_of("irq").unwrap();
+        // The interrupt sequence, INC and three PLAs, the last of which left the handler
+        assert_eq!(subroutine(&profiler, irq), SubroutineProfile { addr: irq, calls: 1, cycles: 7 + 5 + 12, total: 24 });
+        assert_eq!(profiler.collapsed(None), "$0400 13\n$0400;$0500 24\n");
+        assert_eq!(profiler.total_cycles(), 37);
+
+        profiler.clear();
+        assert_eq!(profiler.total_cycles(), 0);
+        assert!(profiler.subroutines().is_empty());
+    }
+
+    #[test]
+    fn report_names_hot_spots() {
+        let (_, _, profiler, symbols) = profile(NESTED, 22);
+        let report = profiler.report(2, Some(&symbols));
+        assert_eq!(
+            report,
+            "address  executions      cycles       %  label\n\
+             $0402           3          18   19.15  loop\n\
+             $040B           3          18   19.15  outer\n"
+        );
+        assert_eq!(profiler.report(1, None).lines().nth(1), Some("$0402           3          18   19.15"));
+    }
+}
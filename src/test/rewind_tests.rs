@@ -0,0 +1,204 @@
+#[cfg(test)]
+mod rewind_tests {
+    use crate::bus::Bus;
+    use crate::cpu::assembler::Assembler;
+    use crate::cpu::config::CpuVariant;
+    use crate::cpu::cpu::CPU;
+    use crate::debug::debugger::{Debugger, StopReason};
+    use crate::debug::monitor::Monitor;
+    use crate::debug::rewind::Rewind;
+    use crate::devices::mem::Mem;
+    use crate::platform::keyboard::Keyboard;
+    use crate::snapshot::{Snapshot, StateReader, StateWriter};
+
+    const PROGRAM: &str = "        *= $0400
+start:  LDX #$00
+loop:   INX
+        STX $10
+        JMP loop
+";
+
+    const LOOP: u16 = 0x0402;
+
+    fn init() -> (CPU, Bus) {
+        let assembly = Assembler::new(CpuVariant::Nmos6502).assemble(PROGRAM).unwrap();
+        let mut cpu = CPU::default();
+        let mut bus = Bus::default();
+        bus.register(0..=0xFFFF, Box::new(Mem::default(1024 * 64)));
+        assembly.load(&mut bus);
+        bus.write(0xFFFC, 0x00);
+        bus.write(0xFFFD, 0x04);
+        cpu.reset(&mut bus);
+        (cpu, bus)
+    }
+
+    // What has to match after going back
+    fn state(cpu: &CPU, bus: &mut Bus) -> (u16, u8, u64, u8) {
+        (cpu.pc, cpu.read_x(), cpu.read_cycles(), bus.peek(0x10))
+    }
+
+    // Steps `count` instructions with the debugger, giving the state before each one
+    fn run(debugger: &mut Debugger, cpu: &mut CPU, bus: &mut Bus, count: usize) -> Vec<(u16, u8, u64, u8)> {
+        let mut history = vec![];
+        for _ in 0..count {
+            history.push(state(cpu, bus));
+            debugger.step_into(cpu, bus);
+        }
+        history
+    }
+
+    #[test]
+    fn snapshots_restore_the_machine() {
+        let (mut cpu, mut bus) = init();
+        cpu.step(&mut bus, 7);
+        let snapshot = Snapshot::take(&cpu, &bus);
+        let before = state(&cpu, &mut bus);
+        cpu.step(&mut bus, 9);
+        assert_ne!(state(&cpu, &mut bus), before);
+
+        snapshot.restore(&mut cpu, &mut bus).unwrap();
+        assert_eq!(state(&cpu, &mut bus), before);
+        assert_eq!(snapshot.cycles(), before.2);
+
+        // It only fits a machine built the same way
+        let mut other = Bus::default();
+        other.register(0..=0x7FFF, Box::new(Mem::default(1024 * 32)));
+        other.register(0x8000..=0xFFFF, Box::new(Mem::default(1024 * 32)));
+        assert_eq!(snapshot.restore(&mut cpu, &mut other).err().unwrap(), "the state has 1 devices but the bus has 2");
+        let mut smaller = Bus::default();
+        smaller.register(0..=0xFFFF, Box::new(Mem::default(1024 * 32)));
+        assert_eq!(snapshot.restore(&mut cpu, &mut smaller).err().unwrap(), "device 0 at $0000: expected 32768 bytes of state, found 65536");
+    }
+
+    #[test]
+    fn state_readers_check_their_lengths() {
+        let mut state = StateWriter::default();
+        state.u16(0x1234);
+        state.bytes(&[1, 2, 3]);
+        let bytes = state.into_bytes();
+
+        let mut reader = StateReader::new(&bytes);
+        assert_eq!(reader.u16(), Ok(0x1234));
+        assert_eq!(reader.finish(), Err("7 bytes of state left over".to_string()));
+        assert_eq!(reader.bytes(), Ok(&[1u8, 2, 3][..]));
+        assert_eq!(reader.finish(), Ok(()));
+        assert_eq!(reader.u8(), Err("the state ends early".to_string()));
+    }
+
+    #[test]
+    fn stepping_back() {
+        let (mut cpu, mut bus) = init();
+        let mut debugger = Debugger::default();
+        debugger.rewind = Some(Rewind::new(20, 3));
+        let history = run(&mut debugger, &mut cpu, &mut bus, 30);
+
+        for expected in history.iter().rev().take(10) {
+            assert_eq!(debugger.step_back(&mut cpu, &mut bus), Ok(StopReason::Stepped));
+            assert_eq!(&state(&cpu, &mut bus), expected);
+        }
+        // Running forward again does the same thing
+        let again = run(&mut debugger, &mut cpu, &mut bus, 10);
+        assert_eq!(again, history[20..]);
+
+        // Only three snapshots are kept, so it can't go all the way back
+        let oldest = debugger.rewind.as_ref().unwrap().oldest().unwrap();
+        while debugger.step_back(&mut cpu, &mut bus).is_ok() {}
+        assert_eq!(cpu.read_cycles(), oldest);
+        assert!(oldest > history[0].2);
+        assert_eq!(debugger.step_back(&mut cpu, &mut bus), Err("can't go back any further".to_string()));
+        assert_eq!(debugger.rewind.as_ref().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn rewinding_and_reverse_continue() {
+        let (mut cpu, mut bus) = init();
+        let mut debugger = Debugger::default();
+        debugger.rewind = Some(Rewind::new(50, 10));
+        let history = run(&mut debugger, &mut cpu, &mut bus, 40);
+
+        // Back 30 cycles, to the first instruction starting after that
+        let now = cpu.read_cycles();
+        debugger.rewind.as_mut().unwrap().rewind(&mut cpu, &mut bus, 30).unwrap();
+        let expected = history.iter().find(|(_, _, cycles, _)| *cycles >= now - 30).unwrap();
+        assert_eq!(&state(&cpu, &mut bus), expected);
+
+        // Each reverse continue goes back one time round the loop
+        let id = debugger.add_breakpoint(LOOP);
+        let position = history.iter().position(|(_, _, cycles, _)| *cycles == expected.2).unwrap();
+        let mut hits = history[..position].iter().rev().filter(|(pc, _, _, _)| *pc == LOOP);
+        for _ in 0..2 {
+            assert_eq!(debugger.reverse_continue(&mut cpu, &mut bus), Ok(StopReason::Breakpoint { id, pc: LOOP }));
+            assert_eq!(&state(&cpu, &mut bus), hits.next().unwrap());
+        }
+
+        debugger.remove(&mut bus, id);
+        assert_eq!(debugger.reverse_continue(&mut cpu, &mut bus), Ok(StopReason::Limit));
+        assert_eq!(Some(cpu.read_cycles()), debugger.rewind.as_ref().unwrap().oldest());
+
+        debugger.rewind = None;
+        assert_eq!(debugger.step_back(&mut cpu, &mut bus), Err("rewind isn't being recorded".to_string()));
+    }
+
+    #[test]
+    fn keys_are_replayed_after_a_rewind() {
+        let mut keyboard = Keyboard::default();
+        let mut pressed = [0xFF; 8];
+        pressed[2] = 0xFE;
+        keyboard.update([0xFF; 8]);
+
+        let mut saved = StateWriter::default();
+        keyboard.save_state(&mut saved);
+        let saved = saved.into_bytes();
+        keyboard.update(pressed);
+        keyboard.update([0xFF; 8]);
+
+        // The frames that were recorded get the same keys whatever is held now
+        keyboard.load_state(&mut StateReader::new(&saved)).unwrap();
+        keyboard.update([0xFF; 8]);
+        assert_eq!(keyboard.get_row(2), Some(0xFE));
+        keyboard.update(pressed);
+        assert_eq!(keyboard.get_row(2), Some(0xFF));
+        keyboard.update(pressed);
+        assert_eq!(keyboard.get_row(2), Some(0xFE));
+
+        // Forgetting the rest of the log goes back to the live keys
+        keyboard.load_state(&mut StateReader::new(&saved)).unwrap();
+        keyboard.discard_future();
+        keyboard.update([0xFF; 8]);
+        assert_eq!(keyboard.get_row(2), Some(0xFF));
+    }
+
+    #[test]
+    fn monitor_commands() {
+        let (mut cpu, mut bus) = init();
+        let mut monitor = Monitor::default();
+
+        let mut run = |line: &str| monitor.execute(&mut cpu, &mut bus, line).unwrap();
+        assert_eq!(run("rec"), "Snapshots aren't being recorded");
+        assert_eq!(run("bk"), "error: rewind isn't being recorded");
+        assert_eq!(run("rec on"), "Recording snapshots");
+        assert_eq!(run("rec"), "No snapshots yet");
+        run("z 5");
+        // Five steps ended after the second INX
+        assert!(run("bk").starts_with("0402  E8        INX             PC:0402 A:00 X:01"));
+        assert!(run("bk 2").starts_with("0403  86 10     STX $10         PC:0403 A:00 X:01"));
+        assert!(run("rg").starts_with("Went back as far as the snapshots go\n0400  A2 00     LDX #$00"));
+        assert_eq!(run("rec"), "1 snapshots, back to 0 cycles ago");
+        assert_eq!(run("bk"), "error: can't go back any further");
+        assert_eq!(run("rec off"), "Stopped recording snapshots");
+    }
+
+    #[test]
+    fn snapshots_newer_than_the_machine() {
+        let (mut cpu, mut bus) = init();
+        let earlier = Snapshot::take(&cpu, &bus);
+        cpu.step(&mut bus, 10);
+        let mut monitor = Monitor::default();
+        monitor.execute(&mut cpu, &mut bus, "rec on");
+        monitor.execute(&mut cpu, &mut bus, "z 2");
+
+        // Going back to before the oldest snapshot by some other way
+        earlier.restore(&mut cpu, &mut bus).unwrap();
+        assert_eq!(monitor.execute(&mut cpu, &mut bus, "rec").unwrap(), "1 snapshots, back to 0 cycles ago");
+    }
+}
@@ -0,0 +1,154 @@
+#[cfg(test)]
+mod snapshot_tests {
+    use std::{cell::RefCell, env, fs, rc::Rc};
+
+    use crate::bus::{Bus, Mapping};
+    use crate::cpu::config::CpuVariant;
+    use crate::cpu::cpu::{CPU, Registers};
+    use crate::debug::monitor::Monitor;
+    use crate::devices::bbcmicro::paged_rom::{PagedRom, ROMSelectRegister};
+    use crate::devices::bbcmicro::system_via::SystemVIA;
+    use crate::devices::mem::Mem;
+    use crate::devices::rom::Rom;
+    use crate::platform::keyboard::Keyboard;
+    use crate::snapshot::{Snapshot, VERSION};
+
+    // A BBC Micro without the video, which needs a window, and with `roms` paged ROMs whose
+    // bytes are all their number
+    fn machine(roms: u8) -> (CPU, Bus) {
+        let cpu = CPU::default();
+        let mut bus = Bus::default();
+        bus.register(0..=0x7FFF, Box::new(Rc::new(RefCell::new(Mem::default(32 * 1024)))));
+        let paged_rom = Rc::new(RefCell::new(PagedRom::default()));
+        for rom in 0..roms {
+            paged_rom.borrow_mut().add_rom(Rom::default(vec![rom; 0x4000]));
+        }
+        bus.register(0x8000..=0xBFFF, Box::new(paged_rom.clone()));
+        bus.register(0xFE30..=0xFE30, Box::new(ROMSelectRegister::default(paged_rom)));
+        bus.register(0xFE40..=0xFE4F, Box::new(SystemVIA::default(Rc::new(RefCell::new(Keyboard::default())))));
+        (cpu, bus)
+    }
+
+    fn temp_path(name: &str) -> String {
+        env::temp_dir().join(format!("snapshot_test_{}_{}", std::process::id(), name)).to_str().unwrap().to_string()
+    }
+
+    // Somewhere every part of the machine has been changed from how it starts
+    fn changed_machine() -> (CPU, Bus) {
+        let (mut cpu, mut bus) = machine(2);
+        cpu.config.variant = CpuVariant::Wdc65C02;
+        cpu.config.speed = 2.5;
+        cpu.set_registers(Registers { a: 0x12, x: 0x34, y: 0x56, sp: 0xF0, status: 0xE3, pc: 0x1234 });
+        bus.write(0x1234, 0x56);
+        bus.write(0xFE30, 1);
+        bus.write(0xFE42, 0xFF);
+        bus.write(0xFE40, 0x0F);
+        bus.write(0xFE4E, 0x82);
+        (cpu, bus)
+    }
+
+    #[test]
+    fn saving_and_loading_the_whole_machine() {
+        let (cpu, bus) = changed_machine();
+        let path = temp_path("machine.state");
+        Snapshot::take(&cpu, &bus).save(&path).unwrap();
+
+        let (mut cpu, mut bus) = machine(2);
+        Snapshot::load(&path).unwrap().restore(&mut cpu, &mut bus).unwrap();
+        assert_eq!((cpu.read_acc(), cpu.read_x(), cpu.read_y(), cpu.read_sp(), cpu.read_status(), cpu.pc), (0x12, 0x34, 0x56, 0xF0, 0xE3, 0x1234));
+        assert_eq!(cpu.config.variant, CpuVariant::Wdc65C02);
+        assert_eq!(cpu.config.speed, 2.5);
+        assert_eq!(bus.peek(0x1234), 0x56);
+        // The second ROM is paged in
+        assert_eq!(bus.peek(0x8000), 1);
+        assert_eq!(bus.peek(0xFE42), 0xFF);
+        assert_eq!(bus.peek(0xFE40), 0x0F);
+        assert_eq!(bus.peek(0xFE4E), 0x82);
+
+        let bytes = fs::read(&path).unwrap();
+        assert_eq!(&bytes[..8], b"6502SNAP");
+        assert_eq!(bytes[8..10], VERSION.to_le_bytes());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn headers_are_checked() {
+        let (cpu, bus) = changed_machine();
+        let bytes = Snapshot::take(&cpu, &bus).to_bytes();
+        assert!(Snapshot::from_bytes(&bytes).is_ok());
+        assert_eq!(Snapshot::from_bytes(b"PK\x03\x04").err().unwrap(), "not a save state");
+        assert_eq!(Snapshot::from_bytes(&bytes[..12]).err().unwrap(), "the header is cut short");
+
+        let mut newer = bytes.clone();
+        newer[8..10].copy_from_slice(&(VERSION + 1).to_le_bytes());
+        assert_eq!(Snapshot::from_bytes(&newer).err().unwrap(), format!("a version {} save state, only version {} can be loaded", VERSION + 1, VERSION));
+
+        let path = temp_path("not_a.state");
+        fs::write(&path, b"hello").unwrap();
+        assert_eq!(Snapshot::load(&path).err().unwrap(), format!("{}: not a save state", path));
+        fs::remove_file(&path).unwrap();
+        assert!(Snapshot::load(&path).err().unwrap().starts_with(&format!("couldn't read {}", path)));
+    }
+
+    #[test]
+    fn incompatible_machines_are_refused() {
+        let (cpu, bus) = changed_machine();
+        let snapshot = Snapshot::take(&cpu, &bus);
+        let (mut cpu, _) = machine(2);
+
+        let mut bare = Bus::default();
+        bare.register(0..=0xFFFF, Box::new(Mem::default(1024 * 64)));
+        assert_eq!(snapshot.restore(&mut cpu, &mut bare).err().unwrap(), "the state has 4 devices but the bus has 1");
+
+        let mut moved = Bus::default();
+        for range in [0..=0x3FFF, 0x8000..=0xBFFF, 0xFE30..=0xFE30, 0xFE40..=0xFE4F] {
+            moved.register(range, Box::new(Mem::default(32 * 1024)));
+        }
+        assert_eq!(snapshot.restore(&mut cpu, &mut moved).err().unwrap(), "device 0 is at $0000-$7FFF in the state but $0000-$3FFF on the bus");
+
+        // The same ranges, with the last one mirrored or laid over the others differently
+        let bus = |last: Mapping| {
+            let mut bus = Bus::default();
+            bus.register(0..=0x7FFF, Box::new(Mem::default(32 * 1024)));
+            bus.map(last, Box::new(Mem::default(16))).unwrap();
+            bus
+        };
+        let (mut cpu, _) = machine(2);
+        let saved = Snapshot::take(&cpu, &bus(Mapping::new(0xFE40..=0xFE4F)));
+        assert!(saved.restore(&mut cpu, &mut bus(Mapping::new(0xFE40..=0xFE4F))).is_ok());
+        assert_eq!(
+            saved.restore(&mut cpu, &mut bus(Mapping::mirrored(0xFE40..=0xFE4F, 0x07))).err().unwrap(),
+            "device 1 has mask $FFFF at priority 0 in the state but mask $0007 at priority 0 on the bus"
+        );
+        assert_eq!(
+            saved.restore(&mut cpu, &mut bus(Mapping { priority: 1, ..Mapping::new(0xFE40..=0xFE4F) })).err().unwrap(),
+            "device 1 has mask $FFFF at priority 0 in the state but mask $FFFF at priority 1 on the bus"
+        );
+
+        // Only one ROM to page in
+        let (mut cpu, mut bus) = machine(1);
+        assert_eq!(snapshot.restore(&mut cpu, &mut bus).err().unwrap(), "device 1 at $8000: there's no paged ROM 1");
+
+        // The variant comes after the magic, version, cycle count and 20 bytes of registers
+        let mut bytes = snapshot.to_bytes();
+        bytes[38] = 7;
+        let (mut cpu, mut bus) = machine(2);
+        assert_eq!(Snapshot::from_bytes(&bytes).unwrap().restore(&mut cpu, &mut bus).err().unwrap(), "unknown CPU variant 7");
+    }
+
+    #[test]
+    fn monitor_commands() {
+        let (mut cpu, mut bus) = changed_machine();
+        let mut monitor = Monitor::default();
+        let path = temp_path("monitor.state");
+
+        assert_eq!(monitor.execute(&mut cpu, &mut bus, &format!("dump {}", path)).unwrap(), format!("Saved the machine to {}", path));
+        let (mut cpu, mut bus) = machine(2);
+        let mut run = |line: &str| monitor.execute(&mut cpu, &mut bus, line).unwrap();
+        assert!(run(&format!("undump {}", path)).starts_with(&format!("Loaded {}\n1234", path)));
+        assert_eq!(run("m 1234 1234").split_whitespace().nth(1), Some("56"));
+        assert_eq!(run("undump"), "error: expected a file");
+        fs::remove_file(&path).unwrap();
+        assert!(run(&format!("undump {}", path)).starts_with("error: couldn't read"));
+    }
+}
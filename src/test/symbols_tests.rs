@@ -0,0 +1,175 @@
+#[cfg(test)]
+mod symbols_tests {
+    use std::{env, fs};
+
+    use crate::bus::Bus;
+    use crate::cpu::assembler::Assembler;
+    use crate::cpu::config::CpuVariant;
+    use crate::cpu::cpu::CPU;
+    use crate::cpu::disassembler::Disassembler;
+    use crate::cpu::trace::RingBuffer;
+    use crate::debug::monitor::Monitor;
+    use crate::debug::symbols::{SourceLine, SymbolTable};
+    use crate::devices::mem::Mem;
+
+    // Three lines of main.s, a line from a macro, and a line whose span covers all of them
+    const DBG: &str = "version\tmajor=2,minor=0
+info\tcsym=0,file=2,lib=0,line=5,mod=1,scope=1,seg=1,span=4,sym=3,type=1
+file\tid=0,name=\"main.s\",size=120,mtime=0x5F000000,mod=0
+file\tid=1,name=\"macros.inc\",size=40,mtime=0x5F000000,mod=0
+line\tid=0,file=0,line=4,span=0
+line\tid=1,file=0,line=5,span=1
+line\tid=2,file=1,line=2,type=2,span=2
+line\tid=3,file=0,line=6,span=2
+line\tid=4,file=0,line=1,span=3
+mod\tid=0,name=\"main.o\",file=0
+seg\tid=0,name=\"CODE\",start=0x000400,size=0x0008,addrsize=absolute,type=ro,oname=\"main.bin\",ooffs=0
+span\tid=0,seg=0,start=0,size=2
+span\tid=1,seg=0,start=2,size=3
+span\tid=2,seg=0,start=5,size=3
+span\tid=3,seg=0,start=0,size=8
+scope\tid=0,name=\"\",mod=0,size=8,span=3
+sym\tid=0,name=\"main\",addrsize=absolute,scope=0,def=4,ref=5,val=0x400,seg=0,type=lab
+sym\tid=1,name=\"@loop\",addrsize=absolute,scope=0,parent=0,def=1,val=0x402,seg=0,type=lab
+sym\tid=2,name=\"COUNT\",addrsize=zeropage,scope=0,def=0,val=0x3,type=equ
+";
+
+    fn line(file: &str, line: usize) -> SourceLine {
+        SourceLine { file: file.to_string(), line }
+    }
+
+    #[test]
+    fn plain_files() {
+        let table = SymbolTable::parse("; OS calls\nOSWRCH = $FFEE\nscreen = &7C00 # BBC hex\n\nbuffer=0x0900\ncount = 16\n").unwrap();
+        assert_eq!(table.address_of("OSWRCH"), Some(0xFFEE));
+        assert_eq!(table.address_of("screen"), Some(0x7C00));
+        assert_eq!(table.address_of("buffer"), Some(0x0900));
+        assert_eq!(table.name_at(16), Some("count"));
+        assert_eq!(SymbolTable::parse("a = $10\nnonsense\n").err().unwrap(), "line 2: expected name = address");
+    }
+
+    #[test]
+    fn vice_labels() {
+        let table = SymbolTable::parse("al C:0400 .main_loop\nal 8:0300 .drive_code\nbreak 0400\nal 0410 .done\n").unwrap();
+        assert_eq!(table.address_of("main_loop"), Some(0x0400));
+        assert_eq!(table.address_of("done"), Some(0x0410));
+        // The disk drive's labels aren't for this machine's memory
+        assert_eq!(table.address_of("drive_code"), None);
+        assert!(SymbolTable::parse("al C:zz00 .bad\n").is_err());
+    }
+
+    #[test]
+    fn ca65_debug_info() {
+        let table = SymbolTable::parse(DBG).unwrap();
+        assert_eq!(table.name_at(0x0400), Some("main"));
+        // Cheap locals and equates aren't labels
+        assert_eq!(table.len(), 1);
+
+        // The smallest span wins, and the macro's own line is skipped for the line using it
+        assert_eq!(table.line_at(0x0400), Some(&line("main.s", 4)));
+        assert_eq!(table.line_at(0x0402), Some(&line("main.s", 5)));
+        assert_eq!(table.line_at(0x0405), Some(&line("main.s", 6)));
+        assert_eq!(table.line_at(0x0403), None);
+        assert_eq!(table.lines().count(), 3);
+    }
+
+    #[test]
+    fn load_resolves_source_files() {
+        let dir = env::temp_dir().join(format!("symbols_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("main.dbg");
+        fs::write(&path, DBG).unwrap();
+
+        let table = SymbolTable::load(path.to_str().unwrap()).unwrap();
+        let expected = dir.join("main.s").to_string_lossy().to_string();
+        assert_eq!(table.line_at(0x0400), Some(&line(&expected, 4)));
+        assert!(SymbolTable::load(dir.join("missing.lbl").to_str().unwrap()).err().unwrap().starts_with("couldn't read"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn labels_with_offsets() {
+        let mut table = SymbolTable::default();
+        table.insert("main_loop", 0x0400);
+        assert_eq!(table.label(0x0400).as_deref(), Some("main_loop"));
+        assert_eq!(table.label(0x0403).as_deref(), Some("main_loop+3"));
+        assert_eq!(table.label(0x04FF).as_deref(), Some("main_loop+255"));
+        assert_eq!(table.label(0x0500), None);
+        assert_eq!(table.label(0x03FF), None);
+
+        let mut other = SymbolTable::default();
+        other.insert("start", 0x0400);
+        other.insert_line(0x0400, "main.s", 1);
+        table.merge(&other);
+        assert_eq!(table.name_at(0x0400), Some("start"));
+        assert_eq!(table.address_of("main_loop"), None);
+        assert_eq!(table.line_at(0x0400), Some(&line("main.s", 1)));
+    }
+
+    #[test]
+    fn bbc_os_symbols() {
+        let table = SymbolTable::bbc_os();
+        assert_eq!(table.address_of("OSWRCH"), Some(0xFFEE));
+        assert_eq!(table.address_of("OSBYTE"), Some(0xFFF4));
+        assert_eq!(table.address_of("WRCHV"), Some(0x020E));
+        assert_eq!(table.name_at(0xFE4D), Some("SYSVIA_IFR"));
+        assert_eq!(table.label(0xDC1F).as_deref(), Some("IRQ+3"));
+    }
+
+    fn init() -> (CPU, Bus) {
+        let assembly = Assembler::new(CpuVariant::Nmos6502)
+            .assemble("*= $0400\nmain_loop: LDA #$41\n JSR $FFEE\n JMP main_loop\n")
+            .unwrap();
+        let mut cpu = CPU::default();
+        let mut bus = Bus::default();
+        bus.register(0..=0xFFFF, Box::new(Mem::default(1024 * 64)));
+        assembly.load(&mut bus);
+        bus.write(0xFFEE, 0x60);
+        bus.write(0xFFFC, 0x00);
+        bus.write(0xFFFD, 0x04);
+        cpu.reset(&mut bus);
+        (cpu, bus)
+    }
+
+    #[test]
+    fn named_disassembly_and_traces() {
+        let (mut cpu, mut bus) = init();
+        let mut symbols = SymbolTable::bbc_os();
+        symbols.insert("main_loop", 0x0400);
+
+        let line = Disassembler::for_cpu(&cpu).decode(&mut bus, 0x0402);
+        assert_eq!(line.format(Some(&symbols)), "0402  20 EE FF  JSR OSWRCH");
+        assert_eq!(line.to_string(), "0402  20 EE FF  JSR $FFEE");
+
+        let buffer = RingBuffer::new(4);
+        cpu.config.tracer = Some(Box::new(buffer.clone()));
+        cpu.step(&mut bus, 3);
+        let events = buffer.events();
+        assert!(events[1].format(Some(&symbols)).ends_with("  ; main_loop+2"));
+        assert!(events[1].format(Some(&symbols)).contains("JSR OSWRCH"));
+        assert!(events[2].format(Some(&symbols)).ends_with("; OSWRCH"));
+        assert_eq!(events[1].format(None), events[1].to_string());
+    }
+
+    #[test]
+    fn monitor_labels() {
+        let (mut cpu, mut bus) = init();
+        let mut monitor = Monitor::default();
+        let path = env::temp_dir().join(format!("symbols_test_{}.lbl", std::process::id()));
+        fs::write(&path, "al C:0400 .main_loop\nal C:FFEE .OSWRCH\n").unwrap();
+
+        let mut run = |line: &str| monitor.execute(&mut cpu, &mut bus, line).unwrap();
+        assert_eq!(run("shl"), "No labels");
+        assert_eq!(run(&format!("ll \"{}\"", path.to_str().unwrap())), "Loaded 2 labels");
+        run("al 0405 .forever");
+        assert_eq!(run("shl"), "$0400 main_loop\n$0405 forever\n$FFEE OSWRCH");
+        assert_eq!(
+            run("d 400 405"),
+            "main_loop:\n0400  A9 41     LDA #$41\n0402  20 EE FF  JSR OSWRCH\nforever:\n0405  4C 00 04  JMP main_loop"
+        );
+        run("b 402");
+        assert!(run("g").starts_with("Breakpoint 1 at $0402 (main_loop+2)\n0402  20 EE FF  JSR OSWRCH"));
+        assert!(run("ll nowhere.lbl").starts_with("error: couldn't read"));
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,121 @@
+#[cfg(test)]
+mod trace_tests {
+    use crate::bus::Bus;
+    use crate::cpu::addresing::AddrMode;
+    use crate::cpu::cpu::CPU;
+    use crate::cpu::trace::{FileTracer, InterruptKind, RingBuffer, TraceEvent};
+    use crate::devices::mem::Mem;
+
+    fn init(program: &[u8]) -> (CPU, Bus, RingBuffer) {
+        let mut cpu = CPU::default();
+        let mut bus = Bus::default();
+        bus.register(0..=0xFFFF, Box::new(Mem::default(1024 * 64)));
+        for (i, byte) in program.iter().enumerate() {
+            bus.write(0x0200 + i as u16, *byte);
+        }
+        bus.write(0xFFFC, 0x00);
+        bus.write(0xFFFD, 0x02);
+        cpu.reset(&mut bus);
+
+        let buffer = RingBuffer::new(16);
+        cpu.config.tracer = Some(Box::new(buffer.clone()));
+        (cpu, bus, buffer)
+    }
+
+    #[test]
+    fn instruction_event() {
+        // LDX #$04, LDA $1230,X
+        let (mut cpu, mut bus, buffer) = init(&[0xA2, 0x04, 0xBD, 0x30, 0x12]);
+        bus.write(0x1234, 0x99);
+        cpu.step(&mut bus, 2);
+
+        let events = buffer.events();
+        assert_eq!(events.len(), 2);
+        let TraceEvent::Instruction(ins) = &events[1] else {
+            panic!("expected an instruction event");
+        };
+        assert_eq!(ins.opcode, 0xBD);
+        assert_eq!(ins.mnemonic, "LDA");
+        assert_eq!(ins.mode, AddrMode::AbsoluteX);
+        assert_eq!(ins.operands(), &[0x30, 0x12]);
+        assert_eq!(ins.effective_address, Some(0x1234));
+        assert_eq!(ins.before.pc, 0x0202);
+        assert_eq!(ins.before.a, 0x00);
+        assert_eq!(ins.after.pc, 0x0205);
+        assert_eq!(ins.after.a, 0x99);
+        assert_eq!(ins.cycles, 4);
+        assert_eq!(ins.start_cycle, 2);
+    }
+
+    #[test]
+    fn implied_has_no_operands_or_address() {
+        let (mut cpu, mut bus, buffer) = init(&[0xE8]); // INX
+        cpu.step(&mut bus, 1);
+        let TraceEvent::Instruction(ins) = &buffer.events()[0] else {
+            panic!("expected an instruction event");
+        };
+        assert!(ins.operands().is_empty());
+        assert_eq!(ins.effective_address, None);
+        assert_eq!(ins.after.x, 1);
+    }
+
+    #[test]
+    fn ring_buffer_drops_oldest() {
+        let (mut cpu, mut bus, _) = init(&[0xEA; 8]);
+        let buffer = RingBuffer::new(3);
+        cpu.config.tracer = Some(Box::new(buffer.clone()));
+        cpu.step(&mut bus, 5);
+
+        let pcs: Vec<u16> = buffer
+            .events()
+            .iter()
+            .map(|e| match e {
+                TraceEvent::Instruction(ins) => ins.before.pc,
+                _ => panic!("expected an instruction event"),
+            })
+            .collect();
+        assert_eq!(pcs, vec![0x0202, 0x0203, 0x0204]);
+    }
+
+    #[test]
+    fn interrupt_event() {
+        let (mut cpu, mut bus, buffer) = init(&[0x58, 0xEA]); // CLI, NOP
+        cpu.step(&mut bus, 1);
+        cpu.set_irq(true);
+        cpu.step(&mut bus, 1);
+
+        let events = buffer.events();
+        assert!(matches!(events[1], TraceEvent::Interrupt { kind: InterruptKind::Irq, before, .. } if before.pc == 0x0201));
+    }
+
+    #[test]
+    fn unknown_opcode_event() {
+        let (mut cpu, mut bus, buffer) = init(&[0xA7, 0x10]); // LAX $10
+        cpu.config.undocumented_opcodes = false;
+        cpu.step(&mut bus, 1);
+        assert_eq!(buffer.events(), vec![TraceEvent::UnknownOpcode { pc: 0x0200, opcode: 0xA7 }]);
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let cpu = CPU::default();
+        assert!(cpu.config.tracer.is_none());
+    }
+
+    #[test]
+    fn file_tracer_writes_a_line_per_event() {
+        let path = std::env::temp_dir().join("emulate6502_trace_test.log");
+        let path = path.to_str().unwrap();
+        let (mut cpu, mut bus, _) = init(&[0xA9, 0x01, 0xEA]); // LDA #$01, NOP
+        cpu.config.tracer = Some(Box::new(FileTracer::create(path).unwrap()));
+        cpu.step(&mut bus, 2);
+        cpu.config.tracer = None;
+
+        let log = std::fs::read_to_string(path).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("0200  A9 01     LDA"));
+        assert!(lines[1].starts_with("0202  EA        NOP"));
+        let _ = std::fs::remove_file(path);
+    }
+}
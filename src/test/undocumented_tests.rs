@@ -0,0 +1,339 @@
+#[cfg(test)]
+mod undocumented_tests {
+    use crate::bus::Bus;
+    use crate::cpu::cpu::CPU;
+    use crate::devices::mem::Mem;
+
+    // Every opcode the NMOS 6502 leaves undocumented
+    const UNDOCUMENTED: [u8; 105] = [
+        0x02, 0x03, 0x04, 0x07, 0x0B, 0x0C, 0x0F, 0x12, 0x13, 0x14, 0x17, 0x1A, 0x1B, 0x1C, 0x1F,
+        0x22, 0x23, 0x27, 0x2B, 0x2F, 0x32, 0x33, 0x34, 0x37, 0x3A, 0x3B, 0x3C, 0x3F,
+        0x42, 0x43, 0x44, 0x47, 0x4B, 0x4F, 0x52, 0x53, 0x54, 0x57, 0x5A, 0x5B, 0x5C, 0x5F,
+        0x62, 0x63, 0x64, 0x67, 0x6B, 0x6F, 0x72, 0x73, 0x74, 0x77, 0x7A, 0x7B, 0x7C, 0x7F,
+        0x80, 0x82, 0x83, 0x87, 0x89, 0x8B, 0x8F, 0x92, 0x93, 0x97, 0x9B, 0x9C, 0x9E, 0x9F,
+        0xA3, 0xA7, 0xAB, 0xAF, 0xB2, 0xB3, 0xB7, 0xBB, 0xBF,
+        0xC2, 0xC3, 0xC7, 0xCB, 0xCF, 0xD2, 0xD3, 0xD4, 0xD7, 0xDA, 0xDB, 0xDC, 0xDF,
+        0xE2, 0xE3, 0xE7, 0xEB, 0xEF, 0xF2, 0xF3, 0xF4, 0xF7, 0xFA, 0xFB, 0xFC, 0xFF,
+    ];
+
+    fn init() -> (CPU, Bus) {
+        let mut cpu = CPU::default();
+        let mut bus = Bus::default();
+        let mem = Box::new(Mem::default(1024 * 64));
+        bus.register(0..=0xFFFF, mem);
+
+        bus.write(0xFFFC, 0x00);
+        bus.write(0xFFFD, 0x00);
+        cpu.reset(&mut bus);
+        (cpu, bus)
+    }
+
+    #[test]
+    fn every_undocumented_opcode_is_implemented() {
+        for ins in UNDOCUMENTED {
+            let (mut cpu, mut bus) = init();
+            bus.write(0x0000, ins);
+            let ticks = cpu.step(&mut bus, 1);
+            // The unknown opcode path only spends the fetch cycle
+            assert!(ticks >= 2, "opcode {:02X} took {} ticks", ins, ticks);
+        }
+    }
+
+    #[test]
+    fn disabled_undocumented_opcodes_are_skipped() {
+        let (mut cpu, mut bus) = init();
+        cpu.config.undocumented_opcodes = false;
+        bus.write(0x0000, 0xA7); // LAX zero page
+        bus.write(0x0001, 0x10);
+        bus.write(0x0010, 0x55);
+        cpu.step(&mut bus, 1);
+        assert_eq!(cpu.pc, 0x0001);
+        assert_eq!(cpu.read_acc(), 0x00);
+    }
+
+    #[test]
+    fn lax_zp() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0xA7); // LAX zero page
+        bus.write(0x0001, 0x10);
+        bus.write(0x0010, 0x85);
+        let ticks = cpu.step(&mut bus, 1);
+        assert_eq!(cpu.read_acc(), 0x85);
+        assert_eq!(cpu.read_x(), 0x85);
+        assert_eq!(cpu.read_status() & 0b10000000, 0b10000000); // N set
+        assert_eq!(ticks, 3);
+    }
+
+    #[test]
+    fn lax_absolute_y_page_cross() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0xA0); // LDY immediate
+        bus.write(0x0001, 0x01);
+        bus.write(0x0002, 0xBF); // LAX absolute, Y
+        bus.write(0x0003, 0xFF);
+        bus.write(0x0004, 0x20);
+        bus.write(0x2100, 0x42);
+        cpu.step(&mut bus, 1);
+        let ticks = cpu.step(&mut bus, 1);
+        assert_eq!(cpu.read_acc(), 0x42);
+        assert_eq!(cpu.read_x(), 0x42);
+        assert_eq!(ticks, 5);
+    }
+
+    #[test]
+    fn sax_zp() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0xA9); // LDA immediate
+        bus.write(0x0001, 0xF0);
+        bus.write(0x0002, 0xA2); // LDX immediate
+        bus.write(0x0003, 0x3C);
+        bus.write(0x0004, 0x87); // SAX zero page
+        bus.write(0x0005, 0x20);
+        cpu.step(&mut bus, 3);
+        assert_eq!(bus.read(0x0020), 0x30);
+    }
+
+    #[test]
+    fn slo_zp() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0xA9); // LDA immediate
+        bus.write(0x0001, 0x01);
+        bus.write(0x0002, 0x07); // SLO zero page
+        bus.write(0x0003, 0x20);
+        bus.write(0x0020, 0x81);
+        cpu.step(&mut bus, 2);
+        assert_eq!(bus.read(0x0020), 0x02);
+        assert_eq!(cpu.read_acc(), 0x03);
+        assert_eq!(cpu.read_status() & 0b00000001, 0b00000001); // C from bit 7
+    }
+
+    #[test]
+    fn slo_indirect_y_takes_eight_cycles() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0x13); // SLO (zp), Y
+        bus.write(0x0001, 0x40);
+        bus.write(0x0040, 0x00);
+        bus.write(0x0041, 0x30);
+        bus.write(0x3000, 0x01);
+        let ticks = cpu.step(&mut bus, 1);
+        assert_eq!(bus.read(0x3000), 0x02);
+        assert_eq!(ticks, 8);
+    }
+
+    #[test]
+    fn rla_zp() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0x38); // SEC
+        bus.write(0x0001, 0xA9); // LDA immediate
+        bus.write(0x0002, 0x0F);
+        bus.write(0x0003, 0x27); // RLA zero page
+        bus.write(0x0004, 0x20);
+        bus.write(0x0020, 0x84);
+        cpu.step(&mut bus, 3);
+        assert_eq!(bus.read(0x0020), 0x09);
+        assert_eq!(cpu.read_acc(), 0x09);
+        assert_eq!(cpu.read_status() & 0b00000001, 0b00000001);
+    }
+
+    #[test]
+    fn sre_zp() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0xA9); // LDA immediate
+        bus.write(0x0001, 0xFF);
+        bus.write(0x0002, 0x47); // SRE zero page
+        bus.write(0x0003, 0x20);
+        bus.write(0x0020, 0x03);
+        cpu.step(&mut bus, 2);
+        assert_eq!(bus.read(0x0020), 0x01);
+        assert_eq!(cpu.read_acc(), 0xFE);
+        assert_eq!(cpu.read_status() & 0b00000001, 0b00000001);
+    }
+
+    #[test]
+    fn rra_zp() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0x18); // CLC
+        bus.write(0x0001, 0xA9); // LDA immediate
+        bus.write(0x0002, 0x10);
+        bus.write(0x0003, 0x67); // RRA zero page
+        bus.write(0x0004, 0x20);
+        bus.write(0x0020, 0x05);
+        cpu.step(&mut bus, 3);
+        // ROR gives 0x02 with carry out 1, then A = 0x10 + 0x02 + 1
+        assert_eq!(bus.read(0x0020), 0x02);
+        assert_eq!(cpu.read_acc(), 0x13);
+    }
+
+    #[test]
+    fn dcp_zp() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0xA9); // LDA immediate
+        bus.write(0x0001, 0x41);
+        bus.write(0x0002, 0xC7); // DCP zero page
+        bus.write(0x0003, 0x20);
+        bus.write(0x0020, 0x42);
+        cpu.step(&mut bus, 2);
+        assert_eq!(bus.read(0x0020), 0x41);
+        assert_eq!(cpu.read_status() & 0b00000011, 0b00000011); // Z and C
+    }
+
+    #[test]
+    fn isc_zp() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0x38); // SEC
+        bus.write(0x0001, 0xA9); // LDA immediate
+        bus.write(0x0002, 0x10);
+        bus.write(0x0003, 0xE7); // ISC zero page
+        bus.write(0x0004, 0x20);
+        bus.write(0x0020, 0x04);
+        cpu.step(&mut bus, 3);
+        assert_eq!(bus.read(0x0020), 0x05);
+        assert_eq!(cpu.read_acc(), 0x0B);
+    }
+
+    #[test]
+    fn anc_copies_n_into_c() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0xA9); // LDA immediate
+        bus.write(0x0001, 0xF0);
+        bus.write(0x0002, 0x0B); // ANC immediate
+        bus.write(0x0003, 0x80);
+        cpu.step(&mut bus, 2);
+        assert_eq!(cpu.read_acc(), 0x80);
+        assert_eq!(cpu.read_status() & 0b10000001, 0b10000001);
+    }
+
+    #[test]
+    fn alr_immediate() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0xA9); // LDA immediate
+        bus.write(0x0001, 0xFF);
+        bus.write(0x0002, 0x4B); // ALR immediate
+        bus.write(0x0003, 0x03);
+        cpu.step(&mut bus, 2);
+        assert_eq!(cpu.read_acc(), 0x01);
+        assert_eq!(cpu.read_status() & 0b00000001, 0b00000001);
+    }
+
+    #[test]
+    fn arr_immediate() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0x38); // SEC
+        bus.write(0x0001, 0xA9); // LDA immediate
+        bus.write(0x0002, 0xFF);
+        bus.write(0x0003, 0x6B); // ARR immediate
+        bus.write(0x0004, 0xC0);
+        cpu.step(&mut bus, 3);
+        assert_eq!(cpu.read_acc(), 0xE0);
+        // C from bit 6, V from bit 6 xor bit 5
+        assert_eq!(cpu.read_status() & 0b01000001, 0b00000001);
+    }
+
+    #[test]
+    fn ane_uses_magic_constant() {
+        let (mut cpu, mut bus) = init();
+        cpu.config.ane_magic = 0xFF;
+        bus.write(0x0000, 0xA2); // LDX immediate
+        bus.write(0x0001, 0x0F);
+        bus.write(0x0002, 0x8B); // ANE immediate
+        bus.write(0x0003, 0x3C);
+        cpu.step(&mut bus, 2);
+        assert_eq!(cpu.read_acc(), 0x0C);
+    }
+
+    #[test]
+    fn lxa_uses_magic_constant() {
+        let (mut cpu, mut bus) = init();
+        cpu.config.lxa_magic = 0x00;
+        bus.write(0x0000, 0xA9); // LDA immediate
+        bus.write(0x0001, 0x0F);
+        bus.write(0x0002, 0xAB); // LXA immediate
+        bus.write(0x0003, 0x3C);
+        cpu.step(&mut bus, 2);
+        assert_eq!(cpu.read_acc(), 0x0C);
+        assert_eq!(cpu.read_x(), 0x0C);
+    }
+
+    #[test]
+    fn sbx_immediate() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0xA9); // LDA immediate
+        bus.write(0x0001, 0x0F);
+        bus.write(0x0002, 0xA2); // LDX immediate
+        bus.write(0x0003, 0x3C);
+        bus.write(0x0004, 0xCB); // SBX immediate
+        bus.write(0x0005, 0x02);
+        cpu.step(&mut bus, 3);
+        assert_eq!(cpu.read_x(), 0x0A);
+        assert_eq!(cpu.read_status() & 0b00000001, 0b00000001);
+    }
+
+    #[test]
+    fn usbc_matches_sbc() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0x38); // SEC
+        bus.write(0x0001, 0xA9); // LDA immediate
+        bus.write(0x0002, 0x10);
+        bus.write(0x0003, 0xEB); // USBC immediate
+        bus.write(0x0004, 0x05);
+        cpu.step(&mut bus, 3);
+        assert_eq!(cpu.read_acc(), 0x0B);
+    }
+
+    #[test]
+    fn shx_ands_with_high_byte_plus_one() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0xA2); // LDX immediate
+        bus.write(0x0001, 0xFF);
+        bus.write(0x0002, 0x9E); // SHX absolute, Y
+        bus.write(0x0003, 0x00);
+        bus.write(0x0004, 0x12);
+        let _ = cpu.step(&mut bus, 1);
+        let ticks = cpu.step(&mut bus, 1);
+        assert_eq!(bus.read(0x1200), 0x13);
+        assert_eq!(ticks, 5);
+    }
+
+    #[test]
+    fn las_absolute_y() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0xBB); // LAS absolute, Y
+        bus.write(0x0001, 0x00);
+        bus.write(0x0002, 0x30);
+        bus.write(0x3000, 0x0F);
+        cpu.step(&mut bus, 1);
+        // SP is 0xFD after reset
+        assert_eq!(cpu.read_acc(), 0x0D);
+        assert_eq!(cpu.read_x(), 0x0D);
+        assert_eq!(cpu.read_sp(), 0x0D);
+    }
+
+    #[test]
+    fn nop_variants_skip_their_operands() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0x80); // NOP immediate
+        bus.write(0x0002, 0x04); // NOP zero page
+        bus.write(0x0004, 0x14); // NOP zero page, X
+        bus.write(0x0006, 0x0C); // NOP absolute
+        bus.write(0x0009, 0x1C); // NOP absolute, X
+        bus.write(0x000C, 0x1A); // NOP implied
+        let ticks = cpu.step(&mut bus, 6);
+        assert_eq!(cpu.pc, 0x000D);
+        assert_eq!(ticks, 2 + 3 + 4 + 4 + 4 + 2);
+    }
+
+    #[test]
+    fn jam_halts_until_reset() {
+        let (mut cpu, mut bus) = init();
+        bus.write(0x0000, 0x02); // JAM
+        bus.write(0x0001, 0xEA); // NOP
+        cpu.step(&mut bus, 3);
+        assert!(cpu.is_jammed());
+        assert_eq!(cpu.pc, 0x0000);
+        cpu.set_nmi(true);
+        cpu.step(&mut bus, 1);
+        assert_eq!(cpu.pc, 0x0000); // Interrupts do not wake it either
+        cpu.reset(&mut bus);
+        assert!(!cpu.is_jammed());
+    }
+}